@@ -1,54 +1,123 @@
 use std::num::NonZeroU8;
 
-use crate::paramset::{normalise_partition_thickness, ParamSet};
+use crate::{paramset::ParamSet, sidewall::Sidewall};
 
+const HEAD: usize = 4 + 2 * Sidewall::GENES + OPEN_GENES;
+
+/// Where the genes of the right sidewall start, after those of the left one.
+const RIGHT_START: usize = 4 + Sidewall::GENES;
+
+/// Genes for [`ParamSet::open`], least significant first.
+const OPEN_GENES: usize = 4;
+
+const OPEN_START: usize = HEAD - OPEN_GENES;
+
+/// Flat array of the genes of a [`ParamSet`].
+///
+/// Values are copied as they are, so conversions should be followed by a
+/// [`Repairer`](crate::mutation::repair::Repairer).
+///
+/// The layer RIs and catalog entries are kept separate from the rest as
+/// `[u8; HEAD + 2 * N]` can't be expressed with const generics (yet).
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct ParamArray([u8; ParamSet::LAYERS + 2]);
+pub struct ParamArray<const N: usize> {
+	head: [u8; HEAD],
+	layers: [u8; N],
+	materials: [u8; N],
+}
 
-impl Default for ParamArray {
+impl<const N: usize> Default for ParamArray<N> {
 	fn default() -> Self {
-		ParamSet::default().into()
+		ParamSet::<N>::default().into()
 	}
 }
 
-impl ParamArray {
+impl<const N: usize> ParamArray<N> {
+	/// Number of genes.
+	pub const LEN: usize = HEAD + 2 * N;
+
+	/// Panics if index is out of bounds
+	pub fn get(&self, index: usize) -> u8 {
+		if index < HEAD {
+			self.head[index]
+		} else if index < HEAD + N {
+			self.layers[index - HEAD]
+		} else {
+			self.materials[index - HEAD - N]
+		}
+	}
+
 	/// Panics if index is out of bounds
 	pub fn set(&mut self, index: usize, value: u8) {
-		self.0[index] = value;
+		if index < HEAD {
+			self.head[index] = value;
+		} else if index < HEAD + N {
+			self.layers[index - HEAD] = value;
+		} else {
+			self.materials[index - HEAD - N] = value;
+		}
+	}
+
+	/// All genes in order, for storage.
+	pub fn genes(self) -> Vec<u8> {
+		[self.head.as_slice(), &self.layers, &self.materials].concat()
+	}
+
+	/// From genes in the order of [`genes`](Self::genes), if there's the right
+	/// number of them.
+	pub fn from_genes(genes: &[u8]) -> Option<Self> {
+		if genes.len() != Self::LEN {
+			return None;
+		}
+		let mut array = Self {
+			head: [0; HEAD],
+			layers: [0; N],
+			materials: [0; N],
+		};
+		array.head.copy_from_slice(&genes[..HEAD]);
+		array.layers.copy_from_slice(&genes[HEAD..HEAD + N]);
+		array.materials.copy_from_slice(&genes[HEAD + N..]);
+		Some(array)
 	}
 }
 
 // Could probably transmute instead, with a fixed repr?
 
-impl From<ParamArray> for ParamSet {
-	fn from(geno: ParamArray) -> Self {
-		let field = geno.0;
-		let mut layers = [0; ParamSet::LAYERS];
-		layers.copy_from_slice(&field[2..]);
+impl<const N: usize> From<ParamArray<N>> for ParamSet<N> {
+	fn from(geno: ParamArray<N>) -> Self {
+		let field = geno.head;
+		let (mut left, mut right) = ([0; Sidewall::GENES], [0; Sidewall::GENES]);
+		left.copy_from_slice(&field[4..RIGHT_START]);
+		right.copy_from_slice(&field[RIGHT_START..OPEN_START]);
+		let mut open = [0; OPEN_GENES];
+		open.copy_from_slice(&field[OPEN_START..]);
 		Self {
-			layers_thickness: field[0],
-			partitions_thickness: normalise_partition_thickness(field[1]),
-			layers: layers.map(|ri| {
-				if ri == 0 {
-					None
-				} else {
-					Some(unsafe { NonZeroU8::new_unchecked(ri.max(ParamSet::MINIMUM_RI)) })
-				}
-			}),
+			width_top: field[0],
+			width_bottom: field[1],
+			sidewalls: [left, right].map(Sidewall::from_genes),
+			layers_thickness: field[2],
+			partitions_thickness: field[3],
+			layers: geno.layers.map(NonZeroU8::new),
+			materials: geno.materials,
+			open: u32::from_le_bytes(open),
 		}
 	}
 }
 
-impl From<ParamSet> for ParamArray {
-	fn from(params: ParamSet) -> Self {
-		let mut field = [0; ParamSet::LAYERS + 2];
-		field[0] = params.layers_thickness;
-		field[1] = normalise_partition_thickness(params.partitions_thickness);
-		field[2..].copy_from_slice(
-			&params
-				.layers
-				.map(|n| n.map_or(0, |n| n.get().max(ParamSet::MINIMUM_RI))),
-		);
-		Self(field)
+impl<const N: usize> From<ParamSet<N>> for ParamArray<N> {
+	fn from(params: ParamSet<N>) -> Self {
+		let mut field = [0; HEAD];
+		field[0] = params.width_top;
+		field[1] = params.width_bottom;
+		field[2] = params.layers_thickness;
+		field[3] = params.partitions_thickness;
+		field[4..RIGHT_START].copy_from_slice(&params.sidewalls[0].genes());
+		field[RIGHT_START..OPEN_START].copy_from_slice(&params.sidewalls[1].genes());
+		field[OPEN_START..].copy_from_slice(&params.open.to_le_bytes());
+		Self {
+			head: field,
+			layers: params.layers.map(|n| n.map_or(0, |n| n.get())),
+			materials: params.materials,
+		}
 	}
 }
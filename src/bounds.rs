@@ -0,0 +1,55 @@
+use std::ops::RangeInclusive;
+
+use genevo::random::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	model::units::Microns,
+	paramset::{ParamSet, DEFAULT_WIDTH_BOTTOM, DEFAULT_WIDTH_TOP},
+};
+
+/// Bounds on the outline of the lens, within which designs are searched.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineBounds {
+	/// Width of the top (entry) face, in mm.
+	pub width_top: RangeInclusive<u8>,
+
+	/// Width of the bottom (exit) face, in mm.
+	pub width_bottom: RangeInclusive<u8>,
+
+	/// Total height of the lens, in micrometres, if constrained.
+	pub height: Option<RangeInclusive<Microns>>,
+}
+
+impl Default for OutlineBounds {
+	fn default() -> Self {
+		Self {
+			width_top: DEFAULT_WIDTH_TOP..=DEFAULT_WIDTH_TOP,
+			width_bottom: DEFAULT_WIDTH_BOTTOM..=DEFAULT_WIDTH_BOTTOM,
+			height: None,
+		}
+	}
+}
+
+impl OutlineBounds {
+	/// Whether the outline of this parameter set is within bounds.
+	pub fn contains<const N: usize>(&self, params: ParamSet<N>) -> bool {
+		self.width_top.contains(&params.width_top)
+			&& self.width_bottom.contains(&params.width_bottom)
+			&& self
+				.height
+				.as_ref()
+				.is_none_or(|height| height.contains(&params.height()))
+	}
+
+	/// Pick random top and bottom widths within bounds.
+	pub fn random_widths<R>(&self, rng: &mut R) -> (u8, u8)
+	where
+		R: Rng + Sized,
+	{
+		(
+			rng.gen_range(self.width_top.clone()),
+			rng.gen_range(self.width_bottom.clone()),
+		)
+	}
+}
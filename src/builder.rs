@@ -1,14 +1,80 @@
-use genevo::{prelude::GenomeBuilder, random::Rng};
+use std::num::NonZeroU8;
 
-use crate::paramset::ParamSet;
+use genevo::{
+	prelude::GenomeBuilder,
+	random::{Rng, SliceRandom},
+};
 
-pub struct RandomBuilder;
+use crate::{
+	bounds::OutlineBounds,
+	constraints::Constraints,
+	gradient::{Curve, GradientSet},
+	paramset::{ParamSet, MAXIMUM_RI, MINIMUM_RI, PARTITION_THICKNESSES},
+	sidewall::Sidewall,
+};
 
-impl GenomeBuilder<ParamSet> for RandomBuilder {
-	fn build_genome<R>(&self, _: usize, rng: &mut R) -> ParamSet
+/// Builds the first generation from random designs.
+pub struct RandomBuilder {
+	/// Widths and sidewalls are drawn from within these.
+	pub outline: OutlineBounds,
+
+	/// Built designs are repaired to fit these.
+	pub constraints: Constraints,
+}
+
+impl<const N: usize> GenomeBuilder<ParamSet<N>> for RandomBuilder {
+	fn build_genome<R>(&self, _: usize, rng: &mut R) -> ParamSet<N>
+	where
+		R: Rng + Sized,
+	{
+		// not using ParamSet::nth() as its sequence doesn't cover deep stacks
+		let len = rng.gen_range(1..=N);
+		let mut layers = [None; N];
+		for layer in &mut layers[..len] {
+			*layer = NonZeroU8::new(rng.gen_range(MINIMUM_RI..=MAXIMUM_RI));
+		}
+		// with a catalog, liquids are picked from it and RIs follow in repair
+		let mut materials = [0; N];
+		if let Some(catalog) = &self.constraints.liquids {
+			let entries = catalog.materials.len().min(u8::MAX.into()) as u8;
+			for material in &mut materials[..len] {
+				*material = rng.gen_range(1..=entries.max(1));
+			}
+		}
+
+		let (width_top, width_bottom) = self.outline.random_widths(rng);
+		let mut params = ParamSet {
+			width_top,
+			width_bottom,
+			sidewalls: [(); 2].map(|_| Sidewall::from_genes(rng.gen())),
+			layers_thickness: rng.gen(),
+			partitions_thickness: *PARTITION_THICKNESSES.choose(rng).unwrap(),
+			layers,
+			materials,
+			open: 0,
+		};
+		self.constraints.repair(&mut params);
+		params
+	}
+}
+
+impl<const N: usize> GenomeBuilder<GradientSet<N>> for RandomBuilder {
+	fn build_genome<R>(&self, _: usize, rng: &mut R) -> GradientSet<N>
 	where
 		R: Rng + Sized,
 	{
-		ParamSet::nth(rng.gen_range(0..ParamSet::MAX_POSSIBILITIES))
+		let (width_top, width_bottom) = self.outline.random_widths(rng);
+		GradientSet {
+			width_top,
+			width_bottom,
+			sidewalls: [(); 2].map(|_| Sidewall::from_genes(rng.gen())),
+			layers_thickness: rng.gen(),
+			partitions_thickness: *PARTITION_THICKNESSES.choose(rng).unwrap(),
+			layers: rng.gen_range(1..=N) as _,
+			curve: *Curve::ALL.choose(rng).unwrap(),
+			top: rng.gen_range(MINIMUM_RI..=MAXIMUM_RI),
+			bottom: rng.gen_range(MINIMUM_RI..=MAXIMUM_RI),
+			shape: rng.gen(),
+		}
 	}
 }
@@ -0,0 +1,339 @@
+use std::{fmt::Display, fs, io, path::Path, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+	model::{layer_slabs, units::model_ri_to_real_ri, Slab},
+	paramset::{ParamSet, MAXIMUM_RI, MINIMUM_RI},
+};
+
+/// A liquid, or mixture, that layers can be filled with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Material {
+	/// Name the liquid is shown with.
+	pub name: String,
+
+	/// Refractive index.
+	pub ri: f64,
+
+	/// Density, in g/mL.
+	pub density: f64,
+
+	/// Cost, per litre.
+	pub cost: f64,
+
+	/// Absorption coefficient, per mm.
+	pub absorption: f64,
+
+	/// Group of liquids this one mixes with: liquids of different groups stay
+	/// apart when they touch. Liquids without a group mix with everything.
+	#[serde(default)]
+	pub miscibility: Option<String>,
+
+	/// Diffusion coefficient into the liquids it mixes with, in m²/s.
+	#[serde(default)]
+	pub diffusivity: Option<f64>,
+
+	/// Change of RI with temperature, per K.
+	#[serde(default)]
+	pub thermo_optic: Option<f64>,
+
+	/// Volumetric thermal expansion coefficient, per K.
+	#[serde(default)]
+	pub expansion: Option<f64>,
+}
+
+impl Material {
+	/// Whether the two liquids would mix if they touched.
+	pub fn mixes_with(&self, other: &Material) -> bool {
+		match (&self.miscibility, &other.miscibility) {
+			(Some(a), Some(b)) => a == b,
+			_ => true,
+		}
+	}
+}
+
+/// Liquids available to fill layers with.
+///
+/// Files list them as `[[material]]` tables in TOML, or as a `material` array
+/// in JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+	/// Liquids, as entries counted from 1 by designs.
+	#[serde(rename = "material")]
+	pub materials: Vec<Material>,
+}
+
+/// What the liquids of a design cost, and how much light they absorb.
+///
+/// Layers that aren't filled with a material are counted as free and clear.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fill {
+	/// Cost of the liquids, in the currency of the catalog.
+	pub cost: f64,
+
+	/// Fraction of light let through by the liquids, going straight down
+	/// through every layer.
+	pub transmittance: f64,
+}
+
+/// Why a catalog couldn't be read.
+#[derive(Debug, Error)]
+pub enum CatalogError {
+	/// The file couldn't be read.
+	#[error("reading catalog {path}: {source}")]
+	Io {
+		/// Path of the catalog.
+		path: PathBuf,
+		/// Error reading it.
+		source: io::Error,
+	},
+
+	/// The file isn't a catalog in TOML.
+	#[error("parsing catalog {path}: {source}")]
+	Toml {
+		/// Path of the catalog.
+		path: PathBuf,
+		/// Error parsing it.
+		source: toml::de::Error,
+	},
+
+	/// The file isn't a catalog in JSON.
+	#[error("parsing catalog {path}: {source}")]
+	Json {
+		/// Path of the catalog.
+		path: PathBuf,
+		/// Error parsing it.
+		source: serde_json::Error,
+	},
+}
+
+/// RI in the units of [`ParamSet`], if it's one the model handles.
+fn model_ri(ri: f64) -> Option<u8> {
+	let model = ((ri - model_ri_to_real_ri(0)) * 100.0).round();
+	(f64::from(MINIMUM_RI)..=f64::from(MAXIMUM_RI))
+		.contains(&model)
+		.then_some(model as _)
+}
+
+impl Catalog {
+	/// Reads a catalog, as TOML if the extension says so and JSON otherwise.
+	pub fn load(path: &Path) -> Result<Self, CatalogError> {
+		let contents = fs::read_to_string(path).map_err(|source| CatalogError::Io {
+			path: path.into(),
+			source,
+		})?;
+		if path.extension().is_some_and(|ext| ext == "toml") {
+			toml::from_str(&contents).map_err(|source| CatalogError::Toml {
+				path: path.into(),
+				source,
+			})
+		} else {
+			serde_json::from_str(&contents).map_err(|source| CatalogError::Json {
+				path: path.into(),
+				source,
+			})
+		}
+	}
+
+	/// Materials whose RI the model can't handle.
+	pub fn unusable(&self) -> impl Iterator<Item = &Material> {
+		self.materials.iter().filter(|m| model_ri(m.ri).is_none())
+	}
+
+	/// Material of an entry, counting from 1 as in [`ParamSet::materials`].
+	pub fn entry(&self, entry: u8) -> Option<&Material> {
+		usize::from(entry)
+			.checked_sub(1)
+			.and_then(|n| self.materials.get(n))
+	}
+
+	/// RI of an entry, in the units of [`ParamSet`].
+	pub fn entry_ri(&self, entry: u8) -> Option<u8> {
+		self.entry(entry).and_then(|m| model_ri(m.ri))
+	}
+
+	/// Entry whose RI is nearest this one, in the units of [`ParamSet`], or
+	/// the first of those as near.
+	pub fn nearest(&self, ri: u8) -> Option<u8> {
+		(1..=self.materials.len().min(u8::MAX.into()) as u8)
+			.filter_map(|entry| Some((entry, self.entry_ri(entry)?)))
+			.min_by_key(|(_, entry_ri)| entry_ri.abs_diff(ri))
+			.map(|(entry, _)| entry)
+	}
+
+	/// First material with this real RI, to the hundredth as in designs.
+	pub fn material_at(&self, ri: f64) -> Option<&Material> {
+		let ri = model_ri(ri)?;
+		self.materials.iter().find(|m| model_ri(m.ri) == Some(ri))
+	}
+
+	/// Materials of each layer of a design, from the top down: that of its
+	/// entry, or for layers without one, the first with its RI.
+	pub fn materials<const N: usize>(&self, params: ParamSet<N>) -> Vec<Option<&Material>> {
+		(0..params.len())
+			.map(|n| match params.materials[n] {
+				0 => {
+					params.layers[n].and_then(|ri| self.material_at(model_ri_to_real_ri(ri.get())))
+				}
+				entry => self.entry(entry),
+			})
+			.collect()
+	}
+
+	/// Materials of each of the [`slabs`](crate::model::slabs) of a design, with none for
+	/// partitions.
+	pub fn slab_materials<const N: usize>(
+		&self,
+		params: ParamSet<N>,
+		slabs: &[Slab],
+	) -> Vec<Option<&Material>> {
+		let mut layers = self.materials(params).into_iter();
+		slabs
+			.iter()
+			.map(|slab| {
+				if slab.partition {
+					None
+				} else {
+					layers.next().flatten()
+				}
+			})
+			.collect()
+	}
+
+	/// Cost and transmittance of the liquids of a design, for a lens this long
+	/// in mm.
+	pub fn fill<const N: usize>(&self, params: ParamSet<N>, length: f64) -> Fill {
+		let outline = params.outline();
+		let (mut cost, mut absorbance) = (0.0, 0.0);
+		for (slab, material) in layer_slabs(params, outline)
+			.into_iter()
+			.zip(self.materials(params))
+		{
+			if let Some(material) = material {
+				// cost is per litre
+				cost += slab.volume(outline, length) / 1000.0 * material.cost;
+				absorbance += slab.thickness() * material.absorption;
+			}
+		}
+		Fill {
+			cost,
+			transmittance: (-absorbance).exp(),
+		}
+	}
+
+	/// Shows a design with material names instead of RIs.
+	///
+	/// Layers that aren't filled with a material keep their RI.
+	pub fn label<const N: usize>(&self, params: ParamSet<N>) -> Labelled<'_, N> {
+		Labelled {
+			catalog: self,
+			params,
+		}
+	}
+}
+
+/// See [`Catalog::label`].
+pub struct Labelled<'c, const N: usize> {
+	catalog: &'c Catalog,
+	params: ParamSet<N>,
+}
+
+impl<const N: usize> Display for Labelled<'_, N> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let design = self.params.to_string();
+		let (shape, _) = design.split_once('|').unwrap_or((&design, ""));
+		write!(f, "{shape}| ")?;
+		let materials = self.catalog.materials(self.params);
+		for (n, ri) in self.params.layers.iter().map_while(|ri| *ri).enumerate() {
+			if !self.params.partitioned(n) {
+				write!(f, "~ ")?;
+			}
+			match materials[n] {
+				Some(material) => write!(f, "{} ", material.name)?,
+				None => write!(f, "{:.02} ", model_ri_to_real_ri(ri.get()))?,
+			}
+		}
+		write!(f, "|")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn catalog() -> Catalog {
+		let material = |name: &str, ri, cost, absorption| Material {
+			name: name.into(),
+			ri,
+			density: 1.0,
+			cost,
+			absorption,
+			miscibility: None,
+			diffusivity: None,
+			thermo_optic: None,
+			expansion: None,
+		};
+		Catalog {
+			materials: vec![
+				material("glycerol", 1.474, 4.0, 0.01),
+				material("water", 1.333, 0.0, 0.1),
+				material("oil", 1.6, 10.0, 0.0),
+			],
+		}
+	}
+
+	fn design(text: &str) -> ParamSet {
+		text.parse().unwrap()
+	}
+
+	#[test]
+	fn loads_the_example_catalog() {
+		let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("catalog.example.toml");
+		let catalog = Catalog::load(&path).unwrap();
+		assert_eq!(catalog.materials.len(), 10);
+		assert_eq!(catalog.entry(0), None);
+		assert_eq!(catalog.entry(1).unwrap().name, "water");
+		assert_eq!(catalog.entry_ri(8), Some(48));
+		assert_eq!(catalog.entry(11), None);
+		assert_eq!(catalog.unusable().count(), 0);
+	}
+
+	#[test]
+	fn finds_entries_by_ri() {
+		let catalog = catalog();
+		assert_eq!(catalog.entry_ri(3), None);
+		assert_eq!(catalog.nearest(34), Some(2));
+		assert_eq!(catalog.nearest(45), Some(1));
+		assert_eq!(
+			catalog.unusable().map(|m| &*m.name).collect::<Vec<_>>(),
+			["oil"]
+		);
+	}
+
+	#[test]
+	fn labels_designs_with_names() {
+		let catalog = catalog();
+		let params = design("top:50mm bottom:50mm layer:5.00mm part:1.00mm  | 1.47#1 1.33 1.40 |");
+		assert_eq!(
+			catalog.label(params).to_string(),
+			"top:50mm bottom:50mm layer:5.00mm part:1.00mm  | glycerol water 1.40 |"
+		);
+	}
+
+	#[test]
+	fn fills_at_cost_and_absorption() {
+		// two 50mm by 5mm layers, of 250mL each in a metre of lens
+		let params =
+			design("top:50mm bottom:50mm layer:5.00mm part:1.00mm  | 1.47#1 1.33#2 1.40 |");
+		let fill = catalog().fill(params, 1000.0);
+		assert!((fill.cost - 1.0).abs() < 1e-9, "{fill:?}");
+		assert!(
+			(fill.transmittance - (-5.0 * (0.01 + 0.1_f64)).exp()).abs() < 1e-9,
+			"{fill:?}"
+		);
+	}
+}
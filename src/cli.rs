@@ -0,0 +1,439 @@
+use std::{collections::HashMap, ffi::OsString, path::PathBuf, str::FromStr};
+
+use genevo::prelude::FitnessFunction;
+use serde::Serialize;
+use thiserror::Error;
+
+use agile_model::{
+	catalog::Fill,
+	evolution::checkpoint::CheckpointError,
+	experiment::{Experiment, ExperimentError},
+	fitness::cache::CacheError,
+	model::{raytrace, Performance, PhysicalPerformance},
+	paramset::{parse::ParseError, ParamSet},
+	record::SaveError,
+};
+
+pub mod acceptance;
+pub mod age;
+pub mod compare;
+pub mod enumerate;
+pub mod evaluate;
+pub mod loads;
+pub mod optimise;
+pub mod recipe;
+pub mod render;
+pub mod sag;
+pub mod sweep;
+pub mod temperature;
+
+pub const USAGE: &str = "\
+Usage: agile-model [--config <experiment.toml>] [--format text|json] <command> ...
+
+Commands:
+  evaluate <design>        Trace a design and show its performance
+  optimise [--seed <n>] [--output <record>] [--checkpoint <file>] [--checkpoint-every <n>]
+           [--resume <checkpoint>] [--cache <file>]
+                           Run the genetic algorithm, saving a checkpoint every
+                           n (10) generations, or carry on from one; fitness is
+                           cached, and kept in the file between runs if given
+  pareto [--seed <n>] [--output <front>]
+                           Find the trade-offs between objectives (layers
+                           genome only)
+  enumerate [--start <n>] [--count <n>]
+                           Evaluate designs in enumeration order
+  sweep <design> --param <top|bottom|layer|part|ri<n>> --from <x> --to <y> [--step <s>]
+                           Evaluate a design while varying one parameter
+  acceptance <design> [--min-exit-ratio <f>] [--max-exit-angle <deg>]
+                     [--max-travel <mm>] [--min-optical <x>]
+                           Check a design against the constraints and limits,
+                           exiting with 1 if it fails
+  render <design> [--output <file.svg>]
+                           Draw a cross-section of a design as SVG
+  compare <design a> <design b>
+                           Show the performance of two designs side by side
+  recipe <design> [--length <mm>]
+                           Show how to mix each layer from the base liquids,
+                           for a lens this long (1000mm)
+  age <design> [--days <d,d,...>] [--diffusivity <m²/s>]
+                           Show performance as liquids that touch diffuse into
+                           each other, after 0, 30 and 365 days by default;
+                           liquids not in the catalog diffuse at 1e-9 m²/s
+  loads <design>           Show how much each partition sags, and how stressed
+                           it is, under the liquid resting on it
+  sag <design> [--meniscus <mm>] [--capillary-length <mm>]
+                           Show what partitions sagging under their loads costs
+                           optically, and menisci this high at the walls
+                           between layers without partitions
+  temperature <design> [--from <°C>] [--to <°C>] [--step <K>]
+                           Show performance across operating temperatures,
+                           5 to 60°C by default, as liquids change RI and
+                           expand; designs are taken to be given at 20°C
+
+Runs with the same --seed and settings give the same results.
+Designs are written as displayed, e.g. 'layer:3.50mm part:1.00mm | 1.33 1.40 |'.
+";
+
+#[derive(Debug, Error)]
+pub enum CliError {
+	#[error("{0}\n\n{USAGE}")]
+	Usage(String),
+
+	#[error("option --{option} needs a value")]
+	MissingValue { option: String },
+
+	#[error("invalid value `{value}` for --{option}")]
+	InvalidValue { option: &'static str, value: String },
+
+	#[error("invalid design: {0}")]
+	Design(#[from] ParseError),
+
+	#[error("designs of {layers} layers can't be enumerated, only those of up to 12")]
+	Unenumerable { layers: usize },
+
+	#[error(transparent)]
+	Experiment(#[from] ExperimentError),
+
+	#[error(transparent)]
+	Checkpoint(#[from] CheckpointError),
+
+	#[error(transparent)]
+	Cache(#[from] CacheError),
+
+	#[error("--config doesn't match the settings of the run being resumed")]
+	ResumeConfig,
+
+	#[error(transparent)]
+	Save(#[from] SaveError),
+
+	#[error("writing {path}: {source}")]
+	Write {
+		path: PathBuf,
+		source: std::io::Error,
+	},
+}
+
+/// How results are written to stdout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+	/// For people.
+	#[default]
+	Text,
+
+	/// For scripts: a single JSON document.
+	Json,
+}
+
+/// Command line, split into a command, its positional arguments, and options.
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+	pub command: Option<String>,
+	pub positional: Vec<String>,
+	options: HashMap<String, String>,
+	pub help: bool,
+}
+
+impl Args {
+	/// Splits arguments; options all take a value, as `--name value` or
+	/// `--name=value`.
+	pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Self, CliError> {
+		let mut parsed = Self::default();
+		let mut args = args
+			.into_iter()
+			.map(|arg| arg.to_string_lossy().into_owned());
+		while let Some(arg) = args.next() {
+			if arg == "--help" || arg == "-h" {
+				parsed.help = true;
+			} else if let Some(option) = arg.strip_prefix("--") {
+				let (name, value) = match option.split_once('=') {
+					Some((name, value)) => (name.to_string(), value.to_string()),
+					None => {
+						let value = args.next().ok_or_else(|| CliError::MissingValue {
+							option: option.into(),
+						})?;
+						(option.to_string(), value)
+					}
+				};
+				parsed.options.insert(name, value);
+			} else if parsed.command.is_none() {
+				parsed.command = Some(arg);
+			} else {
+				parsed.positional.push(arg);
+			}
+		}
+		Ok(parsed)
+	}
+
+	pub fn option(&self, name: &str) -> Option<&str> {
+		self.options.get(name).map(String::as_str)
+	}
+
+	/// Parses an option's value, if it's given.
+	pub fn parsed<T: FromStr>(&self, name: &'static str) -> Result<Option<T>, CliError> {
+		self.option(name)
+			.map(|value| {
+				value.parse().map_err(|_| CliError::InvalidValue {
+					option: name,
+					value: value.into(),
+				})
+			})
+			.transpose()
+	}
+
+	pub fn output(&self) -> Option<PathBuf> {
+		self.option("output").map(PathBuf::from)
+	}
+
+	pub fn format(&self) -> Result<OutputFormat, CliError> {
+		match self.option("format") {
+			None | Some("text") => Ok(OutputFormat::Text),
+			Some("json") => Ok(OutputFormat::Json),
+			Some(other) => Err(CliError::InvalidValue {
+				option: "format",
+				value: other.into(),
+			}),
+		}
+	}
+
+	/// The one design given as positional arguments, which may be split up.
+	pub fn design<const N: usize>(&self) -> Result<ParamSet<N>, CliError> {
+		if self.positional.is_empty() {
+			return Err(CliError::Usage("missing design".into()));
+		}
+		Ok(self.positional.join(" ").parse()?)
+	}
+
+	/// Checks options are among those a command takes.
+	pub fn expect_options(&self, known: &[&str]) -> Result<(), CliError> {
+		for name in self.options.keys() {
+			if !["config", "format"].contains(&name.as_str()) && !known.contains(&name.as_str()) {
+				return Err(CliError::Usage(format!("unknown option --{name}")));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Prints a value as JSON, or as text.
+pub fn emit<T: Serialize>(
+	format: OutputFormat,
+	value: &T,
+	text: impl FnOnce() -> String,
+) -> Result<(), CliError> {
+	match format {
+		OutputFormat::Text => print!("{}", text()),
+		OutputFormat::Json => println!(
+			"{}",
+			serde_json::to_string_pretty(value).map_err(SaveError::from)?
+		),
+	}
+	Ok(())
+}
+
+/// A design, its performance, and how it fares against the experiment.
+#[derive(Clone, Debug, Serialize)]
+pub struct Evaluation<const N: usize> {
+	pub design: ParamSet<N>,
+	pub fitness: u64,
+	pub performance: Performance,
+	pub violations: Vec<String>,
+
+	/// Names of the liquids in each layer, if the experiment has a catalog.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub materials: Option<Vec<Option<String>>>,
+
+	/// Cost and transmittance of a metre of lens, if the experiment has a
+	/// catalog.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fill: Option<Fill>,
+
+	/// Design as shown, with the names of its materials if there's a catalog.
+	#[serde(skip)]
+	pub label: String,
+}
+
+impl<const N: usize> Evaluation<N> {
+	pub fn new(experiment: &Experiment, design: ParamSet<N>) -> Self {
+		let catalog = experiment.catalog();
+		Self {
+			design,
+			fitness: experiment.fitness_function().fitness_of(&design),
+			performance: raytrace(design, experiment.rays),
+			violations: experiment
+				.constraints()
+				.check(design)
+				.iter()
+				.map(ToString::to_string)
+				.collect(),
+			materials: catalog.as_ref().map(|catalog| {
+				catalog
+					.materials(design)
+					.into_iter()
+					.map(|material| material.map(|m| m.name.clone()))
+					.collect()
+			}),
+			fill: catalog.as_ref().map(|catalog| catalog.fill(design, 1000.0)),
+			label: match &catalog {
+				Some(catalog) => catalog.label(design).to_string(),
+				None => design.to_string(),
+			},
+		}
+	}
+
+	pub fn physical(&self) -> PhysicalPerformance {
+		self.performance.into()
+	}
+
+	/// One line of a table, see [`TABLE_HEADER`].
+	pub fn table_row(&self) -> String {
+		let perf = self.physical();
+		format!(
+			"{:>20}  {:>5.2}x  {:>5.1}%  {:>6.2}°  {:>9.1}mm  {}",
+			self.fitness,
+			perf.exit_ratio * perf.concentration,
+			perf.exit_ratio * 100.0,
+			perf.exit_angle,
+			perf.light_travel,
+			self.label
+		)
+	}
+}
+
+pub const TABLE_HEADER: &str = "             fitness   optic    exit    angle       travel  design";
+
+/// How a design performs under some condition, e.g. after ageing or at a
+/// temperature. Fitness comes from performance alone, as constraints don't
+/// change with the condition: see `evaluate` for those.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Performed {
+	pub fitness: u64,
+	pub performance: Performance,
+}
+
+impl Performed {
+	pub fn new(experiment: &Experiment, performance: Performance) -> Self {
+		Self {
+			fitness: experiment.fitness.fitness(performance.into()),
+			performance,
+		}
+	}
+
+	/// Columns of a table after the condition, see [`PERFORMED_HEADER`].
+	pub fn columns(&self) -> String {
+		let perf = PhysicalPerformance::from(self.performance);
+		format!(
+			"  {:>20}  {:>5.2}x  {:>5.1}%  {:>6.2}°  {:>9.1}mm",
+			self.fitness,
+			perf.exit_ratio * perf.concentration,
+			perf.exit_ratio * 100.0,
+			perf.exit_angle,
+			perf.light_travel,
+		)
+	}
+}
+
+pub const PERFORMED_HEADER: &str = "               fitness   optic    exit    angle       travel";
+
+/// Runs a command for designs with up to `N` layers.
+pub fn run<const N: usize>(experiment: &Experiment, args: &Args) -> Result<(), CliError> {
+	let format = args.format()?;
+	match args.command.as_deref() {
+		Some("evaluate") => evaluate::run::<N>(experiment, args, format),
+		Some("optimise" | "optimize") => optimise::run::<N>(experiment, args, format),
+		Some("pareto") => optimise::pareto::<N>(experiment, args, format),
+		Some("enumerate") => enumerate::run::<N>(experiment, args, format),
+		Some("sweep") => sweep::run::<N>(experiment, args, format),
+		Some("acceptance") => acceptance::run::<N>(experiment, args, format),
+		Some("render") => render::run::<N>(args, format),
+		Some("compare") => compare::run::<N>(experiment, args, format),
+		Some("recipe") => recipe::run::<N>(experiment, args, format),
+		Some("age") => age::run::<N>(experiment, args, format),
+		Some("loads") => loads::run::<N>(experiment, args, format),
+		Some("sag") => sag::run::<N>(experiment, args, format),
+		Some("temperature") => temperature::run::<N>(experiment, args, format),
+		Some(other) => Err(CliError::Usage(format!("unknown command `{other}`"))),
+		None => Err(CliError::Usage("missing command".into())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(line: &[&str]) -> Result<Args, CliError> {
+		Args::parse(line.iter().map(OsString::from))
+	}
+
+	#[test]
+	fn splits_commands_positionals_and_options() {
+		let args = args(&[
+			"--config",
+			"run.toml",
+			"evaluate",
+			"layer:3.50mm",
+			"part:1.00mm",
+			"--format=json",
+			"|",
+			"1.33",
+			"|",
+		])
+		.unwrap();
+		assert_eq!(args.command.as_deref(), Some("evaluate"));
+		assert_eq!(args.option("config"), Some("run.toml"));
+		assert_eq!(args.format().unwrap(), OutputFormat::Json);
+		assert!(!args.help);
+
+		let design: ParamSet = args.design().unwrap();
+		assert_eq!(design, "layer:3.50mm part:1.00mm | 1.33 |".parse().unwrap());
+	}
+
+	#[test]
+	fn parses_option_values() {
+		let args = args(&["enumerate", "--start", "5", "--count", "many"]).unwrap();
+		assert_eq!(args.parsed::<u64>("start").unwrap(), Some(5));
+		assert_eq!(args.parsed::<u64>("step").unwrap(), None);
+		assert!(matches!(
+			args.parsed::<u64>("count"),
+			Err(CliError::InvalidValue {
+				option: "count",
+				..
+			})
+		));
+		assert!(args.expect_options(&["start", "count"]).is_ok());
+		assert!(matches!(
+			args.expect_options(&["start"]),
+			Err(CliError::Usage(_))
+		));
+	}
+
+	#[test]
+	fn rejects_incomplete_command_lines() {
+		assert!(matches!(
+			args(&["optimise", "--output"]),
+			Err(CliError::MissingValue { .. })
+		));
+		assert!(matches!(
+			args(&["evaluate", "--format", "xml"]).unwrap().format(),
+			Err(CliError::InvalidValue {
+				option: "format",
+				..
+			})
+		));
+		assert!(matches!(
+			args(&["evaluate"]).unwrap().design::<10>(),
+			Err(CliError::Usage(_))
+		));
+		assert!(args(&["-h"]).unwrap().help);
+	}
+
+	#[test]
+	fn enumerates_only_what_fits() {
+		let experiment = Experiment::default();
+		let args = args(&["enumerate", "--count", "2"]).unwrap();
+		assert!(enumerate::run::<10>(&experiment, &args, OutputFormat::Json).is_ok());
+		assert!(matches!(
+			enumerate::run::<30>(&experiment, &args, OutputFormat::Json),
+			Err(CliError::Unenumerable { layers: 30 })
+		));
+	}
+}
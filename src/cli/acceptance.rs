@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+use agile_model::experiment::Experiment;
+
+use super::{emit, Args, CliError, Evaluation, OutputFormat};
+
+/// One limit a design is checked against.
+#[derive(Clone, Debug, Serialize)]
+struct Check {
+	name: &'static str,
+	value: f64,
+	limit: f64,
+	passed: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Acceptance<const N: usize> {
+	accepted: bool,
+	checks: Vec<Check>,
+	#[serde(flatten)]
+	evaluation: Evaluation<N>,
+}
+
+/// `acceptance <design> [--min-exit-ratio <f>] [--max-exit-angle <deg>]
+/// [--max-travel <mm>] [--min-optical <x>]`
+///
+/// Exits with status 1 if the design isn't accepted.
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&[
+		"min-exit-ratio",
+		"max-exit-angle",
+		"max-travel",
+		"min-optical",
+	])?;
+	let evaluation = Evaluation::<N>::new(experiment, args.design()?);
+	let perf = evaluation.physical();
+
+	let mut checks = Vec::new();
+	let limits = [
+		("min-exit-ratio", "exit ratio", perf.exit_ratio, true),
+		("max-exit-angle", "exit angle", perf.exit_angle, false),
+		("max-travel", "light travel", perf.light_travel, false),
+		(
+			"min-optical",
+			"optical concentration",
+			perf.exit_ratio * perf.concentration,
+			true,
+		),
+	];
+	for (option, name, value, minimum) in limits {
+		if let Some(limit) = args.parsed::<f64>(option)? {
+			checks.push(Check {
+				name,
+				value,
+				limit,
+				passed: if minimum {
+					value >= limit
+				} else {
+					value <= limit
+				},
+			});
+		}
+	}
+
+	let accepted = evaluation.violations.is_empty() && checks.iter().all(|c| c.passed);
+	let acceptance = Acceptance {
+		accepted,
+		checks,
+		evaluation,
+	};
+
+	emit(format, &acceptance, || {
+		let mut text = format!("{}\n", acceptance.evaluation.label);
+		for check in &acceptance.checks {
+			text.push_str(&format!(
+				"{} {}: {:.3} (limit {:.3})\n",
+				if check.passed { "pass" } else { "FAIL" },
+				check.name,
+				check.value,
+				check.limit
+			));
+		}
+		for violation in &acceptance.evaluation.violations {
+			text.push_str(&format!("FAIL {violation}\n"));
+		}
+		text.push_str(if accepted { "accepted\n" } else { "rejected\n" });
+		text
+	})?;
+
+	if !accepted {
+		std::process::exit(1);
+	}
+	Ok(())
+}
@@ -0,0 +1,88 @@
+use serde::Serialize;
+
+use agile_model::{
+	diffusion::{RiProfile, DEFAULT_DIFFUSIVITY},
+	experiment::Experiment,
+	model::raytrace_profile,
+};
+
+use super::{emit, Args, CliError, OutputFormat, Performed, PERFORMED_HEADER};
+
+/// Days after filling that are shown, by default.
+const DEFAULT_DAYS: [f64; 3] = [0.0, 30.0, 365.0];
+
+/// A design as it performs some time after it's filled.
+#[derive(Clone, Debug, Serialize)]
+struct Aged {
+	days: f64,
+	#[serde(flatten)]
+	performed: Performed,
+
+	/// Largest change in RI anywhere in the stack since filling.
+	max_ri_change: f64,
+}
+
+/// `age <design> [--days <d,d,...>] [--diffusivity <m²/s>]`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&["days", "diffusivity"])?;
+	let params = args.design::<N>()?;
+	let mut days = match args.option("days") {
+		Some(list) => list
+			.split(',')
+			.map(|day| {
+				day.trim()
+					.parse::<f64>()
+					.ok()
+					.filter(|d| *d >= 0.0 && d.is_finite())
+					.ok_or_else(|| CliError::InvalidValue {
+						option: "days",
+						value: list.into(),
+					})
+			})
+			.collect::<Result<Vec<_>, _>>()?,
+		None => DEFAULT_DAYS.to_vec(),
+	};
+	days.sort_by(f64::total_cmp);
+	let diffusivity: f64 = args.parsed("diffusivity")?.unwrap_or(DEFAULT_DIFFUSIVITY);
+	if !(diffusivity >= 0.0 && diffusivity.is_finite()) {
+		return Err(CliError::InvalidValue {
+			option: "diffusivity",
+			value: diffusivity.to_string(),
+		});
+	}
+
+	let catalog = experiment.catalog();
+	let filled = RiProfile::new(params, catalog.as_ref(), diffusivity);
+	let mut profile = filled.clone();
+	let mut elapsed = 0.0;
+	let aged: Vec<Aged> = days
+		.into_iter()
+		.map(|day| {
+			profile.diffuse(day - elapsed);
+			elapsed = day;
+			let performance = raytrace_profile(params, &profile.slabs(), experiment.rays);
+			Aged {
+				days: day,
+				performed: Performed::new(experiment, performance),
+				max_ri_change: profile.max_change(&filled),
+			}
+		})
+		.collect();
+
+	emit(format, &aged, || {
+		let mut text = format!("{params}\n days{PERFORMED_HEADER}   max ΔRI\n");
+		for aged in &aged {
+			text.push_str(&format!(
+				"{:>5}{}  {:>8.4}\n",
+				aged.days,
+				aged.performed.columns(),
+				aged.max_ri_change,
+			));
+		}
+		text
+	})
+}
@@ -0,0 +1,110 @@
+use serde::Serialize;
+
+use agile_model::{experiment::Experiment, model::PhysicalPerformance, paramset::ParamSet};
+
+use super::{emit, Args, CliError, Evaluation, OutputFormat};
+
+/// How much design b differs from design a, in physical units.
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Difference {
+	fitness: i128,
+	optical_concentration: f64,
+	exit_ratio: f64,
+	exit_angle: f64,
+	light_travel: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct Comparison<const N: usize> {
+	a: Evaluation<N>,
+	b: Evaluation<N>,
+	difference: Difference,
+}
+
+fn optical(perf: PhysicalPerformance) -> f64 {
+	perf.exit_ratio * perf.concentration
+}
+
+/// `compare <design a> <design b>`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&[])?;
+	let [a, b] = args.positional.as_slice() else {
+		return Err(CliError::Usage(
+			"compare needs exactly two designs, each quoted".into(),
+		));
+	};
+
+	let (a, b) = (
+		Evaluation::new(experiment, a.parse::<ParamSet<N>>()?),
+		Evaluation::new(experiment, b.parse::<ParamSet<N>>()?),
+	);
+	let (pa, pb) = (a.physical(), b.physical());
+	let difference = Difference {
+		fitness: i128::from(b.fitness) - i128::from(a.fitness),
+		optical_concentration: optical(pb) - optical(pa),
+		exit_ratio: pb.exit_ratio - pa.exit_ratio,
+		exit_angle: pb.exit_angle - pa.exit_angle,
+		light_travel: pb.light_travel - pa.light_travel,
+	};
+	let comparison = Comparison { a, b, difference };
+
+	emit(format, &comparison, || {
+		let Comparison {
+			a,
+			b,
+			difference: d,
+		} = &comparison;
+		let mut text = format!("a: {}\nb: {}\n\n", a.label, b.label);
+		text.push_str(&format!(
+			"{:<22} {:>20} {:>20} {:>21}\n",
+			"", "a", "b", "b - a"
+		));
+		text.push_str(&format!(
+			"{:<22} {:>20} {:>20} {:>+21}\n",
+			"fitness", a.fitness, b.fitness, d.fitness
+		));
+		let rows = [
+			(
+				"optical concentration",
+				optical(pa),
+				optical(pb),
+				d.optical_concentration,
+				"x",
+			),
+			(
+				"exit ratio",
+				pa.exit_ratio * 100.0,
+				pb.exit_ratio * 100.0,
+				d.exit_ratio * 100.0,
+				"%",
+			),
+			(
+				"exit angle",
+				pa.exit_angle,
+				pb.exit_angle,
+				d.exit_angle,
+				"°",
+			),
+			(
+				"light travel",
+				pa.light_travel,
+				pb.light_travel,
+				d.light_travel,
+				"mm",
+			),
+		];
+		for (name, a, b, d, unit) in rows {
+			text.push_str(&format!(
+				"{name:<22} {:>20} {:>20} {:>21}\n",
+				format!("{a:.2}{unit}"),
+				format!("{b:.2}{unit}"),
+				format!("{d:+.2}{unit}"),
+			));
+		}
+		text
+	})
+}
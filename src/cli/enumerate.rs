@@ -0,0 +1,28 @@
+use agile_model::{experiment::Experiment, paramset::ParamSet};
+
+use super::{emit, Args, CliError, Evaluation, OutputFormat, TABLE_HEADER};
+
+/// `enumerate [--start <n>] [--count <n>]`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&["start", "count"])?;
+	let start: u64 = args.parsed("start")?.unwrap_or(0);
+	let count: u64 = args.parsed("count")?.unwrap_or(10);
+
+	let last = ParamSet::<N>::MAX_POSSIBILITIES.ok_or(CliError::Unenumerable { layers: N })?;
+	let end = start.saturating_add(count).min(last.saturating_add(1));
+	let evaluations: Vec<Evaluation<N>> = (start..end)
+		.map(|n| Evaluation::new(experiment, ParamSet::<N>::nth(n)))
+		.collect();
+
+	emit(format, &evaluations, || {
+		let mut text = format!("{:>12}{TABLE_HEADER}\n", "n");
+		for (n, evaluation) in (start..).zip(&evaluations) {
+			text.push_str(&format!("{n:>12}{}\n", evaluation.table_row()));
+		}
+		text
+	})
+}
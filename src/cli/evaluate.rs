@@ -0,0 +1,43 @@
+use agile_model::experiment::Experiment;
+
+use super::{emit, Args, CliError, Evaluation, OutputFormat};
+
+/// `evaluate <design>`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&[])?;
+	let evaluation = Evaluation::<N>::new(experiment, args.design()?);
+	emit(format, &evaluation, || {
+		let perf = evaluation.physical();
+		let mut text = format!(
+			"{}\nfitness: {}\noptical concentration: {:.3}x\nexit ratio: {:.2}%\nexit angle: {:.2}°\nlight travel: {:.1}mm\n",
+			evaluation.label,
+			evaluation.fitness,
+			perf.exit_ratio * perf.concentration,
+			perf.exit_ratio * 100.0,
+			perf.exit_angle,
+			perf.light_travel,
+		);
+		if let Some(materials) = &evaluation.materials {
+			let names: Vec<&str> = materials
+				.iter()
+				.map(|name| name.as_deref().unwrap_or("?"))
+				.collect();
+			text.push_str(&format!("materials: {}\n", names.join(" ")));
+		}
+		if let Some(fill) = &evaluation.fill {
+			text.push_str(&format!(
+				"liquid cost: {:.2} per metre\nliquid transmittance: {:.2}%\n",
+				fill.cost,
+				fill.transmittance * 100.0
+			));
+		}
+		for violation in &evaluation.violations {
+			text.push_str(&format!("violates: {violation}\n"));
+		}
+		text
+	})
+}
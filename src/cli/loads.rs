@@ -0,0 +1,39 @@
+use agile_model::{experiment::Experiment, mechanics::partition_loads};
+
+use super::{emit, Args, CliError, OutputFormat};
+
+/// `loads <design>`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&[])?;
+	let params = args.design::<N>()?;
+	let constraints = experiment.constraints();
+	let loads = partition_loads(params, constraints.liquids.as_ref());
+
+	emit(format, &loads, || {
+		let over = |value: f64, max: Option<f64>| {
+			if max.is_some_and(|max| value > max) {
+				" over"
+			} else {
+				""
+			}
+		};
+		let mut text = format!("{params}\nabove layer     span   pressure       sag      stress\n");
+		for load in &loads {
+			text.push_str(&format!(
+				"{:>11}  {:>5.1}mm  {:>7.1}Pa  {:>6.3}mm{:<5}  {:>6.3}MPa{}\n",
+				load.layer + 1,
+				load.span,
+				load.pressure,
+				load.deflection,
+				over(load.deflection, constraints.max_deflection),
+				load.stress,
+				over(load.stress, constraints.max_stress),
+			));
+		}
+		text
+	})
+}
@@ -0,0 +1,280 @@
+use std::path::PathBuf;
+
+use genevo::random::{random_seed, Prng, SeedableRng};
+
+use agile_model::{
+	builder::RandomBuilder,
+	evolution::{checkpoint::Checkpoint, Encoding, Evolution},
+	experiment::{Experiment, Genome},
+	gradient::GradientSet,
+	mutation::repair::{Repaired, Repairer},
+	paramset::ParamSet,
+	pareto::Nsga2,
+	record::{Format, FrontRecord},
+};
+
+use super::{emit, Args, CliError, OutputFormat};
+
+/// Prints progress, keeping stdout for the result when it's JSON.
+fn progress(format: OutputFormat, line: impl std::fmt::Display) {
+	match format {
+		OutputFormat::Text => println!("{line}"),
+		OutputFormat::Json => eprintln!("{line}"),
+	}
+}
+
+/// Seed given with `--seed`, or a random one, within i64 so it can be written
+/// in TOML.
+fn seed(args: &Args) -> Result<u64, CliError> {
+	match args.parsed::<u64>("seed")? {
+		Some(seed) if seed > i64::MAX as u64 => Err(CliError::InvalidValue {
+			option: "seed",
+			value: seed.to_string(),
+		}),
+		Some(seed) => Ok(seed),
+		None => {
+			let [a, b, c, d, e, f, g, h, ..] = random_seed();
+			Ok(u64::from_le_bytes([a, b, c, d, e, f, g, h]) >> 1)
+		}
+	}
+}
+
+/// `optimise [--seed <n>] [--output <record>] [--cache <file>] [--checkpoint <file>] [--checkpoint-every <n>]
+/// [--resume <file>]`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	match experiment.optimiser.genome {
+		Genome::Layers => drive::<N, ParamSet<N>>(experiment, args, format),
+		Genome::Gradient => drive::<N, GradientSet<N>>(experiment, args, format),
+	}
+}
+
+/// Runs the genetic algorithm on genomes of the given encoding.
+fn drive<const N: usize, G: Encoding<N>>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&[
+		"seed",
+		"output",
+		"checkpoint",
+		"checkpoint-every",
+		"resume",
+		"cache",
+	])?;
+	let cache = args.option("cache").map(PathBuf::from);
+	let resume = args.option("resume").map(PathBuf::from);
+	let checkpoint = args
+		.option("checkpoint")
+		.map(PathBuf::from)
+		.or_else(|| resume.clone());
+	let every: u64 = args.parsed("checkpoint-every")?.unwrap_or(10);
+	if every == 0 {
+		return Err(CliError::InvalidValue {
+			option: "checkpoint-every",
+			value: every.to_string(),
+		});
+	}
+
+	let catalog = experiment.catalog();
+	let constraints = experiment.constraints();
+	let mut evolution = match &resume {
+		Some(path) => {
+			if args.option("seed").is_some() {
+				return Err(CliError::Usage(
+					"a resumed run keeps the seed it was started with".into(),
+				));
+			}
+			let evolution = Evolution::<N, G>::resume(Checkpoint::load(path)?);
+			progress(
+				format,
+				format_args!("Resuming at generation {:04}", evolution.generation()),
+			);
+			evolution
+		}
+		None => Evolution::<N, G>::new(experiment, seed(args)?),
+	};
+	progress(format, format_args!("Seed: {}", evolution.record().seed));
+	if let Some(path) = &cache {
+		evolution.cache().load(path)?;
+	}
+
+	loop {
+		let step = match evolution.step() {
+			Ok(step) => step,
+			Err(error) => {
+				progress(format, error);
+				break;
+			}
+		};
+
+		let best = &step.state.best_solution;
+		// with a catalog, designs are shown with the names of their liquids
+		let shown = match &catalog {
+			Some(catalog) => catalog
+				.label(best.solution.genome.design(&constraints))
+				.to_string(),
+			None => best.solution.genome.to_string(),
+		};
+		progress(
+			format,
+			format_args!(
+			"Step: generation: {:04}, average_fitness: {}, best fitness: {}, processing_time: {}\n{}",
+			step.generation,
+			step.state.evaluated_population.average_fitness(),
+			best.solution.fitness,
+			step.state.processing_time,
+			shown,
+		),
+		);
+
+		if let Some(reason) = step.stop {
+			if let Some(best) = evolution.record().best {
+				progress(format, format_args!(
+					"{reason}\nFinal result: generation: {}, best solution with fitness {} found in generation {}\n{}",
+					step.generation, best.fitness, best.generation, best.design,
+				));
+				if let Some(catalog) = &catalog {
+					progress(format, catalog.label(best.design));
+				}
+			}
+			break;
+		}
+
+		if let Some(path) = &checkpoint {
+			if step.generation % every == 0 {
+				if let Some(checkpoint) = evolution.checkpoint() {
+					checkpoint.save(path)?;
+				}
+				if let Some(path) = &cache {
+					evolution.cache().save(path)?;
+				}
+			}
+		}
+	}
+
+	if let Some(path) = &cache {
+		evolution.cache().save(path)?;
+	}
+	let record = evolution.into_record();
+	let repairs = record.repairs;
+	progress(
+		format,
+		format_args!(
+			"Repairs: {} of {} genomes ({:.1}%); compacted {}, RIs clamped {}, partitions snapped {}, constrained {}",
+			repairs.repaired,
+			repairs.checked,
+			repairs.repaired as f64 * 100.0 / repairs.checked.max(1) as f64,
+			repairs.compacted,
+			repairs.ri_clamped,
+			repairs.thickness_snapped,
+			repairs.constrained
+		),
+	);
+	progress(
+		format,
+		format_args!(
+			"Fitness cache: {} hits, {} misses ({:.1}% hit rate), {} designs",
+			record.cache.hits,
+			record.cache.misses,
+			record.cache.hit_rate() * 100.0,
+			record.cache.entries
+		),
+	);
+
+	if let Some(path) = args.output() {
+		record.save(&path)?;
+	}
+	if format == OutputFormat::Json {
+		emit(format, &record, String::new)?;
+	}
+	Ok(())
+}
+
+/// `pareto [--seed <n>] [--output <front>]`
+///
+/// Finds the trade-offs between objectives, instead of a single best design.
+pub fn pareto<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&["seed", "output"])?;
+	if experiment.optimiser.genome != Genome::Layers {
+		return Err(CliError::Usage(
+			"pareto only runs on the layers genome".into(),
+		));
+	}
+	let seed = seed(args)?;
+	progress(format, format_args!("Seed: {seed}"));
+	let repairer = Repairer::new(experiment.constraints());
+	let nsga = Nsga2 {
+		population_size: experiment.optimiser.population_size,
+		generations: experiment.optimiser.generation_limit,
+		builder: RandomBuilder {
+			outline: experiment.outline(),
+			constraints: experiment.constraints(),
+		},
+		crossover: Repaired {
+			inner: experiment.optimiser.crossover,
+			repairer: repairer.clone(),
+		},
+		mutation: Repaired {
+			inner: experiment.optimiser.mutation,
+			repairer,
+		},
+		outline: experiment.outline(),
+		constraints: experiment.constraints(),
+		rays: experiment.rays,
+	};
+
+	let front = nsga.run::<N, _>(&mut Prng::seed_from_u64(seed), |generation, front| {
+		progress(
+			format,
+			format_args!(
+				"Step: generation: {generation:04}, front: {} designs",
+				front.designs.len()
+			),
+		);
+	});
+
+	let record = FrontRecord {
+		config: experiment.clone(),
+		seed,
+		front,
+	};
+	if let Some(path) = args.output() {
+		let contents = Format::from_path(&path).serialise(&record)?;
+		std::fs::write(&path, contents).map_err(|source| CliError::Write { path, source })?;
+	}
+	let catalog = experiment.catalog();
+	emit(format, &record, || {
+		record.front.table(catalog.as_ref()).to_string()
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::ffi::OsString;
+
+	use super::*;
+
+	fn seed_of(value: &str) -> Result<u64, CliError> {
+		seed(&Args::parse(["optimise", "--seed", value].map(OsString::from)).unwrap())
+	}
+
+	#[test]
+	fn takes_only_seeds_toml_can_hold() {
+		assert_eq!(seed_of("9223372036854775807").unwrap(), i64::MAX as u64);
+		assert!(matches!(
+			seed_of("9223372036854775808"),
+			Err(CliError::InvalidValue { option: "seed", .. })
+		));
+		let random = seed(&Args::parse(["optimise"].map(OsString::from)).unwrap()).unwrap();
+		assert!(random <= i64::MAX as u64);
+	}
+}
@@ -0,0 +1,30 @@
+use agile_model::experiment::Experiment;
+
+use super::{emit, Args, CliError, OutputFormat};
+
+/// Length of the lens, along its extrusion, in mm.
+const DEFAULT_LENGTH: f64 = 1000.0;
+
+/// `recipe <design> [--length <mm>]`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&["length"])?;
+	let Some(mixing) = &experiment.mixing else {
+		return Err(CliError::Usage(
+			"recipes need base liquids in the `[mixing]` section of the experiment".into(),
+		));
+	};
+	let length: f64 = args.parsed("length")?.unwrap_or(DEFAULT_LENGTH);
+	if !(length > 0.0 && length.is_finite()) {
+		return Err(CliError::InvalidValue {
+			option: "length",
+			value: length.to_string(),
+		});
+	}
+
+	let recipe = mixing.recipe(args.design::<N>()?, length);
+	emit(format, &recipe, || recipe.to_string())
+}
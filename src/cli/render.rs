@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use agile_model::{
+	model::{
+		geo::Outline,
+		slabs,
+		units::{model_ri_to_real_ri, Microns},
+	},
+	paramset::{MAXIMUM_RI, MINIMUM_RI},
+};
+
+use super::{emit, Args, CliError, OutputFormat};
+
+/// Pixels per mm.
+const SCALE: f64 = 4.0;
+
+/// Space around the drawing, in pixels.
+const MARGIN: f64 = 10.0;
+
+/// Points taken along each wall of a slab, so curved walls look curved.
+const WALL_SAMPLES: usize = 8;
+
+#[derive(Serialize)]
+struct Rendered {
+	svg: String,
+}
+
+/// Grey for an RI: darker is denser.
+fn shade(ri: f64) -> u8 {
+	let (min, max) = (
+		model_ri_to_real_ri(MINIMUM_RI),
+		model_ri_to_real_ri(MAXIMUM_RI),
+	);
+	let t = (ri.clamp(min, max) - min) / (max - min);
+	(230.0 - 130.0 * t) as u8
+}
+
+/// Outline of a horizontal slice, clockwise from its top left, in pixels.
+fn slice_points(outline: Outline, top: Microns, bottom: Microns) -> Vec<(f64, f64)> {
+	let to_px = |x: f64, y: f64| {
+		(
+			MARGIN + x / 1000.0 * SCALE,
+			MARGIN + (outline.height as f64 - y) / 1000.0 * SCALE,
+		)
+	};
+	let heights: Vec<f64> = (0..=WALL_SAMPLES)
+		.map(|n| top as f64 - (top - bottom) as f64 * n as f64 / WALL_SAMPLES as f64)
+		.collect();
+
+	let mut points: Vec<_> = heights
+		.iter()
+		.map(|&y| to_px(outline.walls_at(y).1, y))
+		.collect();
+	points.extend(
+		heights
+			.iter()
+			.rev()
+			.map(|&y| to_px(outline.walls_at(y).0, y)),
+	);
+	points
+}
+
+fn polygon(points: &[(f64, f64)]) -> String {
+	points
+		.iter()
+		.map(|(x, y)| format!("{x:.2},{y:.2}"))
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// `render <design> [--output <file.svg>]`
+pub fn run<const N: usize>(args: &Args, format: OutputFormat) -> Result<(), CliError> {
+	args.expect_options(&["output"])?;
+	let params = args.design::<N>()?;
+	let outline = params.outline();
+
+	let width = outline.width_top.max(outline.width_bottom) as f64 / 1000.0 * SCALE;
+	let height = outline.height as f64 / 1000.0 * SCALE;
+	let mut svg = format!(
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.2} {h:.2}\">\n",
+		w = width + 2.0 * MARGIN,
+		h = height + 2.0 * MARGIN,
+	);
+	let _ = writeln!(svg, "<title>{params}</title>");
+
+	for slab in slabs(params, outline) {
+		let points = slice_points(outline, slab.top, slab.bottom);
+		let grey = if slab.partition { 60 } else { shade(slab.ri) };
+		let _ = writeln!(
+			svg,
+			"<polygon points=\"{}\" fill=\"rgb({grey},{grey},{grey})\"/>",
+			polygon(&points)
+		);
+
+		let thickness = (slab.top - slab.bottom) as f64 / 1000.0 * SCALE;
+		if !slab.partition && thickness >= 8.0 {
+			let y = MARGIN
+				+ (outline.height as f64 - (slab.top + slab.bottom) as f64 / 2.0) / 1000.0 * SCALE;
+			let _ = writeln!(
+				svg,
+				"<text x=\"{:.2}\" y=\"{y:.2}\" font-size=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"middle\">{:.2}</text>",
+				MARGIN + width / 2.0,
+				thickness.min(12.0) * 0.8,
+				slab.ri,
+			);
+		}
+	}
+
+	let _ = writeln!(
+		svg,
+		"<polygon points=\"{}\" fill=\"none\" stroke=\"black\"/>",
+		polygon(&slice_points(outline, outline.height, 0))
+	);
+	svg.push_str("</svg>\n");
+
+	match args.output() {
+		Some(path) => {
+			std::fs::write(&path, &svg).map_err(|source| CliError::Write { path, source })?
+		}
+		None => emit(format, &Rendered { svg: svg.clone() }, || svg.clone())?,
+	}
+	Ok(())
+}
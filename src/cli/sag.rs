@@ -0,0 +1,103 @@
+use serde::Serialize;
+
+use agile_model::{
+	experiment::Experiment,
+	mechanics::{partition_loads, sagged_slabs},
+	model::{curve::Curve, curve_open_interfaces, raytrace_profile, slabs},
+};
+
+use super::{emit, Args, CliError, OutputFormat, Performed, PERFORMED_HEADER};
+
+/// Length over which menisci fall away from the walls, in mm: the capillary
+/// length of water against air.
+const DEFAULT_CAPILLARY_LENGTH: f64 = 2.7;
+
+/// A design traced with its interfaces shaped one way.
+#[derive(Clone, Debug, Serialize)]
+struct Shaped {
+	interfaces: &'static str,
+	#[serde(flatten)]
+	performed: Performed,
+
+	/// Optical concentration lost against flat interfaces, from 0 to 1.
+	cost: f64,
+}
+
+/// `sag <design> [--meniscus <mm>] [--capillary-length <mm>]`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&["meniscus", "capillary-length"])?;
+	let params = args.design::<N>()?;
+	let meniscus: Option<f64> = args.parsed("meniscus")?;
+	if let Some(rise) = meniscus.filter(|rise| !rise.is_finite()) {
+		return Err(CliError::InvalidValue {
+			option: "meniscus",
+			value: rise.to_string(),
+		});
+	}
+	let length: f64 = args
+		.parsed("capillary-length")?
+		.unwrap_or(DEFAULT_CAPILLARY_LENGTH);
+	if !(length > 0.0 && length.is_finite()) {
+		return Err(CliError::InvalidValue {
+			option: "capillary-length",
+			value: length.to_string(),
+		});
+	}
+
+	let catalog = experiment.catalog();
+	let mut profiles = vec![
+		("flat", slabs(params, params.outline())),
+		("sagged", sagged_slabs(params, catalog.as_ref())),
+	];
+	if let Some(rise) = meniscus {
+		let mut slabs = sagged_slabs(params, catalog.as_ref());
+		// mm to µm
+		curve_open_interfaces(
+			&mut slabs,
+			Curve::Meniscus {
+				rise: rise * 1000.0,
+				length: length * 1000.0,
+			},
+		);
+		profiles.push(("menisci", slabs));
+	}
+
+	let flat = raytrace_profile(params, &profiles[0].1, experiment.rays).optical_concentration();
+	let shaped: Vec<Shaped> = profiles
+		.into_iter()
+		.map(|(interfaces, slabs)| {
+			let performance = raytrace_profile(params, &slabs, experiment.rays);
+			Shaped {
+				interfaces,
+				performed: Performed::new(experiment, performance),
+				cost: if flat == 0 {
+					0.0
+				} else {
+					1.0 - performance.optical_concentration() as f64 / flat as f64
+				},
+			}
+		})
+		.collect();
+
+	emit(format, &shaped, || {
+		let sag = partition_loads(params, catalog.as_ref())
+			.iter()
+			.map(|load| load.deflection)
+			.fold(0.0, f64::max);
+		let mut text =
+			format!("{params}\nlargest sag: {sag:.3}mm\ninterfaces{PERFORMED_HEADER}     cost\n");
+		for shaped in &shaped {
+			text.push_str(&format!(
+				"{:<10}{}  {:>6.2}%\n",
+				shaped.interfaces,
+				shaped.performed.columns(),
+				shaped.cost * 100.0,
+			));
+		}
+		text
+	})
+}
@@ -0,0 +1,135 @@
+use serde::Serialize;
+
+use agile_model::{
+	design::Design,
+	experiment::Experiment,
+	paramset::{parse::ParseError, ParamSet},
+};
+
+use super::{emit, Args, CliError, Evaluation, OutputFormat, TABLE_HEADER};
+
+/// A parameter of a design that can be swept, in physical units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Param {
+	Top,
+	Bottom,
+	Layer,
+	Part,
+
+	/// RI of a layer, counted from 1 at the top.
+	Ri(usize),
+}
+
+impl Param {
+	fn parse(name: &str) -> Option<Self> {
+		match name {
+			"top" => Some(Self::Top),
+			"bottom" => Some(Self::Bottom),
+			"layer" => Some(Self::Layer),
+			"part" => Some(Self::Part),
+			_ => name
+				.strip_prefix("ri")
+				.and_then(|n| n.parse().ok())
+				.filter(|n| *n > 0)
+				.map(Self::Ri),
+		}
+	}
+
+	/// Smallest change that makes a difference.
+	fn default_step(self) -> f64 {
+		match self {
+			Self::Top | Self::Bottom => 1.0,
+			Self::Layer | Self::Part => 0.1,
+			Self::Ri(_) => 0.01,
+		}
+	}
+
+	fn set(self, design: &mut Design, value: f64) -> bool {
+		match self {
+			Self::Top => design.width_top = value,
+			Self::Bottom => design.width_bottom = value,
+			Self::Layer => design.layer_thickness = value,
+			Self::Part => design.partition_thickness = value,
+			Self::Ri(n) => match design.layers.get_mut(n - 1) {
+				Some(ri) => {
+					*ri = value;
+					// the layer is no longer filled with its catalog entry
+					if let Some(material) = design.materials.get_mut(n - 1) {
+						*material = 0;
+					}
+				}
+				None => return false,
+			},
+		}
+		true
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SweepPoint<const N: usize> {
+	value: f64,
+	#[serde(flatten)]
+	evaluation: Evaluation<N>,
+}
+
+/// `sweep <design> --param <name> --from <x> --to <y> [--step <s>]`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&["param", "from", "to", "step"])?;
+	let base = Design::from(args.design::<N>()?);
+
+	let name = args
+		.option("param")
+		.ok_or_else(|| CliError::Usage("sweep needs --param".into()))?;
+	let param = Param::parse(name).ok_or_else(|| CliError::InvalidValue {
+		option: "param",
+		value: name.into(),
+	})?;
+	let from: f64 = args
+		.parsed("from")?
+		.ok_or_else(|| CliError::Usage("sweep needs --from".into()))?;
+	let to: f64 = args
+		.parsed("to")?
+		.ok_or_else(|| CliError::Usage("sweep needs --to".into()))?;
+	let step: f64 = args.parsed("step")?.unwrap_or(param.default_step());
+	if step <= 0.0 || !step.is_finite() {
+		return Err(CliError::InvalidValue {
+			option: "step",
+			value: step.to_string(),
+		});
+	}
+
+	let direction = if to < from { -1.0 } else { 1.0 };
+	let steps = ((to - from).abs() / step + 1e-9).floor() as usize;
+	let mut points = Vec::with_capacity(steps + 1);
+	for n in 0..=steps {
+		let value = from + direction * step * n as f64;
+		let mut design = base.clone();
+		if !param.set(&mut design, value) {
+			return Err(CliError::Usage(format!(
+				"--param {name} is beyond the design's {} layers",
+				base.layers.len()
+			)));
+		}
+		let params = ParamSet::<N>::try_from(design).map_err(ParseError::from)?;
+		points.push(SweepPoint {
+			value,
+			evaluation: Evaluation::new(experiment, params),
+		});
+	}
+
+	emit(format, &points, || {
+		let mut text = format!("{name:>8}{TABLE_HEADER}\n");
+		for point in &points {
+			text.push_str(&format!(
+				"{:>8.2}{}\n",
+				point.value,
+				point.evaluation.table_row()
+			));
+		}
+		text
+	})
+}
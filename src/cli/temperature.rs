@@ -0,0 +1,74 @@
+use serde::Serialize;
+
+use agile_model::{experiment::Experiment, thermal::operating_range};
+
+use super::{emit, Args, CliError, OutputFormat, Performed, PERFORMED_HEADER};
+
+/// Operating range that's shown by default, in °C.
+const DEFAULT_RANGE: (f64, f64, f64) = (5.0, 60.0, 5.0);
+
+/// A design as it performs at one temperature.
+#[derive(Clone, Debug, Serialize)]
+struct Operating {
+	temperature: f64,
+	#[serde(flatten)]
+	performed: Performed,
+
+	/// Height of the stack, in mm.
+	height: f64,
+}
+
+/// `temperature <design> [--from <°C>] [--to <°C>] [--step <K>]`
+pub fn run<const N: usize>(
+	experiment: &Experiment,
+	args: &Args,
+	format: OutputFormat,
+) -> Result<(), CliError> {
+	args.expect_options(&["from", "to", "step"])?;
+	let params = args.design::<N>()?;
+	let (from, to, step) = DEFAULT_RANGE;
+	let from: f64 = args.parsed("from")?.unwrap_or(from);
+	let to: f64 = args.parsed("to")?.unwrap_or(to);
+	let step: f64 = args.parsed("step")?.unwrap_or(step);
+	for (option, value) in [("from", from), ("to", to)] {
+		if !value.is_finite() {
+			return Err(CliError::InvalidValue {
+				option,
+				value: value.to_string(),
+			});
+		}
+	}
+	if step <= 0.0 || !step.is_finite() {
+		return Err(CliError::InvalidValue {
+			option: "step",
+			value: step.to_string(),
+		});
+	}
+
+	let direction = if to < from { -1.0 } else { 1.0 };
+	let steps = ((to - from).abs() / step + 1e-9).floor() as usize;
+	let temperatures = (0..=steps).map(|n| from + direction * step * n as f64);
+	let catalog = experiment.catalog();
+	let points: Vec<Operating> =
+		operating_range(params, catalog.as_ref(), temperatures, experiment.rays)
+			.into_iter()
+			.map(|point| Operating {
+				temperature: point.temperature,
+				performed: Performed::new(experiment, point.performance),
+				height: point.height,
+			})
+			.collect();
+
+	emit(format, &points, || {
+		let mut text = format!("{params}\n   °C{PERFORMED_HEADER}     height\n");
+		for point in &points {
+			text.push_str(&format!(
+				"{:>5.1}{}  {:>7.2}mm\n",
+				point.temperature,
+				point.performed.columns(),
+				point.height,
+			));
+		}
+		text
+	})
+}
@@ -0,0 +1,856 @@
+use std::{
+	cmp::{Ordering, Reverse},
+	fmt::Display,
+	num::NonZeroU8,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	catalog::Catalog,
+	mechanics::partition_loads,
+	model::units::{model_ri_to_real_ri, Microns},
+	paramset::{ParamSet, MAXIMUM_RI, MINIMUM_RI, PARTITION_THICKNESSES},
+};
+
+/// Minimum RI step between adjacent layers, unless one is given.
+pub const DEFAULT_MIN_RI_STEP: f64 = 0.01;
+
+/// Manufacturability constraints on designs.
+///
+/// All constraints are optional; the default set constrains nothing, but
+/// those read from a file have a minimum RI step.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Constraints {
+	/// Maximum total height of the lens, in mm.
+	pub max_height: Option<f64>,
+
+	/// Minimum difference in RI between adjacent layers.
+	///
+	/// When read from a file, this is 0.01 unless given, as adjacent layers of
+	/// the same RI can't be told apart; 0 allows any step.
+	#[serde(default = "default_min_ri_step")]
+	pub min_ri_step: Option<f64>,
+
+	/// Direction the RIs must go in, from the top down.
+	pub grading: Option<Grading>,
+
+	/// Partition thicknesses that are available, in mm.
+	pub partitions: Option<Vec<f64>>,
+
+	/// Lowest and highest RI of the liquids available.
+	pub ri_range: Option<[f64; 2]>,
+
+	/// Liquids available, when only those can be used.
+	pub liquids: Option<Catalog>,
+
+	/// Maximum sag of partitions under the liquid resting on them, in mm.
+	pub max_deflection: Option<f64>,
+
+	/// Maximum bending stress in partitions, in MPa.
+	pub max_stress: Option<f64>,
+
+	/// What to do with designs that violate constraints.
+	#[serde(default)]
+	pub enforcement: Enforcement,
+}
+
+/// Direction of the RI gradient, from the top of the lens down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grading {
+	/// RIs rise towards the bottom.
+	Increasing,
+	/// RIs fall towards the bottom.
+	Decreasing,
+}
+
+/// How constraint violations affect fitness.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "mode")]
+pub enum Enforcement {
+	/// Violating designs get the lowest possible fitness.
+	#[default]
+	Reject,
+
+	/// Fitness is scaled down by `1 - weight × severity`, where severity is
+	/// summed over all violations.
+	Penalty {
+		/// How much fitness is lost per unit of severity.
+		weight: f64,
+	},
+}
+
+/// A way in which a design breaks a constraint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+	/// Lens is taller than the maximum, both in mm.
+	Height {
+		/// Height of the lens.
+		height: f64,
+		/// Tallest allowed.
+		max: f64,
+	},
+
+	/// RIs of a layer and the one above it are too close.
+	///
+	/// Layers are indexed from 0 at the top.
+	RiStep {
+		/// Lower of the two layers.
+		layer: usize,
+		/// Difference between their RIs.
+		step: f64,
+		/// Smallest difference allowed.
+		min: f64,
+	},
+
+	/// RI of a layer goes against the grading, by this much.
+	Grading {
+		/// Layer that breaks the grading.
+		layer: usize,
+		/// How far its RI goes back from the one above.
+		reversal: f64,
+	},
+
+	/// Partition thickness isn't available, in mm.
+	Partition {
+		/// Thickness of the partitions.
+		thickness: f64,
+	},
+
+	/// RI of a layer is outside the range available, by this much.
+	RiRange {
+		/// Layer that's out of range.
+		layer: usize,
+		/// Its RI.
+		ri: f64,
+		/// How far it is from the nearest end of the range.
+		outside: f64,
+	},
+
+	/// No liquid available has the RI of a layer.
+	Liquid {
+		/// Layer without a liquid.
+		layer: usize,
+		/// Its RI.
+		ri: f64,
+	},
+
+	/// Layer has no partition above it, but the liquids won't stay apart.
+	Unstable {
+		/// Lower of the two layers.
+		layer: usize,
+		/// Why they won't stay apart.
+		cause: Instability,
+	},
+
+	/// Partition above a layer sags more than the maximum, both in mm.
+	Deflection {
+		/// Layer under the partition.
+		layer: usize,
+		/// Sag in the middle of the partition.
+		deflection: f64,
+		/// Most sag allowed.
+		max: f64,
+	},
+
+	/// Partition above a layer is stressed more than the maximum, both in MPa.
+	Stress {
+		/// Layer under the partition.
+		layer: usize,
+		/// Largest bending stress in the partition.
+		stress: f64,
+		/// Most stress allowed.
+		max: f64,
+	},
+}
+
+/// Why two liquids that touch won't stay apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instability {
+	/// One of the liquids isn't known, so neither is how it behaves.
+	Unknown,
+
+	/// The liquid above is at least as dense as the one below.
+	Inverted,
+
+	/// The liquids mix.
+	Miscible,
+}
+
+impl Display for Instability {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Self::Unknown => "one of the liquids isn't in the catalog",
+			Self::Inverted => "the liquid above isn't lighter",
+			Self::Miscible => "the liquids mix",
+		})
+	}
+}
+
+impl Violation {
+	/// How bad the violation is, relative to the constraint.
+	///
+	/// Zero is barely a violation, one is pretty bad.
+	pub fn severity(self) -> f64 {
+		match self {
+			Self::Height { height, max } => (height - max) / max,
+			Self::Deflection {
+				deflection: value,
+				max,
+				..
+			}
+			| Self::Stress {
+				stress: value, max, ..
+			} => (value - max) / max,
+			Self::RiStep { step, min, .. } => (min - step) / min,
+			Self::Grading {
+				reversal: outside, ..
+			}
+			| Self::RiRange { outside, .. } => {
+				outside / (model_ri_to_real_ri(MAXIMUM_RI) - model_ri_to_real_ri(MINIMUM_RI))
+			}
+			Self::Partition { .. } | Self::Liquid { .. } | Self::Unstable { .. } => 1.0,
+		}
+	}
+}
+
+impl Display for Violation {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Height { height, max } => {
+				write!(f, "lens is {height:.1}mm tall, over the {max:.1}mm maximum")
+			}
+			Self::RiStep { layer, step, min } => write!(
+				f,
+				"layer {} is only {step:.2} RI away from the one above, under the {min:.2} minimum",
+				layer + 1
+			),
+			Self::Grading { layer, reversal } => {
+				write!(
+					f,
+					"layer {} goes against the grading by {reversal:.2} RI",
+					layer + 1
+				)
+			}
+			Self::Partition { thickness } => {
+				write!(f, "partitions of {thickness:.1}mm are not available")
+			}
+			Self::RiRange { layer, ri, .. } => {
+				write!(
+					f,
+					"layer {} has RI {ri:.2}, which is not available",
+					layer + 1
+				)
+			}
+			Self::Liquid { layer, ri } => {
+				write!(
+					f,
+					"layer {} has RI {ri:.2}, which none of the liquids have",
+					layer + 1
+				)
+			}
+			Self::Unstable { layer, cause } => {
+				write!(
+					f,
+					"layer {} has no partition above it, but {cause}",
+					layer + 1
+				)
+			}
+			Self::Deflection {
+				layer,
+				deflection,
+				max,
+			} => write!(
+				f,
+				"partition above layer {} sags {deflection:.2}mm, over the {max:.2}mm maximum",
+				layer + 1
+			),
+			Self::Stress { layer, stress, max } => write!(
+				f,
+				"partition above layer {} is stressed to {stress:.2}MPa, over the {max:.2}MPa maximum",
+				layer + 1
+			),
+		}
+	}
+}
+
+fn default_min_ri_step() -> Option<f64> {
+	Some(DEFAULT_MIN_RI_STEP)
+}
+
+fn mm_to_tenths(mm: f64) -> u8 {
+	(mm * 10.0).round().clamp(0.0, u8::MAX.into()) as _
+}
+
+fn ri_step_to_model(step: f64) -> u8 {
+	(step * 100.0).round().clamp(0.0, u8::MAX.into()) as _
+}
+
+impl Constraints {
+	/// Checks a design against all constraints.
+	pub fn check<const N: usize>(&self, params: ParamSet<N>) -> Vec<Violation> {
+		let mut violations = Vec::new();
+
+		if let Some(max) = self.max_height {
+			let height = params.height() as f64 / 1000.0;
+			if height > max {
+				violations.push(Violation::Height { height, max });
+			}
+		}
+
+		let ris: Vec<u8> = params
+			.layers
+			.iter()
+			.map_while(|ri| ri.map(|ri| ri.get()))
+			.collect();
+		if let Some((min, max)) = self.model_ri_range() {
+			for (layer, ri) in ris.iter().enumerate() {
+				let outside = ri.saturating_sub(max).max(min.saturating_sub(*ri));
+				if outside > 0 {
+					violations.push(Violation::RiRange {
+						layer,
+						ri: model_ri_to_real_ri(*ri),
+						outside: f64::from(outside) / 100.0,
+					});
+				}
+			}
+		}
+		if let (Some(catalog), Some(liquids)) = (&self.liquids, self.model_liquids()) {
+			for (layer, ri) in ris.iter().enumerate() {
+				let filled = match params.materials[layer] {
+					0 => liquids.binary_search(ri).is_ok(),
+					entry => catalog.entry_ri(entry) == Some(*ri),
+				};
+				if !filled {
+					violations.push(Violation::Liquid {
+						layer,
+						ri: model_ri_to_real_ri(*ri),
+					});
+				}
+			}
+		}
+		for (layer, cause) in self.instabilities(params) {
+			violations.push(Violation::Unstable { layer, cause });
+		}
+		for (n, pair) in ris.windows(2).enumerate() {
+			let (above, below) = (model_ri_to_real_ri(pair[0]), model_ri_to_real_ri(pair[1]));
+			let layer = n + 1;
+
+			if let Some(min) = self.min_ri_step {
+				let step = (below - above).abs();
+				if pair[0].abs_diff(pair[1]) < ri_step_to_model(min) {
+					violations.push(Violation::RiStep { layer, step, min });
+				}
+			}
+
+			let reversal = match self.grading {
+				Some(Grading::Increasing) => above - below,
+				Some(Grading::Decreasing) => below - above,
+				None => 0.0,
+			};
+			if reversal > 0.0 {
+				violations.push(Violation::Grading { layer, reversal });
+			}
+		}
+
+		if let Some(partitions) = self.model_partitions() {
+			if !partitions.contains(&params.partitions_thickness) {
+				violations.push(Violation::Partition {
+					thickness: f64::from(params.partitions_thickness) / 10.0,
+				});
+			}
+		}
+
+		violations.extend(self.overloads(params));
+
+		violations
+	}
+
+	/// Partitions that sag or are stressed more than the maximum, from the top
+	/// down.
+	fn overloads<const N: usize>(&self, params: ParamSet<N>) -> Vec<Violation> {
+		if self.max_deflection.is_none() && self.max_stress.is_none() {
+			return Vec::new();
+		}
+		let mut violations = Vec::new();
+		for load in partition_loads(params, self.liquids.as_ref()) {
+			if let Some(max) = self.max_deflection.filter(|max| load.deflection > *max) {
+				violations.push(Violation::Deflection {
+					layer: load.layer,
+					deflection: load.deflection,
+					max,
+				});
+			}
+			if let Some(max) = self.max_stress.filter(|max| load.stress > *max) {
+				violations.push(Violation::Stress {
+					layer: load.layer,
+					stress: load.stress,
+					max,
+				});
+			}
+		}
+		violations
+	}
+
+	/// Applies enforcement to a fitness value, given the violations found.
+	pub fn enforce(&self, fitness: u64, lowest: u64, violations: &[Violation]) -> u64 {
+		if violations.is_empty() {
+			return fitness;
+		}
+
+		match self.enforcement {
+			Enforcement::Reject => lowest,
+			Enforcement::Penalty { weight } => {
+				let severity: f64 = violations.iter().map(|v| v.severity()).sum();
+				let factor = (1.0 - weight * severity).clamp(0.0, 1.0);
+				// rounding through f64 could otherwise go above the fitness
+				(((fitness as f64) * factor) as u64)
+					.min(fitness)
+					.max(lowest)
+			}
+		}
+	}
+
+	/// Snaps partitions to the nearest available thickness, and RIs into the
+	/// available range.
+	///
+	/// With liquids, each layer is filled with its catalog entry, wrapped
+	/// around if there's no such entry, or the liquid of the nearest RI if it
+	/// has none; the RI is then that of the liquid. Without, catalog entries
+	/// are cleared.
+	pub fn quantise<const N: usize>(&self, params: &mut ParamSet<N>) {
+		if let Some(partitions) = self.model_partitions() {
+			if let Some(nearest) = partitions
+				.into_iter()
+				.min_by_key(|p| p.abs_diff(params.partitions_thickness))
+			{
+				params.partitions_thickness = nearest;
+			}
+		}
+
+		let len = params.len();
+		let layers = &mut params.layers[..len];
+		if let Some((min, max)) = self.model_ri_range() {
+			for ri in layers.iter_mut().flatten() {
+				*ri = NonZeroU8::new(ri.get().clamp(min, max)).unwrap_or(*ri);
+			}
+		}
+
+		let Some(catalog) = self.liquids.as_ref().filter(|c| !c.materials.is_empty()) else {
+			params.materials.fill(0);
+			return;
+		};
+		let entries = catalog.materials.len().min(u8::MAX.into()) as u8;
+		for (layer, material) in layers.iter_mut().zip(&mut params.materials) {
+			let Some(ri) = layer else { continue };
+			*material = match *material {
+				0 => catalog.nearest(ri.get()).unwrap_or(0),
+				entry => (entry - 1) % entries + 1,
+			};
+			if let Some(entry_ri) = catalog.entry_ri(*material) {
+				*ri = NonZeroU8::new(entry_ri).unwrap_or(*ri);
+			}
+		}
+		params.materials[len..].fill(0);
+	}
+
+	/// Modifies a design so it satisfies the constraints, as far as possible.
+	///
+	/// Once [quantised](Self::quantise), RIs are sorted to follow the grading
+	/// and spread apart to the minimum step, partitions are made thicker until
+	/// they hold the liquid on them, and layers are made thinner (or fewer) to
+	/// fit the maximum height.
+	pub fn repair<const N: usize>(&self, params: &mut ParamSet<N>) {
+		self.quantise(params);
+
+		// liquids go along with their RIs
+		let len = params.len();
+		let mut stack: Vec<_> = params.layers[..len]
+			.iter()
+			.copied()
+			.zip(params.materials)
+			.collect();
+		match self.grading {
+			Some(Grading::Increasing) => stack.sort_by_key(|(ri, _)| *ri),
+			Some(Grading::Decreasing) => stack.sort_by_key(|(ri, _)| Reverse(*ri)),
+			None => {}
+		}
+		for (n, (ri, material)) in stack.into_iter().enumerate() {
+			params.layers[n] = ri;
+			params.materials[n] = material;
+		}
+
+		if let Some(step) = self.min_ri_step.map(ri_step_to_model) {
+			self.spread_ris(params, step);
+		}
+
+		// only layers that are there can go without a partition
+		params.open &= u32::MAX
+			.checked_shl(params.len() as _)
+			.map_or(u32::MAX, |above| !above)
+			& !1;
+		for (layer, _) in self.instabilities(*params) {
+			params.set_partitioned(layer, true);
+		}
+
+		self.thicken_partitions(params);
+
+		if let Some(max) = self.max_height {
+			fit_height(params, (max * 1000.0) as _);
+		}
+	}
+
+	/// Makes partitions the thinnest available that's thicker and holds the
+	/// liquid on them, or the thickest if none does.
+	fn thicken_partitions<const N: usize>(&self, params: &mut ParamSet<N>) {
+		if self.overloads(*params).is_empty() {
+			return;
+		}
+		let mut thicker = self
+			.model_partitions()
+			.unwrap_or_else(|| PARTITION_THICKNESSES.to_vec());
+		thicker.retain(|p| *p > params.partitions_thickness);
+		thicker.sort_unstable();
+
+		for thickness in thicker {
+			params.partitions_thickness = thickness;
+			if self.overloads(*params).is_empty() {
+				break;
+			}
+		}
+	}
+
+	/// Layers without a partition above them whose liquids won't stay apart
+	/// from those above, from the top down.
+	///
+	/// Liquids stay apart if the one below is denser and they don't mix; that
+	/// can only be known for liquids in the catalog.
+	pub fn instabilities<const N: usize>(&self, params: ParamSet<N>) -> Vec<(usize, Instability)> {
+		let materials = self
+			.liquids
+			.as_ref()
+			.map(|c| c.materials(params))
+			.unwrap_or_default();
+		let material = |n: usize| materials.get(n).copied().flatten();
+		(1..params.len())
+			.filter(|n| !params.partitioned(*n))
+			.filter_map(|n| {
+				let cause = match (material(n - 1), material(n)) {
+					(Some(above), Some(below)) if above.density >= below.density => {
+						Instability::Inverted
+					}
+					(Some(above), Some(below)) if above.mixes_with(below) => Instability::Miscible,
+					(Some(_), Some(_)) => return None,
+					_ => Instability::Unknown,
+				};
+				Some((n, cause))
+			})
+			.collect()
+	}
+
+	/// Available partition thicknesses, in the units of [`ParamSet`].
+	pub fn model_partitions(&self) -> Option<Vec<u8>> {
+		let partitions = self.partitions.as_ref()?;
+		Some(partitions.iter().map(|p| mm_to_tenths(*p)).collect())
+	}
+
+	/// Available RIs, in the units of [`ParamSet`], within those possible.
+	fn model_ri_range(&self) -> Option<(u8, u8)> {
+		let [min, max] = self.ri_range?;
+		let model = |ri: f64| {
+			((ri - model_ri_to_real_ri(0)) * 100.0)
+				.round()
+				.clamp(MINIMUM_RI.into(), MAXIMUM_RI.into()) as u8
+		};
+		Some((model(min), model(max)))
+	}
+
+	/// Available liquids, in the units of [`ParamSet`], sorted and without
+	/// duplicates or any outside of the range.
+	fn model_liquids(&self) -> Option<Vec<u8>> {
+		let (min, max) = self.model_ri_range().unwrap_or((MINIMUM_RI, MAXIMUM_RI));
+		let mut liquids: Vec<u8> = self
+			.liquids
+			.as_ref()?
+			.materials
+			.iter()
+			.map(|m| ((m.ri - model_ri_to_real_ri(0)) * 100.0).round())
+			.filter(|ri| (f64::from(min)..=f64::from(max)).contains(ri))
+			.map(|ri| ri as u8)
+			.collect();
+		liquids.sort_unstable();
+		liquids.dedup();
+		Some(liquids)
+	}
+
+	/// Moves mutated layers onto the liquids available.
+	///
+	/// Mutations that would leave a layer on the same liquid after snapping
+	/// move it to the next liquid in the direction of the change instead, so
+	/// small steps still go somewhere; the liquid nearest to the mutated RI is
+	/// chosen among those past the original one.
+	pub fn step_liquids<const N: usize>(&self, before: &ParamSet<N>, params: &mut ParamSet<N>) {
+		let Some(liquids) = self.model_liquids() else {
+			return;
+		};
+		for (old, new) in before.layers.iter().zip(&mut params.layers) {
+			let (Some(old), Some(ri)) = (old, new.as_mut()) else {
+				continue;
+			};
+			let (old, target) = (old.get(), ri.get());
+			let past = liquids.iter().filter(|l| match target.cmp(&old) {
+				Ordering::Greater => **l > old,
+				Ordering::Less => **l < old,
+				Ordering::Equal => false,
+			});
+			if let Some(liquid) = past.min_by_key(|l| l.abs_diff(target)) {
+				*ri = NonZeroU8::new(*liquid).unwrap_or(*ri);
+			}
+		}
+	}
+
+	/// An available RI at least the step away from another, going up or down.
+	///
+	/// With liquids, that's the nearest liquid that far; otherwise it's
+	/// exactly the step, if that's within the range.
+	fn step_from(&self, from: u8, step: u8, up: bool) -> Option<u8> {
+		let (min, max) = self.model_ri_range().unwrap_or((MINIMUM_RI, MAXIMUM_RI));
+		match self.model_liquids() {
+			Some(liquids) if up => liquids
+				.into_iter()
+				.find(|l| *l >= from.saturating_add(step)),
+			Some(liquids) => liquids
+				.into_iter()
+				.rev()
+				.find(|l| from.checked_sub(step).is_some_and(|down| *l <= down)),
+			None if up => Some(from.saturating_add(step)).filter(|ri| *ri <= max),
+			None => from.checked_sub(step).filter(|ri| *ri >= min),
+		}
+	}
+
+	/// Moves RIs apart so adjacent layers differ by at least the step.
+	///
+	/// If that can't be done within the range of RIs, the stack is cut short.
+	fn spread_ris<const N: usize>(&self, params: &mut ParamSet<N>, step: u8) {
+		let len = params.len();
+		for n in 1..len {
+			let (above, this) = match (params.layers[n - 1], params.layers[n]) {
+				(Some(above), Some(this)) => (above.get(), this.get()),
+				_ => break,
+			};
+
+			if above.abs_diff(this) >= step {
+				continue;
+			}
+
+			let up = self.step_from(above, step, true);
+			let down = self.step_from(above, step, false);
+			let go_up = match self.grading {
+				Some(Grading::Increasing) => true,
+				Some(Grading::Decreasing) => false,
+				None => this >= above,
+			};
+
+			let new = match (go_up, up, down) {
+				(true, Some(up), _) => Some(up),
+				(false, _, Some(down)) => Some(down),
+				// when there's no grading, try the other way
+				(true, None, down) if self.grading.is_none() => down,
+				(false, up, None) if self.grading.is_none() => up,
+				_ => None,
+			};
+
+			if let Some(new) = new {
+				params.layers[n] = NonZeroU8::new(new);
+				params.materials[n] = self
+					.liquids
+					.as_ref()
+					.and_then(|c| c.nearest(new))
+					.unwrap_or(0);
+			} else {
+				params.layers[n..].fill(None);
+				params.materials[n..].fill(0);
+				break;
+			}
+		}
+	}
+}
+
+/// Makes layers thinner, and then fewer, until the lens fits in the height.
+fn fit_height<const N: usize>(params: &mut ParamSet<N>, max: Microns) {
+	while params.height() > max {
+		let len = params.len() as Microns;
+		let fixed = (params.partitions_thickness as Microns) * 100 + 3_000;
+		let per_layer = max / len.max(1);
+
+		if per_layer >= fixed {
+			let tenths = (per_layer - fixed) / 100;
+			params.layers_thickness = params.layers_thickness.min(tenths as _);
+			break;
+		}
+
+		params.layers_thickness = 0;
+		if len <= 1 {
+			break;
+		}
+		params.layers[(len - 1) as usize] = None;
+		params.materials[(len - 1) as usize] = 0;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::catalog::Material;
+
+	fn graded() -> Constraints {
+		Constraints {
+			max_height: None,
+			min_ri_step: Some(0.02),
+			grading: Some(Grading::Increasing),
+			partitions: Some(vec![2.5]),
+			ri_range: None,
+			liquids: None,
+			max_deflection: None,
+			max_stress: None,
+			enforcement: Enforcement::Reject,
+		}
+	}
+
+	#[test]
+	fn finds_each_violation() {
+		let params: ParamSet = "layer:3.00mm part:1.00mm | 1.40 1.41 1.35 |"
+			.parse()
+			.unwrap();
+		let violations = graded().check(params);
+		assert!(matches!(violations[0], Violation::RiStep { layer: 1, .. }));
+		assert!(matches!(violations[1], Violation::Grading { layer: 2, .. }));
+		assert_eq!(violations[2], Violation::Partition { thickness: 1.0 });
+		assert_eq!(violations.len(), 3);
+	}
+
+	#[test]
+	fn repairs_onto_available_partitions() {
+		let constraints = graded();
+		let mut params: ParamSet = "layer:3.00mm part:1.00mm | 1.40 1.41 1.35 |"
+			.parse()
+			.unwrap();
+		constraints.repair(&mut params);
+		assert_eq!(params.partitions_thickness, 25);
+		assert_eq!(constraints.check(params), []);
+	}
+
+	#[test]
+	fn thickens_partitions_that_sag() {
+		let constraints = Constraints {
+			min_ri_step: None,
+			grading: None,
+			partitions: Some(vec![1.0, 2.5, 5.0]),
+			max_deflection: Some(0.005),
+			..graded()
+		};
+		let mut params: ParamSet = "top:50mm bottom:50mm layer:3.00mm part:1.00mm | 1.40 1.45 |"
+			.parse()
+			.unwrap();
+		let violations = constraints.check(params);
+		assert!(matches!(
+			violations[..],
+			[Violation::Deflection { layer: 1, .. }]
+		));
+
+		constraints.repair(&mut params);
+		assert_eq!(params.partitions_thickness, 25);
+		assert_eq!(constraints.check(params), []);
+	}
+
+	#[test]
+	fn rejects_or_penalises() {
+		let violations = [Violation::Partition { thickness: 1.0 }];
+		let mut constraints = graded();
+		assert_eq!(constraints.enforce(1000, 0, &violations), 0);
+		assert_eq!(constraints.enforce(1000, 0, &[]), 1000);
+		constraints.enforcement = Enforcement::Penalty { weight: 0.5 };
+		let severity = violations[0].severity();
+		assert_eq!(
+			constraints.enforce(1000, 0, &violations),
+			(1000.0 * (1.0 - 0.5 * severity)) as u64
+		);
+	}
+
+	#[test]
+	fn quantises_onto_catalog_entries() {
+		let liquid = |ri| Material {
+			name: format!("{ri}"),
+			ri,
+			density: 1.0,
+			cost: 0.0,
+			absorption: 0.0,
+			miscibility: None,
+			diffusivity: None,
+			thermo_optic: None,
+			expansion: None,
+		};
+		let constraints = Constraints {
+			liquids: Some(Catalog {
+				materials: vec![liquid(1.33), liquid(1.40), liquid(1.45)],
+			}),
+			..graded()
+		};
+		let mut params: ParamSet = "layer:3.00mm part:1.00mm | 1.36 1.50#2 1.33#5 |"
+			.parse()
+			.unwrap();
+		constraints.quantise(&mut params);
+		// the nearest liquid, the entry given, and the entry wrapped around
+		assert_eq!(params.materials[..4], [1, 2, 2, 0]);
+		assert_eq!(
+			params.to_string().split_once('|').unwrap().1,
+			" 1.33#1 1.40#2 1.40#2 |"
+		);
+	}
+
+	#[test]
+	fn finds_liquids_that_wont_stay_apart() {
+		let liquid = |ri, density, group: &str| Material {
+			name: group.into(),
+			ri,
+			density,
+			cost: 0.0,
+			absorption: 0.0,
+			miscibility: Some(group.into()),
+			diffusivity: None,
+			thermo_optic: None,
+			expansion: None,
+		};
+		let constraints = Constraints {
+			liquids: Some(Catalog {
+				materials: vec![
+					liquid(1.333, 1.0, "aqueous"),
+					liquid(1.462, 0.85, "oil"),
+					liquid(1.474, 1.26, "aqueous"),
+				],
+			}),
+			..Constraints::default()
+		};
+		// water under oil stays put, glycerol mixes into the water, and 1.40
+		// isn't a liquid at all
+		let mut params: ParamSet = "layer:3.00mm part:1.00mm | 1.46#2 ~ 1.33#1 ~ 1.47#3 ~ 1.40 |"
+			.parse()
+			.unwrap();
+		assert_eq!(
+			constraints.instabilities(params),
+			[(2, Instability::Miscible), (3, Instability::Unknown)]
+		);
+		let inverted: ParamSet = "layer:3.00mm part:1.00mm | 1.33#1 ~ 1.46#2 |"
+			.parse()
+			.unwrap();
+		assert_eq!(
+			constraints.instabilities(inverted),
+			[(1, Instability::Inverted)]
+		);
+
+		constraints.repair(&mut params);
+		assert_eq!(constraints.instabilities(params), []);
+		assert!(!params.partitioned(1));
+	}
+}
@@ -0,0 +1,281 @@
+use std::num::NonZeroU8;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+	model::{
+		profile::Profile,
+		units::{model_ri_to_real_ri, real_ri_to_model_ri},
+	},
+	paramset::ParamSet,
+};
+
+/// A [`ParamSet`] in physical units, for exchange with other tools.
+///
+/// Converting to a `ParamSet` rounds values to the nearest representable one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Design {
+	/// Width of the top (entry) face, in mm.
+	pub width_top: f64,
+
+	/// Width of the bottom (exit) face, in mm.
+	pub width_bottom: f64,
+
+	/// Shape of both sidewalls, or of the left one if `right_sidewall` is
+	/// given.
+	#[serde(default)]
+	pub sidewall: Profile,
+
+	/// Shape of the right sidewall, if it differs from the left one.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub right_sidewall: Option<Profile>,
+
+	/// Thickness of each layer, in mm.
+	pub layer_thickness: f64,
+
+	/// Thickness of each partition, in mm.
+	pub partition_thickness: f64,
+
+	/// Refractive index of each layer, from the top down.
+	pub layers: Vec<f64>,
+
+	/// Catalog entry of each layer, from 1, or 0 for layers only given by
+	/// their RI. Empty if no layer has one.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub materials: Vec<usize>,
+
+	/// Layers that touch the one above without a partition, from 0 at the top.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub open: Vec<usize>,
+}
+
+/// Why a design can't be turned into a [`ParamSet`].
+#[derive(Clone, Copy, Debug, Error, PartialEq)]
+pub enum DesignError {
+	/// A face is too narrow or too wide, in mm.
+	#[error("width {0}mm is out of range (1mm to 255mm)")]
+	Width(f64),
+
+	/// Layers are too thin or too thick, in mm.
+	#[error("layer thickness {0}mm is out of range (3.0mm to 28.5mm)")]
+	LayerThickness(f64),
+
+	/// Partitions are too thick, in mm.
+	#[error("partition thickness {0}mm is out of range (0.0mm to 25.5mm)")]
+	PartitionThickness(f64),
+
+	/// More layers than the model has room for.
+	#[error("too many layers: {got}, but at most {max} are possible")]
+	TooManyLayers {
+		/// Layers in the design.
+		got: usize,
+		/// Most layers possible.
+		max: usize,
+	},
+
+	/// An RI is outside of what the model holds.
+	#[error("refractive index {0} can't be represented")]
+	RefractiveIndex(f64),
+
+	/// More catalog entries than layers.
+	#[error("{got} catalog entries given for {layers} layers")]
+	Materials {
+		/// Catalog entries in the design.
+		got: usize,
+		/// Layers in the design.
+		layers: usize,
+	},
+
+	/// A catalog entry is past those the model holds.
+	#[error("catalog entry {0} can't be represented")]
+	Material(usize),
+
+	/// The top layer, or one that doesn't exist, is marked open.
+	#[error("layer {0} can't go without a partition, there's no layer above it")]
+	Open(usize),
+}
+
+impl<const N: usize> From<ParamSet<N>> for Design {
+	fn from(params: ParamSet<N>) -> Self {
+		Self {
+			width_top: f64::from(params.width_top),
+			width_bottom: f64::from(params.width_bottom),
+			sidewall: params.sidewalls[0].into(),
+			right_sidewall: (params.sidewalls[1] != params.sidewalls[0])
+				.then(|| params.sidewalls[1].into()),
+			layer_thickness: (f64::from(params.layers_thickness) + 30.0) / 10.0,
+			partition_thickness: f64::from(params.partitions_thickness) / 10.0,
+			layers: params
+				.layers
+				.iter()
+				.map_while(|ri| ri.map(|ri| model_ri_to_real_ri(ri.get())))
+				.collect(),
+			materials: if params.materials[..params.len()].iter().all(|m| *m == 0) {
+				Vec::new()
+			} else {
+				params.materials[..params.len()]
+					.iter()
+					.map(|m| usize::from(*m))
+					.collect()
+			},
+			open: (0..params.len())
+				.filter(|n| !params.partitioned(*n))
+				.collect(),
+		}
+	}
+}
+
+/// Rounds a physical quantity to a number of steps, if it fits in a u8.
+fn quantise(value: f64, offset: f64, step: f64) -> Option<u8> {
+	let steps = ((value - offset) / step).round();
+	if (0.0..=f64::from(u8::MAX)).contains(&steps) {
+		Some(steps as _)
+	} else {
+		None
+	}
+}
+
+impl<const N: usize> TryFrom<Design> for ParamSet<N> {
+	type Error = DesignError;
+
+	fn try_from(design: Design) -> Result<Self, Self::Error> {
+		if design.layers.len() > N {
+			return Err(DesignError::TooManyLayers {
+				got: design.layers.len(),
+				max: N,
+			});
+		}
+
+		let mut layers = [None; N];
+		for (layer, ri) in layers.iter_mut().zip(&design.layers) {
+			*layer = Some(
+				real_ri_to_model_ri(*ri)
+					.and_then(NonZeroU8::new)
+					.ok_or(DesignError::RefractiveIndex(*ri))?,
+			);
+		}
+
+		if design.materials.len() > design.layers.len() {
+			return Err(DesignError::Materials {
+				got: design.materials.len(),
+				layers: design.layers.len(),
+			});
+		}
+		let mut materials = [0; N];
+		for (material, entry) in materials.iter_mut().zip(&design.materials) {
+			*material = u8::try_from(*entry).map_err(|_| DesignError::Material(*entry))?;
+		}
+
+		let mut open = 0;
+		for layer in design.open {
+			if layer == 0 || layer >= design.layers.len() {
+				return Err(DesignError::Open(layer));
+			}
+			open |= 1 << layer;
+		}
+
+		let width = |mm: f64| {
+			quantise(mm, 0.0, 1.0)
+				.filter(|w| *w > 0)
+				.ok_or(DesignError::Width(mm))
+		};
+
+		Ok(Self {
+			width_top: width(design.width_top)?,
+			width_bottom: width(design.width_bottom)?,
+			sidewalls: [
+				design.sidewall.into(),
+				design.right_sidewall.unwrap_or(design.sidewall).into(),
+			],
+			layers_thickness: quantise(design.layer_thickness, 3.0, 0.1)
+				.ok_or(DesignError::LayerThickness(design.layer_thickness))?,
+			partitions_thickness: quantise(design.partition_thickness, 0.0, 0.1)
+				.ok_or(DesignError::PartitionThickness(design.partition_thickness))?,
+			layers,
+			materials,
+			open,
+		})
+	}
+}
+
+impl<const N: usize> Serialize for ParamSet<N> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		Design::from(*self).serialize(serializer)
+	}
+}
+
+impl<'de, const N: usize> Deserialize<'de> for ParamSet<N> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Design::deserialize(deserializer)?
+			.try_into()
+			.map_err(D::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sidewall::Sidewall;
+
+	fn lopsided() -> ParamSet {
+		let mut params = ParamSet::nth(123_456_789);
+		params.sidewalls = [
+			Sidewall::Parabolic(200),
+			Sidewall::Piecewise([10, 120, 250]),
+		];
+		params
+	}
+
+	#[test]
+	fn round_trips_through_json_and_toml() {
+		let params = lopsided();
+		let json = serde_json::to_string(&params).unwrap();
+		assert_eq!(serde_json::from_str::<ParamSet>(&json).unwrap(), params);
+		let toml = toml::to_string(&params).unwrap();
+		assert_eq!(toml::from_str::<ParamSet>(&toml).unwrap(), params);
+	}
+
+	#[test]
+	fn writes_physical_units() {
+		let design = Design::from(ParamSet::<10>::default());
+		assert_eq!(design.width_top, 104.0);
+		assert_eq!(design.layer_thickness, 3.0);
+		assert_eq!(design.layers, [1.33]);
+		// matching walls are written once
+		assert_eq!(design.right_sidewall, None);
+		assert!(Design::from(lopsided()).right_sidewall.is_some());
+	}
+
+	#[test]
+	fn rejects_what_cannot_be_represented() {
+		let mut design = Design::from(ParamSet::<10>::default());
+		design.layers = vec![1.40; 11];
+		assert_eq!(
+			ParamSet::<10>::try_from(design.clone()),
+			Err(DesignError::TooManyLayers { got: 11, max: 10 })
+		);
+		design.layers = vec![0.5];
+		assert_eq!(
+			ParamSet::<10>::try_from(design.clone()),
+			Err(DesignError::RefractiveIndex(0.5))
+		);
+		design.layers = vec![1.40, 1.45];
+		design.open = vec![2];
+		assert_eq!(
+			ParamSet::<10>::try_from(design.clone()),
+			Err(DesignError::Open(2))
+		);
+		design.open = vec![1];
+		assert!(!ParamSet::<10>::try_from(design.clone())
+			.unwrap()
+			.partitioned(1));
+		design.layers = vec![1.40];
+		design.open.clear();
+		design.width_top = 0.0;
+		assert_eq!(
+			ParamSet::<10>::try_from(design),
+			Err(DesignError::Width(0.0))
+		);
+	}
+}
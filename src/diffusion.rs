@@ -0,0 +1,327 @@
+use serde::Serialize;
+
+use crate::{
+	catalog::Catalog,
+	model::{
+		curve::Curve,
+		slabs,
+		units::{model_ri_to_real_ri, real_ri_to_model_ri, Microns},
+		Slab,
+	},
+	paramset::ParamSet,
+};
+
+/// Cells each layer is divided into.
+const CELLS_PER_LAYER: usize = 32;
+
+/// Time steps per day, at least; implicit steps are stable at any size, this
+/// is for accuracy.
+const STEPS_PER_DAY: f64 = 4.0;
+
+/// Diffusion coefficient for liquids that don't give one, in m²/s: about
+/// that of small molecules in water.
+pub const DEFAULT_DIFFUSIVITY: f64 = 1e-9;
+
+/// Seconds in a day.
+const DAY: f64 = 86_400.0;
+
+/// A slice of the stack, small enough that its RI can be taken as even.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Cell {
+	/// Height of the top of the cell.
+	pub top: Microns,
+	/// Height of its bottom.
+	pub bottom: Microns,
+	/// Real RI, not in the units of [`ParamSet`].
+	pub ri: f64,
+	/// Whether the cell is a partition, which nothing diffuses through.
+	pub partition: bool,
+}
+
+/// RIs along the stack, from the top down, as liquids diffuse into each other.
+///
+/// Liquids only diffuse between layers that touch without a partition and
+/// that mix. Concentration is taken to go with RI linearly, so it's the RI
+/// that diffuses, following Fick's second law in one dimension.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RiProfile {
+	/// Cells from the top down.
+	pub cells: Vec<Cell>,
+
+	/// Diffusion coefficient of each cell, in mm²/s.
+	#[serde(skip)]
+	diffusivity: Vec<f64>,
+
+	/// Whether each cell exchanges liquid with the one below.
+	#[serde(skip)]
+	joined: Vec<bool>,
+}
+
+impl RiProfile {
+	/// Profile of a design as it's filled, with even layers.
+	///
+	/// Coefficients of liquids come from the catalog, if they're in it and have
+	/// one; the default is used otherwise.
+	pub fn new<const N: usize>(
+		params: ParamSet<N>,
+		catalog: Option<&Catalog>,
+		default: f64,
+	) -> Self {
+		let slabs = slabs(params, params.outline());
+		let materials = catalog
+			.map(|c| c.slab_materials(params, &slabs))
+			.unwrap_or_default();
+		let material = |n: usize| materials.get(n).copied().flatten();
+
+		let mut profile = Self {
+			cells: Vec::new(),
+			diffusivity: Vec::new(),
+			joined: Vec::new(),
+		};
+		for (n, slab) in slabs.iter().enumerate() {
+			let cells = if slab.partition { 1 } else { CELLS_PER_LAYER };
+			let height = slab.top - slab.bottom;
+			let diffusivity = material(n).and_then(|m| m.diffusivity).unwrap_or(default);
+			// liquids touching without a partition mix, unless they're known not to
+			let joins_next = !slab.partition
+				&& slabs.get(n + 1).is_some_and(|below| {
+					!below.partition
+						&& match (material(n), material(n + 1)) {
+							(Some(a), Some(b)) => a.mixes_with(b),
+							_ => true,
+						}
+				});
+
+			for cell in 0..cells {
+				let top = slab.top - height * cell as Microns / cells as Microns;
+				let bottom = slab.top - height * (cell + 1) as Microns / cells as Microns;
+				profile.cells.push(Cell {
+					top,
+					bottom,
+					ri: slab.ri,
+					partition: slab.partition,
+				});
+				// m²/s to mm²/s
+				profile.diffusivity.push(diffusivity * 1e6);
+				profile
+					.joined
+					.push(!slab.partition && (cell + 1 < cells || joins_next));
+			}
+		}
+		profile
+	}
+
+	/// Lets liquids diffuse for a number of days.
+	///
+	/// Uses backward Euler steps, solving the tridiagonal system with the
+	/// Thomas algorithm.
+	pub fn diffuse(&mut self, days: f64) {
+		if days <= 0.0 || self.cells.is_empty() {
+			return;
+		}
+		let steps = (days * STEPS_PER_DAY).ceil().max(1.0);
+		let dt = days * DAY / steps;
+
+		// coupling between each cell and the one below, per step
+		let coupling: Vec<f64> = (0..self.cells.len())
+			.map(|n| {
+				if !self.joined[n] {
+					return 0.0;
+				}
+				let (a, b) = (self.cells[n], self.cells[n + 1]);
+				// µm to mm
+				let (ha, hb) = (
+					(a.top - a.bottom) as f64 / 1000.0,
+					(b.top - b.bottom) as f64 / 1000.0,
+				);
+				let (da, db) = (self.diffusivity[n], self.diffusivity[n + 1]);
+				// harmonic mean, as for conductances in series
+				let d = if da + db > 0.0 {
+					2.0 * da * db / (da + db)
+				} else {
+					0.0
+				};
+				d * dt / ((ha + hb) / 2.0)
+			})
+			.collect();
+		let widths: Vec<f64> = self
+			.cells
+			.iter()
+			.map(|c| ((c.top - c.bottom) as f64 / 1000.0).max(f64::EPSILON))
+			.collect();
+
+		let len = self.cells.len();
+		let (mut upper, mut rhs) = (vec![0.0; len], vec![0.0; len]);
+		for _ in 0..steps as u64 {
+			// forward sweep
+			for n in 0..len {
+				let above = if n > 0 { coupling[n - 1] } else { 0.0 };
+				let below = coupling[n];
+				let lower = -above / widths[n];
+				let mut diagonal = 1.0 + (above + below) / widths[n];
+				let mut value = self.cells[n].ri;
+				if n > 0 {
+					diagonal -= lower * upper[n - 1];
+					value -= lower * rhs[n - 1];
+				}
+				upper[n] = -below / widths[n] / diagonal;
+				rhs[n] = value / diagonal;
+			}
+			// back substitution
+			for n in (0..len).rev() {
+				let next = if n + 1 < len {
+					self.cells[n + 1].ri
+				} else {
+					0.0
+				};
+				self.cells[n].ri = rhs[n] - upper[n] * next;
+			}
+		}
+	}
+
+	/// Slabs for tracing, with cells merged where their RIs round the same.
+	pub fn slabs(&self) -> Vec<Slab> {
+		let mut slabs: Vec<Slab> = Vec::new();
+		for cell in &self.cells {
+			let ri = model_ri_to_real_ri(real_ri_to_model_ri(cell.ri).unwrap_or_default());
+			match slabs.last_mut() {
+				Some(last) if last.ri == ri && last.partition == cell.partition => {
+					last.bottom = cell.bottom;
+				}
+				_ => slabs.push(Slab {
+					top: cell.top,
+					bottom: cell.bottom,
+					ri,
+					partition: cell.partition,
+					curve: Curve::Flat,
+				}),
+			}
+		}
+		slabs
+	}
+
+	/// Largest change in RI from another profile of the same design.
+	pub fn max_change(&self, from: &Self) -> f64 {
+		self.cells
+			.iter()
+			.zip(&from.cells)
+			.map(|(a, b)| (a.ri - b.ri).abs())
+			.fold(0.0, f64::max)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::catalog::Material;
+
+	/// Two pairs of touching layers, with a partition between the pairs.
+	const PAIRS: &str = "layer:3.00mm part:1.00mm | 1.35 ~ 1.45 1.40 ~ 1.50 |";
+
+	fn profile(text: &str, diffusivity: f64) -> RiProfile {
+		let params: ParamSet = text.parse().unwrap();
+		RiProfile::new(params, None, diffusivity)
+	}
+
+	/// RI times thickness of each run of liquid between partitions.
+	fn masses(profile: &RiProfile) -> Vec<f64> {
+		profile
+			.cells
+			.split(|cell| cell.partition)
+			.filter(|run| !run.is_empty())
+			.map(|run| {
+				run.iter()
+					.map(|cell| cell.ri * (cell.top - cell.bottom) as f64)
+					.sum()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn conserves_ri_between_partitions() {
+		let mut profile = profile(PAIRS, DEFAULT_DIFFUSIVITY);
+		let before = masses(&profile);
+		assert_eq!(before.len(), 2);
+		for days in [1.0, 30.0, 365.0] {
+			profile.diffuse(days);
+			for (after, before) in masses(&profile).iter().zip(&before) {
+				assert!((after - before).abs() < before * 1e-9, "{after} ≠ {before}");
+			}
+		}
+		assert!(profile.max_change(&self::profile(PAIRS, DEFAULT_DIFFUSIVITY)) > 0.0);
+	}
+
+	#[test]
+	fn stays_stable_and_evens_out_with_long_steps() {
+		let mut profile = profile(PAIRS, 1e-3);
+		profile.diffuse(1.0);
+		let runs: Vec<_> = profile
+			.cells
+			.split(|cell| cell.partition)
+			.filter(|run| !run.is_empty())
+			.collect();
+		for (run, mean) in runs.into_iter().zip([1.40, 1.45]) {
+			for cell in run {
+				assert!(cell.ri.is_finite());
+				assert!((cell.ri - mean).abs() < 1e-4, "{} ≠ {mean}", cell.ri);
+			}
+		}
+	}
+
+	#[test]
+	fn keeps_partitioned_layers_apart() {
+		let text = "layer:3.00mm part:1.00mm | 1.35 1.45 1.40 1.50 |";
+		let filled = profile(text, DEFAULT_DIFFUSIVITY);
+		let mut aged = filled.clone();
+		aged.diffuse(365.0);
+		assert!(aged.max_change(&filled) < 1e-6);
+
+		// a partition between the pairs keeps each to its own liquids
+		let mut aged = profile(PAIRS, 1e-3);
+		aged.diffuse(1.0);
+		let partition = aged.cells.iter().rposition(|cell| cell.partition).unwrap();
+		assert!(aged.cells[..partition]
+			.iter()
+			.all(|cell| cell.partition || cell.ri < 1.405));
+		assert!(aged.cells[partition + 1..]
+			.iter()
+			.all(|cell| cell.ri > 1.445));
+	}
+
+	#[test]
+	fn merges_cells_that_round_the_same() {
+		// as filled: the top partition, two layers, a partition and two layers
+		let slabs = profile(PAIRS, DEFAULT_DIFFUSIVITY).slabs();
+		assert_eq!(slabs.len(), 6);
+		assert!(slabs.windows(2).all(|pair| pair[0].bottom == pair[1].top));
+	}
+
+	#[test]
+	fn keeps_liquids_that_dont_mix_apart() {
+		let liquid = |ri, group: &str| Material {
+			name: group.into(),
+			ri,
+			density: 1.0,
+			cost: 0.0,
+			absorption: 0.0,
+			miscibility: Some(group.into()),
+			diffusivity: None,
+			thermo_optic: None,
+			expansion: None,
+		};
+		let params: ParamSet = "layer:3.00mm part:1.00mm | 1.33#1 ~ 1.46#2 |"
+			.parse()
+			.unwrap();
+		let aged = |groups: [&str; 2]| {
+			let catalog = Catalog {
+				materials: vec![liquid(1.33, groups[0]), liquid(1.46, groups[1])],
+			};
+			let filled = RiProfile::new(params, Some(&catalog), DEFAULT_DIFFUSIVITY);
+			let mut aged = filled.clone();
+			aged.diffuse(30.0);
+			aged.max_change(&filled)
+		};
+		assert!(aged(["aqueous", "oil"]) < 1e-9);
+		assert!(aged(["aqueous", "aqueous"]) > 1e-3);
+	}
+}
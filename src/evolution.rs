@@ -0,0 +1,423 @@
+use std::{
+	fmt::{Debug, Display},
+	sync::Arc,
+};
+
+use genevo::{
+	algorithm::Algorithm,
+	ga::{genetic_algorithm, GeneticAlgorithm, GeneticAlgorithmError, State},
+	genetic::Genotype,
+	operator::{CrossoverOp, MutationOp},
+	population::{build_population, GenomeBuilder, Population},
+	random::{Prng, Rng, SeedableRng},
+};
+
+use crate::{
+	array::ParamArray,
+	builder::RandomBuilder,
+	constraints::Constraints,
+	experiment::{
+		operators::{Crossover, Mutation, Reinserter, Selection},
+		Experiment,
+	},
+	fitness::{
+		cache::{CacheStats, Cached, FitnessCache},
+		AgileFitness,
+	},
+	gradient::GradientSet,
+	model::raytrace,
+	mutation::repair::{RepairStats, Repaired, Repairer},
+	paramset::ParamSet,
+	record::{Evaluated, GenerationStats, RunRecord},
+};
+
+use checkpoint::Checkpoint;
+
+/// Saving runs to carry them on later.
+pub mod checkpoint;
+
+/// A genome the algorithm can run on, standing for a design.
+pub trait Encoding<const N: usize>: Genotype<Dna = u8> + Copy + Display + 'static {
+	/// Builds the first generation.
+	type Builder: GenomeBuilder<Self>;
+	/// Crossover the experiment chose, for this genome.
+	type Crossover: CrossoverOp<Self> + Debug + Sync;
+	/// Mutation the experiment chose, for this genome.
+	type Mutation: MutationOp<Self> + Debug + Sync;
+
+	/// Design the genome stands for, within the liquids and partitions the
+	/// constraints allow.
+	fn design(self, constraints: &Constraints) -> ParamSet<N>;
+
+	/// All genes, as kept in checkpoints.
+	fn genes(self) -> Vec<u8>;
+
+	/// From genes in the order of [`genes`](Self::genes), if there's the right
+	/// number of them.
+	fn from_genes(genes: &[u8]) -> Option<Self>;
+
+	/// Operators as set up in the experiment, repairing with the repairer if
+	/// the genome needs it.
+	fn operators(
+		experiment: &Experiment,
+		repairer: &Repairer,
+	) -> (Self::Builder, Self::Crossover, Self::Mutation);
+}
+
+impl<const N: usize> Encoding<N> for ParamSet<N> {
+	type Builder = RandomBuilder;
+	type Crossover = Repaired<Crossover>;
+	type Mutation = Repaired<Mutation>;
+
+	fn design(self, _: &Constraints) -> ParamSet<N> {
+		self
+	}
+
+	fn genes(self) -> Vec<u8> {
+		ParamArray::from(self).genes()
+	}
+
+	fn from_genes(genes: &[u8]) -> Option<Self> {
+		ParamArray::from_genes(genes).map(Self::from)
+	}
+
+	fn operators(
+		experiment: &Experiment,
+		repairer: &Repairer,
+	) -> (Self::Builder, Self::Crossover, Self::Mutation) {
+		let opt = &experiment.optimiser;
+		(
+			RandomBuilder {
+				outline: experiment.outline(),
+				constraints: experiment.constraints(),
+			},
+			Repaired {
+				inner: opt.crossover,
+				repairer: repairer.clone(),
+			},
+			Repaired {
+				inner: opt.mutation,
+				repairer: repairer.clone(),
+			},
+		)
+	}
+}
+
+/// Gradients are always valid, as they're brought into range when turned into
+/// designs, so they aren't repaired.
+impl<const N: usize> Encoding<N> for GradientSet<N> {
+	type Builder = RandomBuilder;
+	type Crossover = Crossover;
+	type Mutation = Mutation;
+
+	fn design(self, constraints: &Constraints) -> ParamSet<N> {
+		self.params(constraints)
+	}
+
+	fn genes(self) -> Vec<u8> {
+		GradientSet::genes(self).to_vec()
+	}
+
+	fn from_genes(genes: &[u8]) -> Option<Self> {
+		genes.try_into().ok().map(GradientSet::from_genes)
+	}
+
+	fn operators(
+		experiment: &Experiment,
+		_: &Repairer,
+	) -> (Self::Builder, Self::Crossover, Self::Mutation) {
+		let opt = &experiment.optimiser;
+		(
+			RandomBuilder {
+				outline: experiment.outline(),
+				constraints: experiment.constraints(),
+			},
+			opt.crossover,
+			opt.mutation,
+		)
+	}
+}
+
+/// The genetic algorithm, as set up from an [`Experiment`].
+type Ga<const N: usize, G> = GeneticAlgorithm<
+	G,
+	u64,
+	Cached<N>,
+	Selection,
+	<G as Encoding<N>>::Crossover,
+	<G as Encoding<N>>::Mutation,
+	Reinserter<Cached<N>>,
+>;
+
+/// State just before a generation, as much as is needed to run it again.
+#[derive(Clone, Debug)]
+struct ResumePoint<const N: usize, G> {
+	generation: u64,
+	rng: Prng,
+	population: Vec<G>,
+	recorded: usize,
+	best: Option<Evaluated<N>>,
+	repairs: RepairStats,
+	cache: CacheStats,
+}
+
+/// One generation of a run.
+#[derive(Clone, Debug)]
+pub struct Generation<G: Genotype> {
+	/// Number of the generation.
+	pub generation: u64,
+	/// Population and its best so far, as genevo keeps them.
+	pub state: State<G, u64>,
+
+	/// Why the run stopped, if this was its last generation.
+	pub stop: Option<String>,
+}
+
+/// A run of the genetic algorithm, one generation at a time.
+///
+/// Unlike genevo's simulator, this owns the random number generator, so a run
+/// can be [checkpointed](Self::checkpoint) and resumed exactly.
+///
+/// Runs are on [`ParamSet`] genomes, unless another [`Encoding`] is given.
+#[derive(Debug)]
+pub struct Evolution<const N: usize, G: Encoding<N> = ParamSet<N>> {
+	algorithm: Ga<N, G>,
+	rng: Prng,
+	repairer: Repairer,
+	cache: Arc<FitnessCache<N>>,
+
+	/// Generation to run next, from 1.
+	generation: u64,
+	record: RunRecord<N>,
+	last: Option<ResumePoint<N, G>>,
+}
+
+impl<const N: usize, G: Encoding<N>> Evolution<N, G> {
+	/// Starts a run from a random population.
+	///
+	/// Everything random in the run comes from the seed, so the same seed and
+	/// settings give the same run.
+	pub fn new(experiment: &Experiment, seed: u64) -> Self {
+		let mut rng = Prng::seed_from_u64(seed);
+		let (builder, ..) = G::operators(experiment, &Repairer::default());
+		let population = build_population()
+			.with_genome_builder(builder)
+			.of_size(experiment.optimiser.population_size)
+			.using_seed(rng.gen());
+		let record = RunRecord {
+			config: experiment.clone(),
+			seed,
+			best: None,
+			generations: Vec::new(),
+			repairs: Default::default(),
+			cache: Default::default(),
+		};
+		Self::build(population, rng, 1, record)
+	}
+
+	/// Carries on a run from a checkpoint, exactly as if it hadn't stopped.
+	pub fn resume(checkpoint: Checkpoint<N, G>) -> Self {
+		Self::build(
+			Population::with_individuals(checkpoint.population),
+			checkpoint.rng,
+			checkpoint.generation,
+			checkpoint.record,
+		)
+	}
+
+	fn build(population: Population<G>, rng: Prng, generation: u64, record: RunRecord<N>) -> Self {
+		let experiment = &record.config;
+		let opt = &experiment.optimiser;
+		let cache = Arc::<FitnessCache<N>>::default();
+		cache.restore(record.cache);
+		let fitness = Cached::new(experiment.fitness_function(), cache.clone());
+		let repairer = Repairer::new(experiment.constraints());
+		repairer.restore(record.repairs);
+		let (_, crossover, mutation) = G::operators(experiment, &repairer);
+
+		let algorithm = genetic_algorithm()
+			.with_evaluation(fitness.clone())
+			.with_selection(opt.selection)
+			.with_crossover(crossover)
+			.with_mutation(mutation)
+			.with_reinsertion(Reinserter {
+				reinsertion: opt.reinsertion,
+				fitness,
+			})
+			.with_initial_population(population)
+			.build();
+
+		Self {
+			algorithm,
+			rng,
+			repairer,
+			cache,
+			generation,
+			record,
+			last: None,
+		}
+	}
+
+	/// Runs the next generation.
+	pub fn step(&mut self) -> Result<Generation<G>, GeneticAlgorithmError> {
+		let before = (self.rng.clone(), self.repairer.stats(), self.cache.stats());
+		let generation = self.generation;
+		let state = self.algorithm.next(generation, &mut self.rng)?;
+
+		self.last = Some(ResumePoint {
+			generation,
+			rng: before.0,
+			population: state.evaluated_population.individuals().to_vec(),
+			recorded: self.record.generations.len(),
+			best: self.record.best,
+			repairs: before.1,
+			cache: before.2,
+		});
+		self.generation += 1;
+
+		let best = &state.best_solution;
+		self.record.generations.push(GenerationStats {
+			generation,
+			average_fitness: *state.evaluated_population.average_fitness(),
+			best_fitness: best.solution.fitness,
+		});
+		if self
+			.record
+			.best
+			.is_none_or(|prev| prev.fitness < best.solution.fitness)
+		{
+			let design = best
+				.solution
+				.genome
+				.design(&self.record.config.constraints());
+			self.record.best = Some(Evaluated {
+				design,
+				fitness: best.solution.fitness,
+				performance: raytrace(design, self.record.config.rays),
+				generation: best.generation,
+			});
+		}
+		self.record.repairs = self.repairer.stats();
+		self.record.cache = self.cache.stats();
+
+		let limit = self.record.config.optimiser.generation_limit;
+		let stop = if best.solution.fitness == AgileFitness::HIGHEST {
+			Some(format!(
+				"Stopped after a solution with the highest fitness was found in generation {generation}"
+			))
+		} else if generation >= limit {
+			Some(format!(
+				"Stopped after reaching the limit of {limit} generations"
+			))
+		} else {
+			None
+		};
+
+		Ok(Generation {
+			generation,
+			state,
+			stop,
+		})
+	}
+
+	/// Everything needed to resume the run, from just before the last
+	/// generation, so it's run again.
+	///
+	/// This is `None` until the first generation has run.
+	pub fn checkpoint(&self) -> Option<Checkpoint<N, G>> {
+		let last = self.last.clone()?;
+		let mut record = RunRecord {
+			best: last.best,
+			repairs: last.repairs,
+			cache: last.cache,
+			..self.record.clone()
+		};
+		record.generations.truncate(last.recorded);
+		Some(Checkpoint {
+			generation: last.generation,
+			population: last.population,
+			rng: last.rng,
+			record,
+		})
+	}
+
+	/// Fitness of all designs evaluated so far, which can be saved and loaded
+	/// to carry over to other runs.
+	pub fn cache(&self) -> &FitnessCache<N> {
+		&self.cache
+	}
+
+	/// Generation to run next.
+	pub fn generation(&self) -> u64 {
+		self.generation
+	}
+
+	/// Record of the run so far.
+	pub fn record(&self) -> &RunRecord<N> {
+		&self.record
+	}
+
+	/// Record of the run, once it's over.
+	pub fn into_record(self) -> RunRecord<N> {
+		self.record
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::RaySource;
+
+	fn experiment() -> Experiment {
+		let mut experiment = Experiment {
+			rays: RaySource {
+				entry_interval: 20.0,
+				angle_interval: 30.0,
+				..RaySource::default()
+			},
+			..Experiment::default()
+		};
+		experiment.optimiser.population_size = 12;
+		experiment.optimiser.generation_limit = 6;
+		experiment
+	}
+
+	fn run_out(evolution: &mut Evolution<5>) {
+		while evolution.step().unwrap().stop.is_none() {}
+	}
+
+	#[test]
+	fn resumes_exactly_from_a_checkpoint() {
+		let mut straight = Evolution::<5>::new(&experiment(), 7);
+		for _ in 0..3 {
+			straight.step().unwrap();
+		}
+		let checkpoint = straight.checkpoint().unwrap();
+		assert_eq!(checkpoint.generation, 3);
+		let json = serde_json::to_string(&checkpoint).unwrap();
+		let mut resumed = Evolution::resume(serde_json::from_str(&json).unwrap());
+		assert_eq!(resumed.generation(), 3);
+
+		run_out(&mut straight);
+		run_out(&mut resumed);
+		let (straight, resumed) = (straight.into_record(), resumed.into_record());
+		assert_eq!(resumed.generations.len(), straight.generations.len());
+		let (a, b) = (straight.best.unwrap(), resumed.best.unwrap());
+		assert_eq!(
+			(b.design, b.fitness, b.generation),
+			(a.design, a.fitness, a.generation)
+		);
+		assert_eq!(resumed.repairs, straight.repairs);
+	}
+
+	#[test]
+	fn same_seed_runs_the_same() {
+		let mut runs = [7, 7, 8].map(|seed| Evolution::<5>::new(&experiment(), seed));
+		for run in &mut runs {
+			run_out(run);
+		}
+		let [a, b, c] = runs.map(|run| serde_json::to_value(run.into_record()).unwrap());
+		assert_eq!(a, b);
+		assert_ne!(a["best"], c["best"]);
+	}
+}
@@ -0,0 +1,142 @@
+use std::{fs, io, path::Path, path::PathBuf};
+
+use genevo::random::Prng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{experiment::Experiment, paramset::ParamSet, record::RunRecord};
+
+use super::Encoding;
+
+/// Everything needed to carry on a run exactly where it left off.
+///
+/// Checkpoints are always written as JSON.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "G: Encoding<N>")]
+pub struct Checkpoint<const N: usize, G = ParamSet<N>> {
+	/// Generation to run next.
+	pub generation: u64,
+
+	/// Population of that generation.
+	#[serde(with = "genomes")]
+	pub population: Vec<G>,
+
+	/// State of the random number generator before that generation.
+	pub rng: Prng,
+
+	/// Record of the run until that generation.
+	pub record: RunRecord<N>,
+}
+
+/// Why a checkpoint couldn't be saved or loaded.
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+	/// The file couldn't be written or read.
+	#[error("{action} checkpoint {path}: {source}")]
+	Io {
+		/// Whether it was being written or read.
+		action: &'static str,
+		/// Path of the checkpoint.
+		path: PathBuf,
+		/// Error writing or reading it.
+		source: io::Error,
+	},
+
+	/// The checkpoint couldn't be serialised, or the file isn't one.
+	#[error("in checkpoint {path}: {source}")]
+	Json {
+		/// Path of the checkpoint.
+		path: PathBuf,
+		/// Error serialising or parsing it.
+		source: serde_json::Error,
+	},
+}
+
+/// Genomes as their raw genes, so they come back exactly as they were.
+///
+/// Serialised [`ParamSet`]s go through [`Design`](crate::design::Design),
+/// which normalises some values.
+mod genomes {
+	use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+	use super::Encoding;
+
+	pub fn serialize<S: Serializer, G: Encoding<N>, const N: usize>(
+		population: &[G],
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(population.iter().map(|genome| genome.genes()))
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>, G: Encoding<N>, const N: usize>(
+		deserializer: D,
+	) -> Result<Vec<G>, D::Error> {
+		Vec::<Vec<u8>>::deserialize(deserializer)?
+			.iter()
+			.map(|genes| {
+				G::from_genes(genes).ok_or_else(|| {
+					D::Error::custom(format!(
+						"genome has {} genes, which isn't right for {N} layers",
+						genes.len()
+					))
+				})
+			})
+			.collect()
+	}
+}
+
+impl<const N: usize, G: Encoding<N>> Checkpoint<N, G> {
+	/// Writes the checkpoint, replacing any previous one only once it's
+	/// complete.
+	pub fn save(&self, path: &Path) -> Result<(), CheckpointError> {
+		let io = |source| CheckpointError::Io {
+			action: "writing",
+			path: path.into(),
+			source,
+		};
+		let contents = serde_json::to_string(self).map_err(|source| CheckpointError::Json {
+			path: path.into(),
+			source,
+		})?;
+		let partial = path.with_extension("partial");
+		fs::write(&partial, contents).map_err(io)?;
+		fs::rename(&partial, path).map_err(io)
+	}
+
+	/// Reads a checkpoint saved by [`save`](Self::save).
+	pub fn load(path: &Path) -> Result<Self, CheckpointError> {
+		serde_json::from_str(&read(path)?).map_err(|source| CheckpointError::Json {
+			path: path.into(),
+			source,
+		})
+	}
+}
+
+fn read(path: &Path) -> Result<String, CheckpointError> {
+	fs::read_to_string(path).map_err(|source| CheckpointError::Io {
+		action: "reading",
+		path: path.into(),
+		source,
+	})
+}
+
+/// Settings of the run a checkpoint is from, which tells how many layers
+/// it's for.
+pub fn experiment(path: &Path) -> Result<Experiment, CheckpointError> {
+	#[derive(Deserialize)]
+	struct Record {
+		config: Experiment,
+	}
+
+	#[derive(Deserialize)]
+	struct Peek {
+		record: Record,
+	}
+
+	let peek: Peek =
+		serde_json::from_str(&read(path)?).map_err(|source| CheckpointError::Json {
+			path: path.into(),
+			source,
+		})?;
+	Ok(peek.record.config)
+}
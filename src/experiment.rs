@@ -0,0 +1,574 @@
+use std::{fs, io, path::Path, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+	bounds::OutlineBounds,
+	catalog::{Catalog, CatalogError, Material},
+	constraints::{Constraints, DEFAULT_MIN_RI_STEP},
+	fitness::{spec::FitnessSpec, AgileFitness},
+	mixing::{Mixing, MixingModel},
+	model::{units::model_ri_to_real_ri, RaySource},
+	paramset::{DEFAULT_LAYERS, DEFAULT_WIDTH_BOTTOM, MAXIMUM_RI, MINIMUM_RI},
+};
+
+use operators::{Crossover, Mutation, Reinsertion, Selection};
+
+/// Genetic operators an experiment can choose from.
+pub mod operators;
+
+/// Numbers of layers the binary is built for.
+pub const SUPPORTED_LAYERS: [usize; 5] = [5, 10, 15, 20, 30];
+
+/// Everything that defines an optimisation run, as loaded from a TOML file.
+///
+/// All sections are optional and default to the settings the model was
+/// developed with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Experiment {
+	/// Bounds on the shape of the lens.
+	pub geometry: Geometry,
+	/// Liquids and partitions available.
+	pub materials: Materials,
+	/// Rays designs are traced with.
+	pub rays: RaySource,
+	/// What makes a design fit.
+	pub fitness: FitnessSpec,
+	/// What designs must keep to.
+	pub constraints: Constraints,
+	/// Settings for the genetic algorithm.
+	pub optimiser: Optimiser,
+
+	/// Base liquids that layers are mixed from, for recipes.
+	pub mixing: Option<Mixing>,
+}
+
+impl Default for Experiment {
+	fn default() -> Self {
+		Self {
+			geometry: Geometry::default(),
+			materials: Materials::default(),
+			rays: RaySource::default(),
+			fitness: FitnessSpec::default(),
+			constraints: Constraints {
+				min_ri_step: Some(DEFAULT_MIN_RI_STEP),
+				..Default::default()
+			},
+			optimiser: Optimiser::default(),
+			mixing: None,
+		}
+	}
+}
+
+/// Bounds on the shape of the lens.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Geometry {
+	/// Maximum number of layers.
+	pub layers: usize,
+
+	/// Lowest and highest width of the top (entry) face, in mm.
+	pub width_top: [u8; 2],
+
+	/// Lowest and highest width of the bottom (exit) face, in mm.
+	pub width_bottom: [u8; 2],
+
+	/// Lowest and highest total height, in mm.
+	pub height: Option<[f64; 2]>,
+}
+
+impl Default for Geometry {
+	fn default() -> Self {
+		Self {
+			layers: DEFAULT_LAYERS,
+			width_top: [104, 208],
+			width_bottom: [DEFAULT_WIDTH_BOTTOM, DEFAULT_WIDTH_BOTTOM],
+			height: None,
+		}
+	}
+}
+
+/// Liquids and partitions the lens can be made of.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Materials {
+	/// Lowest and highest RI of the liquids available.
+	pub ri: [f64; 2],
+
+	/// Partition thicknesses that are available, in mm.
+	pub partitions: Option<Vec<f64>>,
+
+	/// File to read [`liquids`](Self::liquids) from, relative to the
+	/// experiment file.
+	///
+	/// It's read when the experiment is loaded, so records keep the liquids
+	/// themselves.
+	pub catalog: Option<PathBuf>,
+
+	/// Liquids layers can be filled with; if given, no other RIs are used.
+	#[serde(rename = "material")]
+	pub liquids: Option<Vec<Material>>,
+}
+
+impl Default for Materials {
+	fn default() -> Self {
+		Self {
+			ri: [
+				model_ri_to_real_ri(MINIMUM_RI),
+				model_ri_to_real_ri(MAXIMUM_RI),
+			],
+			partitions: None,
+			catalog: None,
+			liquids: None,
+		}
+	}
+}
+
+/// How designs are encoded as genomes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Genome {
+	/// Each layer's RI, see [`ParamSet`](crate::paramset::ParamSet).
+	#[default]
+	Layers,
+
+	/// A curve from a top to a bottom RI, see
+	/// [`GradientSet`](crate::gradient::GradientSet).
+	Gradient,
+}
+
+/// Settings for the genetic algorithm.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Optimiser {
+	/// How designs are encoded as genomes.
+	pub genome: Genome,
+	/// Genomes in each generation.
+	pub population_size: usize,
+	/// Generations to run for at most.
+	pub generation_limit: u64,
+	/// How parents are chosen.
+	pub selection: Selection,
+	/// How parents are bred.
+	pub crossover: Crossover,
+	/// How children are mutated.
+	pub mutation: Mutation,
+	/// How children replace their parents.
+	pub reinsertion: Reinsertion,
+}
+
+impl Default for Optimiser {
+	fn default() -> Self {
+		Self {
+			genome: Genome::Layers,
+			population_size: 200,
+			generation_limit: 2000,
+			selection: Selection::RouletteWheel {
+				ratio: 0.7,
+				individuals_per_parents: 3,
+			},
+			crossover: Crossover::Uniform,
+			mutation: Mutation::BreederValue {
+				rate: 0.05,
+				range: 1,
+				precision: 3,
+			},
+			reinsertion: Reinsertion::Elitist {
+				ratio: 0.7,
+				offspring_has_precedence: false,
+			},
+		}
+	}
+}
+
+/// Why an experiment couldn't be loaded.
+#[derive(Debug, Error)]
+pub enum ExperimentError {
+	/// The file couldn't be read.
+	#[error("reading {path}: {source}")]
+	Io {
+		/// Path of the experiment.
+		path: PathBuf,
+		/// Error reading it.
+		source: io::Error,
+	},
+
+	/// The file isn't an experiment in TOML.
+	#[error("in {path}: {source}")]
+	Parse {
+		/// Path of the experiment.
+		path: PathBuf,
+		/// Error parsing it.
+		source: toml::de::Error,
+	},
+
+	/// A setting is out of range, or doesn't go with the others.
+	#[error("invalid `{field}`: {reason}")]
+	Invalid {
+		/// Setting, as its path in the file.
+		field: &'static str,
+		/// What's wrong with it.
+		reason: String,
+	},
+
+	/// The catalog it names couldn't be read.
+	#[error(transparent)]
+	Catalog(#[from] Box<CatalogError>),
+}
+
+fn invalid(field: &'static str, reason: impl Into<String>) -> Result<(), ExperimentError> {
+	Err(ExperimentError::Invalid {
+		field,
+		reason: reason.into(),
+	})
+}
+
+fn check_fraction(field: &'static str, value: f64) -> Result<(), ExperimentError> {
+	if (0.0..=1.0).contains(&value) {
+		Ok(())
+	} else {
+		invalid(field, format!("{value} should be between 0 and 1"))
+	}
+}
+
+fn check_range<T: PartialOrd + std::fmt::Display>(
+	field: &'static str,
+	[low, high]: [T; 2],
+) -> Result<(), ExperimentError> {
+	if low <= high {
+		Ok(())
+	} else {
+		invalid(field, format!("lowest {low} is above highest {high}"))
+	}
+}
+
+impl Experiment {
+	/// Reads and validates an experiment file.
+	pub fn load(path: &Path) -> Result<Self, ExperimentError> {
+		let contents = fs::read_to_string(path).map_err(|source| ExperimentError::Io {
+			path: path.into(),
+			source,
+		})?;
+		let mut experiment: Self =
+			toml::from_str(&contents).map_err(|source| ExperimentError::Parse {
+				path: path.into(),
+				source,
+			})?;
+		if let Some(catalog) = experiment.materials.catalog.take() {
+			if experiment.materials.liquids.is_some() {
+				invalid(
+					"materials.catalog",
+					"give either a catalog file or materials, not both",
+				)?;
+			}
+			let catalog = path.parent().unwrap_or(Path::new("")).join(catalog);
+			experiment.materials.liquids =
+				Some(Catalog::load(&catalog).map_err(Box::new)?.materials);
+		}
+		experiment.validate()?;
+		Ok(experiment)
+	}
+
+	/// Checks settings are usable, beyond what their types ensure.
+	pub fn validate(&self) -> Result<(), ExperimentError> {
+		let Geometry {
+			layers,
+			width_top,
+			width_bottom,
+			height,
+		} = self.geometry;
+		if !SUPPORTED_LAYERS.contains(&layers) {
+			invalid(
+				"geometry.layers",
+				format!("{layers} is not supported, use one of {SUPPORTED_LAYERS:?}"),
+			)?;
+		}
+		check_range("geometry.width_top", width_top)?;
+		check_range("geometry.width_bottom", width_bottom)?;
+		if width_top[0] == 0 || width_bottom[0] == 0 {
+			invalid("geometry.width_top", "widths must be at least 1mm")?;
+		}
+		if let Some(height) = height {
+			check_range("geometry.height", height)?;
+		}
+
+		let [ri_min, ri_max] = self.materials.ri;
+		let (lowest, highest) = (
+			model_ri_to_real_ri(MINIMUM_RI),
+			model_ri_to_real_ri(MAXIMUM_RI),
+		);
+		check_range("materials.ri", self.materials.ri)?;
+		if ri_min < lowest - 0.005 || ri_max > highest + 0.005 {
+			invalid(
+				"materials.ri",
+				format!("the model only handles RIs from {lowest:.2} to {highest:.2}"),
+			)?;
+		}
+		if let Some(partitions) = &self.materials.partitions {
+			if partitions.is_empty() {
+				invalid("materials.partitions", "at least one is needed")?;
+			}
+			if let Some(bad) = partitions.iter().find(|p| !(0.0..=25.5).contains(*p)) {
+				invalid(
+					"materials.partitions",
+					format!("{bad}mm is outside of 0mm to 25.5mm"),
+				)?;
+			}
+		}
+		if let Some(liquids) = self.catalog() {
+			if liquids.materials.is_empty() {
+				invalid("materials.material", "at least one is needed")?;
+			}
+			if let Some(bad) = liquids.unusable().next() {
+				invalid(
+					"materials.material",
+					format!(
+						"{} has RI {}, the model only handles RIs from {lowest:.2} to {highest:.2}",
+						bad.name, bad.ri
+					),
+				)?;
+			}
+			if let Some(bad) = liquids
+				.materials
+				.iter()
+				.find(|m| m.diffusivity.is_some_and(|d| !(d >= 0.0 && d.is_finite())))
+			{
+				invalid(
+					"materials.material",
+					format!("{} should have a diffusivity of 0 or above", bad.name),
+				)?;
+			}
+			if let Some(bad) = liquids.materials.iter().find(|m| {
+				!m.thermo_optic.unwrap_or_default().is_finite()
+					|| !m.expansion.unwrap_or_default().is_finite()
+			}) {
+				invalid(
+					"materials.material",
+					format!("{} has a thermal coefficient that isn't a number", bad.name),
+				)?;
+			}
+			if liquids.materials.len() > u8::MAX.into() {
+				invalid("materials.material", "at most 255 can be listed")?;
+			}
+			if let Some(bad) = liquids
+				.materials
+				.iter()
+				.find(|m| m.ri < ri_min - 0.005 || m.ri > ri_max + 0.005)
+			{
+				invalid(
+					"materials.material",
+					format!("{} has RI {}, outside of `materials.ri`", bad.name, bad.ri),
+				)?;
+			}
+		}
+		if self.materials.catalog.is_some() {
+			invalid(
+				"materials.catalog",
+				"catalogs are read when the experiment is loaded",
+			)?;
+		}
+		if self.constraints.liquids.is_some() {
+			invalid("constraints.liquids", "set these in `materials` instead")?;
+		}
+		if self.constraints.partitions.is_some() {
+			invalid("constraints.partitions", "set these in `materials` instead")?;
+		}
+		if self.constraints.ri_range.is_some() {
+			invalid("constraints.ri_range", "set this as `materials.ri` instead")?;
+		}
+		for (field, max) in [
+			(
+				"constraints.max_deflection",
+				self.constraints.max_deflection,
+			),
+			("constraints.max_stress", self.constraints.max_stress),
+		] {
+			if max.is_some_and(|max| !(max > 0.0 && max.is_finite())) {
+				invalid(field, "should be above 0")?;
+			}
+		}
+
+		if let Some(mixing) = &self.mixing {
+			if mixing.low.ri >= mixing.high.ri {
+				invalid("mixing.high", "should have a higher RI than `mixing.low`")?;
+			}
+			if let MixingModel::Tabulated { points } = &mixing.model {
+				if points.len() < 2 {
+					invalid("mixing.model.points", "at least two are needed")?;
+				}
+				if points
+					.iter()
+					.any(|[fraction, _]| !(0.0..=1.0).contains(fraction))
+				{
+					invalid("mixing.model.points", "fractions should be between 0 and 1")?;
+				}
+				if points.windows(2).any(|pair| pair[0][0] >= pair[1][0]) {
+					invalid("mixing.model.points", "should be in order of fraction")?;
+				}
+			}
+		}
+
+		let rays = self.rays;
+		if rays.entry_interval <= 0.0 {
+			invalid("rays.entry_interval", "should be above 0")?;
+		}
+		if rays.angle_interval <= 0.0 {
+			invalid("rays.angle_interval", "should be above 0")?;
+		}
+		if rays.angle_min < -90.0 || rays.angle_max > 90.0 {
+			invalid("rays.angle_min", "angles should be within -90° to 90°")?;
+		}
+		check_range("rays.angle_min", [rays.angle_min, rays.angle_max])?;
+
+		if self.fitness.terms.is_empty() {
+			invalid("fitness.terms", "at least one is needed")?;
+		}
+		if self.fitness.terms.iter().any(|t| t.weight < 0.0) {
+			invalid("fitness.terms", "weights can't be negative")?;
+		}
+
+		let opt = &self.optimiser;
+		if opt.population_size < 2 {
+			invalid("optimiser.population_size", "should be at least 2")?;
+		}
+		check_fraction("optimiser.selection.ratio", opt.selection.ratio())?;
+		if opt.selection.individuals_per_parents() < 2 {
+			invalid(
+				"optimiser.selection.individuals_per_parents",
+				"should be at least 2",
+			)?;
+		}
+		if let Crossover::Blend { alpha } = opt.crossover {
+			if !(alpha >= 0.0 && alpha.is_finite()) {
+				invalid("optimiser.crossover.alpha", "should be 0 or above")?;
+			}
+		}
+		check_fraction("optimiser.mutation.rate", opt.mutation.rate())?;
+		check_fraction("optimiser.reinsertion.ratio", opt.reinsertion.ratio())?;
+
+		Ok(())
+	}
+
+	/// Bounds on the outline of designs.
+	pub fn outline(&self) -> OutlineBounds {
+		let Geometry {
+			width_top,
+			width_bottom,
+			height,
+			..
+		} = self.geometry;
+		OutlineBounds {
+			width_top: width_top[0]..=width_top[1],
+			width_bottom: width_bottom[0]..=width_bottom[1],
+			height: height.map(|[low, high]| (low * 1000.0) as _..=(high * 1000.0) as _),
+		}
+	}
+
+	/// Fitness function for designs, from all relevant settings.
+	pub fn fitness_function(&self) -> AgileFitness {
+		AgileFitness {
+			outline: self.outline(),
+			constraints: self.constraints(),
+			spec: self.fitness.clone(),
+			rays: self.rays,
+		}
+	}
+
+	/// Liquids available, if layers are restricted to them.
+	pub fn catalog(&self) -> Option<Catalog> {
+		self.materials
+			.liquids
+			.clone()
+			.map(|materials| Catalog { materials })
+	}
+
+	/// Constraints, including those from the materials available.
+	pub fn constraints(&self) -> Constraints {
+		Constraints {
+			partitions: self.materials.partitions.clone(),
+			ri_range: Some(self.materials.ri),
+			liquids: self.catalog(),
+			..self.constraints.clone()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::constraints::Grading;
+
+	fn parse(text: &str) -> Experiment {
+		toml::from_str(text).unwrap()
+	}
+
+	fn invalid_field(experiment: &Experiment) -> Option<&'static str> {
+		match experiment.validate() {
+			Err(ExperimentError::Invalid { field, .. }) => Some(field),
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn example_has_the_defaults() {
+		let example = parse(include_str!("../experiment.example.toml"));
+		let default = Experiment::default();
+		assert_eq!(example.geometry, default.geometry);
+		assert_eq!(example.materials, default.materials);
+		assert_eq!(example.constraints, default.constraints);
+		assert_eq!(example.optimiser, default.optimiser);
+		let metrics = |e: &Experiment| e.fitness.terms.iter().map(|t| t.metric).collect::<Vec<_>>();
+		assert_eq!(metrics(&example), metrics(&default));
+		assert!(example.validate().is_ok());
+	}
+
+	#[test]
+	fn keeps_the_minimum_step_with_other_constraints() {
+		let experiment = parse("[constraints]\ngrading = \"increasing\"");
+		assert_eq!(
+			experiment.constraints.min_ri_step,
+			Some(DEFAULT_MIN_RI_STEP)
+		);
+		assert_eq!(experiment.constraints.grading, Some(Grading::Increasing));
+	}
+
+	#[test]
+	fn accepts_only_supported_layers() {
+		for layers in SUPPORTED_LAYERS {
+			let experiment = parse(&format!("[geometry]\nlayers = {layers}"));
+			assert!(experiment.validate().is_ok(), "{layers}");
+		}
+		let experiment = parse("[geometry]\nlayers = 12");
+		assert_eq!(invalid_field(&experiment), Some("geometry.layers"));
+	}
+
+	#[test]
+	fn rejects_unusable_settings() {
+		let cases = [
+			("[geometry]\nwidth_top = [200, 100]", "geometry.width_top"),
+			("[materials]\nri = [1.20, 1.50]", "materials.ri"),
+			("[materials]\npartitions = []", "materials.partitions"),
+			("[rays]\nangle_interval = 0.0", "rays.angle_interval"),
+			("[fitness]\nterms = []", "fitness.terms"),
+			(
+				"[optimiser]\npopulation_size = 1",
+				"optimiser.population_size",
+			),
+		];
+		for (text, field) in cases {
+			assert_eq!(invalid_field(&parse(text)), Some(field), "{text}");
+		}
+		assert!(toml::from_str::<Experiment>("[geometry]\nlayer = 10").is_err());
+	}
+
+	#[test]
+	fn materials_become_constraints() {
+		let experiment = parse("[materials]\nri = [1.35, 1.45]\npartitions = [2.0]");
+		let constraints = experiment.constraints();
+		assert_eq!(constraints.ri_range, Some([1.35, 1.45]));
+		assert_eq!(constraints.partitions, Some(vec![2.0]));
+		assert_eq!(constraints.min_ri_step, Some(DEFAULT_MIN_RI_STEP));
+	}
+}
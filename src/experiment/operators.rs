@@ -0,0 +1,308 @@
+use genevo::{
+	algorithm::EvaluatedPopulation,
+	genetic::{Children, FitnessFunction, Genotype, Offspring, Parents},
+	operator::{prelude::*, CrossoverOp, GeneticOperator, MutationOp, ReinsertionOp, SelectionOp},
+	random::Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::mutation::crossover::{
+	AgileCrossBreeder, BlendCrossBreeder, BlockCrossBreeder, OnePointCrossBreeder,
+	TwoPointCrossBreeder,
+};
+
+/// How parents are selected, and how many.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Selection {
+	/// Each parent drawn with a chance in proportion to its fitness.
+	RouletteWheel {
+		/// Fraction of the population selected.
+		ratio: f64,
+		/// Parents bred together.
+		individuals_per_parents: usize,
+	},
+	/// Like the roulette wheel, but all parents drawn in one spin at even
+	/// spacings, so the fittest aren't picked more often than their share.
+	UniversalSampling {
+		/// Fraction of the population selected.
+		ratio: f64,
+		/// Parents bred together.
+		individuals_per_parents: usize,
+	},
+	/// The fittest of a random few.
+	Tournament {
+		/// Fraction of the population selected.
+		ratio: f64,
+		/// Parents bred together.
+		individuals_per_parents: usize,
+		/// Genomes in each tournament.
+		size: usize,
+		/// Chance that the fittest of them wins, rather than the next.
+		probability: f64,
+	},
+	/// The fittest of the population.
+	Maximize {
+		/// Fraction of the population selected.
+		ratio: f64,
+		/// Parents bred together.
+		individuals_per_parents: usize,
+	},
+}
+
+impl Selection {
+	/// Fraction of the population selected as parents.
+	pub fn ratio(self) -> f64 {
+		match self {
+			Self::RouletteWheel { ratio, .. }
+			| Self::UniversalSampling { ratio, .. }
+			| Self::Tournament { ratio, .. }
+			| Self::Maximize { ratio, .. } => ratio,
+		}
+	}
+
+	/// Parents bred together.
+	pub fn individuals_per_parents(self) -> usize {
+		match self {
+			Self::RouletteWheel {
+				individuals_per_parents,
+				..
+			}
+			| Self::UniversalSampling {
+				individuals_per_parents,
+				..
+			}
+			| Self::Tournament {
+				individuals_per_parents,
+				..
+			}
+			| Self::Maximize {
+				individuals_per_parents,
+				..
+			} => individuals_per_parents,
+		}
+	}
+}
+
+impl GeneticOperator for Selection {
+	fn name() -> String {
+		"Configured-Selection".to_string()
+	}
+}
+
+impl<G: Genotype> SelectionOp<G, u64> for Selection {
+	fn select_from<R>(
+		&self,
+		population: &EvaluatedPopulation<G, u64>,
+		rng: &mut R,
+	) -> Vec<Parents<G>>
+	where
+		R: Rng + Sized,
+	{
+		match *self {
+			Self::RouletteWheel {
+				ratio,
+				individuals_per_parents,
+			} => RouletteWheelSelector::new(ratio, individuals_per_parents)
+				.select_from(population, rng),
+			Self::UniversalSampling {
+				ratio,
+				individuals_per_parents,
+			} => UniversalSamplingSelector::new(ratio, individuals_per_parents)
+				.select_from(population, rng),
+			Self::Tournament {
+				ratio,
+				individuals_per_parents,
+				size,
+				probability,
+			} => TournamentSelector::new(ratio, individuals_per_parents, size, probability, false)
+				.select_from(population, rng),
+			Self::Maximize {
+				ratio,
+				individuals_per_parents,
+			} => MaximizeSelector::new(ratio, individuals_per_parents).select_from(population, rng),
+		}
+	}
+}
+
+/// How parents are bred into children.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Crossover {
+	/// Each gene from a random parent, see [`AgileCrossBreeder`].
+	#[default]
+	Uniform,
+
+	/// See [`OnePointCrossBreeder`].
+	OnePoint,
+
+	/// See [`TwoPointCrossBreeder`].
+	TwoPoint,
+
+	/// See [`BlockCrossBreeder`].
+	Block,
+
+	/// See [`BlendCrossBreeder`].
+	Blend {
+		/// How far children can go beyond their parents.
+		alpha: f64,
+	},
+}
+
+impl GeneticOperator for Crossover {
+	fn name() -> String {
+		"Configured-Crossover".to_string()
+	}
+}
+
+impl<G> CrossoverOp<G> for Crossover
+where
+	G: Genotype,
+	AgileCrossBreeder: CrossoverOp<G>,
+	OnePointCrossBreeder: CrossoverOp<G>,
+	TwoPointCrossBreeder: CrossoverOp<G>,
+	BlockCrossBreeder: CrossoverOp<G>,
+	BlendCrossBreeder: CrossoverOp<G>,
+{
+	fn crossover<R>(&self, parents: Parents<G>, rng: &mut R) -> Children<G>
+	where
+		R: Rng + Sized,
+	{
+		match *self {
+			Self::Uniform => AgileCrossBreeder.crossover(parents, rng),
+			Self::OnePoint => OnePointCrossBreeder.crossover(parents, rng),
+			Self::TwoPoint => TwoPointCrossBreeder.crossover(parents, rng),
+			Self::Block => BlockCrossBreeder.crossover(parents, rng),
+			Self::Blend { alpha } => BlendCrossBreeder { alpha }.crossover(parents, rng),
+		}
+	}
+}
+
+/// How genomes are mutated.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Mutation {
+	/// Small steps up or down, see [`BreederValueMutator`].
+	BreederValue {
+		/// Fraction of genes mutated.
+		rate: f64,
+		/// Largest step.
+		range: u8,
+		/// How finely steps shrink from the largest.
+		precision: u8,
+	},
+
+	/// Genes replaced with random values.
+	RandomValue {
+		/// Fraction of genes mutated.
+		rate: f64,
+	},
+}
+
+impl Mutation {
+	/// Fraction of genes mutated.
+	pub fn rate(self) -> f64 {
+		match self {
+			Self::BreederValue { rate, .. } | Self::RandomValue { rate } => rate,
+		}
+	}
+}
+
+impl GeneticOperator for Mutation {
+	fn name() -> String {
+		"Configured-Mutation".to_string()
+	}
+}
+
+impl<G> MutationOp<G> for Mutation
+where
+	G: Genotype<Dna = u8> + BreederGenomeMutation<Dna = u8> + RandomGenomeMutation<Dna = u8>,
+{
+	fn mutate<R>(&self, genome: G, rng: &mut R) -> G
+	where
+		R: Rng + Sized,
+	{
+		match *self {
+			Self::BreederValue {
+				rate,
+				range,
+				precision,
+			} => BreederValueMutator::new(rate, range, precision, u8::MIN, u8::MAX)
+				.mutate(genome, rng),
+			Self::RandomValue { rate } => {
+				RandomValueMutator::new(rate, u8::MIN, u8::MAX).mutate(genome, rng)
+			}
+		}
+	}
+}
+
+/// How offspring are put back into the population.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Reinsertion {
+	/// The fittest of offspring and population are kept.
+	Elitist {
+		/// Fraction of the population replaced.
+		ratio: f64,
+		/// Whether offspring can replace fitter genomes of the population.
+		#[serde(default)]
+		offspring_has_precedence: bool,
+	},
+
+	/// Random members of the population are replaced.
+	Uniform {
+		/// Fraction of the population replaced.
+		ratio: f64,
+	},
+}
+
+impl Reinsertion {
+	/// Fraction of the population replaced by offspring.
+	pub fn ratio(self) -> f64 {
+		match self {
+			Self::Elitist { ratio, .. } | Self::Uniform { ratio } => ratio,
+		}
+	}
+}
+
+/// [`Reinsertion`] with the fitness function that elitism needs.
+#[derive(Clone, Debug)]
+pub struct Reinserter<E> {
+	/// How offspring are put back.
+	pub reinsertion: Reinsertion,
+	/// Fitness of genomes, to tell the fittest.
+	pub fitness: E,
+}
+
+impl<E: Clone> GeneticOperator for Reinserter<E> {
+	fn name() -> String {
+		"Configured-Reinserter".to_string()
+	}
+}
+
+impl<G, E> ReinsertionOp<G, u64> for Reinserter<E>
+where
+	G: Genotype,
+	E: FitnessFunction<G, u64>,
+{
+	fn combine<R>(
+		&self,
+		offspring: &mut Offspring<G>,
+		population: &EvaluatedPopulation<G, u64>,
+		rng: &mut R,
+	) -> Vec<G>
+	where
+		R: Rng + Sized,
+	{
+		match self.reinsertion {
+			Reinsertion::Elitist {
+				ratio,
+				offspring_has_precedence,
+			} => ElitistReinserter::new(self.fitness.clone(), offspring_has_precedence, ratio)
+				.combine(offspring, population, rng),
+			Reinsertion::Uniform { ratio } => {
+				UniformReinserter::new(ratio).combine(offspring, population, rng)
+			}
+		}
+	}
+}
@@ -1,30 +1,64 @@
 use genevo::genetic::FitnessFunction;
 
-use crate::{model::raytrace, paramset::ParamSet};
+use crate::{
+	bounds::OutlineBounds,
+	constraints::Constraints,
+	model::{raytrace, RaySource},
+	paramset::ParamSet,
+};
 
-#[derive(Clone, Copy, Debug)]
-pub struct AgileFitness;
+use spec::FitnessSpec;
 
-impl FitnessFunction<ParamSet, u64> for AgileFitness {
-	fn fitness_of(&self, params: &ParamSet) -> u64 {
-		raytrace(*params).summarise()
+/// Fitness of designs already evaluated, kept across runs.
+pub mod cache;
+/// How performance is turned into fitness, as experiments set it.
+pub mod spec;
+
+/// Fitness of designs, from how they perform and keep to the constraints.
+#[derive(Clone, Debug, Default)]
+pub struct AgileFitness {
+	/// Designs outside of these bounds are given the lowest possible fitness.
+	pub outline: OutlineBounds,
+
+	/// Designs that violate these are rejected or penalised.
+	pub constraints: Constraints,
+
+	/// How performance is turned into fitness.
+	pub spec: FitnessSpec,
+
+	/// Rays traced to measure performance.
+	pub rays: RaySource,
+}
+
+impl AgileFitness {
+	/// Fitness of a perfect design, within `i64` so it fits in TOML integers.
+	pub const HIGHEST: u64 = i64::MAX as _;
+	/// Fitness of rejected designs.
+	pub const LOWEST: u64 = 0;
+}
+
+impl<const N: usize> FitnessFunction<ParamSet<N>, u64> for AgileFitness {
+	fn fitness_of(&self, params: &ParamSet<N>) -> u64 {
+		if !self.outline.contains(*params) {
+			return Self::LOWEST;
+		}
+
+		let fitness = self.spec.fitness(raytrace(*params, self.rays).into());
+		let violations = self.constraints.check(*params);
+		self.constraints.enforce(fitness, Self::LOWEST, &violations)
 	}
 
 	fn average(&self, a: &[u64]) -> u64 {
-		let sum = a
-			.iter()
-			.copied()
-			.reduce(|sum, e| sum.saturating_add(e))
-			.unwrap_or_default();
-		let len = a.len().try_into().unwrap_or(u64::MAX);
-		sum / len
+		// fitness spans half of u64, so this can't be summed in one
+		let sum: u128 = a.iter().map(|f| u128::from(*f)).sum();
+		(sum / (a.len() as u128).max(1)) as _
 	}
 
 	fn highest_possible_fitness(&self) -> u64 {
-		u64::MAX
+		Self::HIGHEST
 	}
 
 	fn lowest_possible_fitness(&self) -> u64 {
-		0
+		Self::LOWEST
 	}
 }
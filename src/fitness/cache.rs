@@ -0,0 +1,343 @@
+use std::{
+	collections::HashMap,
+	fs,
+	hash::{BuildHasher, RandomState},
+	io,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+};
+
+use genevo::genetic::FitnessFunction;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{array::ParamArray, evolution::Encoding, model::MODEL_VERSION, paramset::ParamSet};
+
+use super::AgileFitness;
+
+/// Maps are split so threads evaluating a population rarely wait on each other.
+const SHARDS: usize = 16;
+
+/// How well the cache did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+	/// Lookups of designs that were in the cache.
+	pub hits: u64,
+	/// Lookups of designs that had to be traced.
+	pub misses: u64,
+
+	/// Designs in the cache, for all model settings.
+	pub entries: u64,
+}
+
+impl CacheStats {
+	/// Proportion of lookups that were hits, from 0 to 1.
+	pub fn hit_rate(self) -> f64 {
+		let lookups = self.hits + self.misses;
+		if lookups == 0 {
+			0.0
+		} else {
+			self.hits as f64 / lookups as f64
+		}
+	}
+}
+
+/// Why a cache couldn't be saved or loaded.
+#[derive(Debug, Error)]
+pub enum CacheError {
+	/// The file couldn't be written or read.
+	#[error("{action} fitness cache {path}: {source}")]
+	Io {
+		/// Whether it was being written or read.
+		action: &'static str,
+		/// Path of the cache.
+		path: PathBuf,
+		/// Error writing or reading it.
+		source: io::Error,
+	},
+
+	/// The file isn't a cache.
+	#[error("in fitness cache {path}: {source}")]
+	Json {
+		/// Path of the cache.
+		path: PathBuf,
+		/// Error parsing it.
+		source: serde_json::Error,
+	},
+}
+
+/// Cached fitness, as stored on disk: model settings hash, genes, fitness.
+type Entry = (u64, Vec<u8>, u64);
+
+/// Fitness of designs already evaluated, shared between threads.
+///
+/// Designs are keyed along with a hash of the settings that affect fitness,
+/// so a cache saved by one experiment can be loaded by another without giving
+/// it wrong answers.
+#[derive(Debug, Default)]
+pub struct FitnessCache<const N: usize> {
+	shards: [Mutex<HashMap<(u64, ParamSet<N>), u64>>; SHARDS],
+	hasher: RandomState,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+impl<const N: usize> FitnessCache<N> {
+	fn shard(&self, key: &(u64, ParamSet<N>)) -> &Mutex<HashMap<(u64, ParamSet<N>), u64>> {
+		&self.shards[self.hasher.hash_one(key) as usize % SHARDS]
+	}
+
+	/// Fitness of a design, computing and keeping it if it isn't known yet.
+	pub fn get_or_insert(
+		&self,
+		config: u64,
+		params: ParamSet<N>,
+		compute: impl FnOnce() -> u64,
+	) -> u64 {
+		let key = (config, params);
+		let shard = self.shard(&key);
+		if let Some(fitness) = shard.lock().unwrap().get(&key) {
+			self.hits.fetch_add(1, Ordering::Relaxed);
+			return *fitness;
+		}
+
+		// computed without the lock, so other designs can be looked up meanwhile
+		self.misses.fetch_add(1, Ordering::Relaxed);
+		let fitness = compute();
+		shard.lock().unwrap().insert(key, fitness);
+		fitness
+	}
+
+	/// Lookups so far, and the designs held.
+	pub fn stats(&self) -> CacheStats {
+		CacheStats {
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+			entries: self
+				.shards
+				.iter()
+				.map(|shard| shard.lock().unwrap().len() as u64)
+				.sum(),
+		}
+	}
+
+	/// Carries on counting hits and misses from earlier counts, as when
+	/// resuming a run.
+	pub fn restore(&self, stats: CacheStats) {
+		self.hits.store(stats.hits, Ordering::Relaxed);
+		self.misses.store(stats.misses, Ordering::Relaxed);
+	}
+
+	/// Adds the designs from a saved cache, if there is one.
+	pub fn load(&self, path: &Path) -> Result<(), CacheError> {
+		let contents = match fs::read_to_string(path) {
+			Ok(contents) => contents,
+			Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+			Err(source) => {
+				return Err(CacheError::Io {
+					action: "reading",
+					path: path.into(),
+					source,
+				})
+			}
+		};
+		let entries: Vec<Entry> =
+			serde_json::from_str(&contents).map_err(|source| CacheError::Json {
+				path: path.into(),
+				source,
+			})?;
+
+		// caches for other numbers of layers have nothing to offer
+		for (config, genes, fitness) in entries {
+			if let Some(genes) = ParamArray::<N>::from_genes(&genes) {
+				let key = (config, genes.into());
+				self.shard(&key).lock().unwrap().insert(key, fitness);
+			}
+		}
+		Ok(())
+	}
+
+	/// Writes all designs, for later runs to [`load`](Self::load).
+	pub fn save(&self, path: &Path) -> Result<(), CacheError> {
+		let mut entries: Vec<Entry> = Vec::new();
+		for shard in &self.shards {
+			entries.extend(
+				shard
+					.lock()
+					.unwrap()
+					.iter()
+					.map(|((config, params), fitness)| {
+						(*config, ParamArray::from(*params).genes(), *fitness)
+					}),
+			);
+		}
+		entries.sort_unstable();
+
+		let io = |source| CacheError::Io {
+			action: "writing",
+			path: path.into(),
+			source,
+		};
+		let contents = serde_json::to_string(&entries).map_err(|source| CacheError::Json {
+			path: path.into(),
+			source,
+		})?;
+		let partial = path.with_extension("partial");
+		fs::write(&partial, contents).map_err(io)?;
+		fs::rename(&partial, path).map_err(io)
+	}
+}
+
+/// 64-bit FNV-1a, which unlike std's hasher is the same across builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+		(hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+	})
+}
+
+impl AgileFitness {
+	/// Hash of everything that affects fitness, including the model version.
+	pub fn config_hash(&self) -> u64 {
+		let settings = serde_json::to_string(&(
+			MODEL_VERSION,
+			&self.outline,
+			&self.constraints,
+			&self.spec,
+			&self.rays,
+		))
+		.expect("fitness settings are always serialisable");
+		fnv1a(settings.as_bytes())
+	}
+}
+
+/// Fitness function that looks designs up in a cache before tracing them.
+#[derive(Clone, Debug)]
+pub struct Cached<const N: usize> {
+	/// Fitness of designs that aren't in the cache.
+	pub fitness: AgileFitness,
+	/// Cache, which can be shared with other runs.
+	pub cache: Arc<FitnessCache<N>>,
+	config: u64,
+}
+
+impl<const N: usize> Cached<N> {
+	/// Looks up designs evaluated with the same settings as this fitness.
+	pub fn new(fitness: AgileFitness, cache: Arc<FitnessCache<N>>) -> Self {
+		Self {
+			config: fitness.config_hash(),
+			fitness,
+			cache,
+		}
+	}
+}
+
+/// Genomes are looked up by the design they stand for, so different
+/// [`Encoding`]s share what's known.
+impl<const N: usize, G: Encoding<N>> FitnessFunction<G, u64> for Cached<N> {
+	fn fitness_of(&self, genome: &G) -> u64 {
+		let params = genome.design(&self.fitness.constraints);
+		self.cache
+			.get_or_insert(self.config, params, || self.fitness.fitness_of(&params))
+	}
+
+	fn average(&self, a: &[u64]) -> u64 {
+		FitnessFunction::<ParamSet<N>, u64>::average(&self.fitness, a)
+	}
+
+	fn highest_possible_fitness(&self) -> u64 {
+		AgileFitness::HIGHEST
+	}
+
+	fn lowest_possible_fitness(&self) -> u64 {
+		AgileFitness::LOWEST
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+
+	use super::*;
+
+	fn path(name: &str) -> PathBuf {
+		env::temp_dir().join(format!("agile-{name}-{}.json", std::process::id()))
+	}
+
+	#[test]
+	fn hashes_settings_stably() {
+		assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+		assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+		let fitness = AgileFitness::default();
+		assert_eq!(fitness.config_hash(), fitness.clone().config_hash());
+		let mut other = fitness.clone();
+		other.rays.entry_interval /= 2.0;
+		assert_ne!(other.config_hash(), fitness.config_hash());
+	}
+
+	#[test]
+	fn round_trips_through_a_file() {
+		let path = path("cache-round-trip");
+		let cache = FitnessCache::<10>::default();
+		let designs = [ParamSet::nth(12), ParamSet::nth(3456)];
+		for (n, design) in designs.into_iter().enumerate() {
+			cache.get_or_insert(7, design, || n as u64 + 1);
+		}
+		cache.save(&path).unwrap();
+
+		let loaded = FitnessCache::<10>::default();
+		loaded.load(&path).unwrap();
+		fs::remove_file(&path).unwrap();
+		assert_eq!(loaded.stats().entries, 2);
+		for (n, design) in designs.into_iter().enumerate() {
+			assert_eq!(
+				loaded.get_or_insert(7, design, || unreachable!()),
+				n as u64 + 1
+			);
+		}
+		// same design under other settings
+		assert_eq!(loaded.get_or_insert(8, designs[0], || 99), 99);
+		assert_eq!((loaded.stats().hits, loaded.stats().misses), (2, 1));
+
+		// no cache yet is an empty one
+		loaded.load(&path).unwrap();
+	}
+
+	#[test]
+	fn ignores_fitness_from_other_model_versions() {
+		let fitness = AgileFitness::default();
+		let settings = |version: u32| {
+			serde_json::to_string(&(
+				version,
+				&fitness.outline,
+				&fitness.constraints,
+				&fitness.spec,
+				&fitness.rays,
+			))
+			.unwrap()
+		};
+		assert_eq!(
+			fnv1a(settings(MODEL_VERSION).as_bytes()),
+			fitness.config_hash()
+		);
+
+		let path = path("cache-old-version");
+		let design = ParamSet::<10>::nth(42);
+		let old: Vec<Entry> = vec![(
+			fnv1a(settings(MODEL_VERSION - 1).as_bytes()),
+			ParamArray::from(design).genes(),
+			1,
+		)];
+		fs::write(&path, serde_json::to_string(&old).unwrap()).unwrap();
+		let cache = Arc::new(FitnessCache::default());
+		cache.load(&path).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		let cached = Cached::new(fitness.clone(), cache.clone());
+		assert_eq!(cached.fitness_of(&design), fitness.fitness_of(&design));
+		assert_eq!(cache.stats().misses, 1);
+	}
+}
@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::PhysicalPerformance;
+
+/// A measure of [`Performance`](crate::model::Performance), in physical units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+	/// Proportion of rays that exit at the bottom, from 0 to 1.
+	ExitRatio,
+
+	/// Average exit angle to the normal, in degrees.
+	ExitAngle,
+
+	/// Distance light travels inside the lens for each ray that exits at the
+	/// bottom, in mm, scaled by the number of rays traced.
+	///
+	/// That's the total travel of the rays out of the bottom over the exit
+	/// ratio, so designs that let fewer rays out don't travel less for it.
+	LightTravel,
+
+	/// Top width over bottom width.
+	Concentration,
+
+	/// Geometric concentration times the exit ratio.
+	OpticalConcentration,
+}
+
+impl Metric {
+	/// Value of the metric for this performance.
+	pub fn value(self, perf: PhysicalPerformance) -> f64 {
+		match self {
+			Self::ExitRatio => perf.exit_ratio,
+			Self::ExitAngle => perf.exit_angle,
+			Self::LightTravel if perf.exit_ratio > 0.0 => perf.light_travel / perf.exit_ratio,
+			Self::LightTravel => f64::INFINITY,
+			Self::Concentration => perf.concentration,
+			Self::OpticalConcentration => perf.exit_ratio * perf.concentration,
+		}
+	}
+
+	/// Default worst and best values, for normalisation.
+	fn range(self) -> (f64, f64) {
+		match self {
+			Self::ExitRatio => (0.0, 1.0),
+			Self::ExitAngle => (90.0, 0.0),
+			Self::LightTravel => (100_000.0, 0.0),
+			Self::Concentration | Self::OpticalConcentration => (0.0, 5.0),
+		}
+	}
+}
+
+/// One metric's contribution to fitness.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Term {
+	/// What's measured.
+	pub metric: Metric,
+
+	/// Relative importance, when combining by weight.
+	#[serde(default = "one")]
+	pub weight: f64,
+
+	/// Value normalised to 0, if not the metric's default.
+	pub worst: Option<f64>,
+
+	/// Value normalised to 1, if not the metric's default.
+	pub best: Option<f64>,
+}
+
+fn one() -> f64 {
+	1.0
+}
+
+impl Term {
+	/// Term over the metric's default range.
+	pub fn new(metric: Metric, weight: f64) -> Self {
+		Self {
+			metric,
+			weight,
+			worst: None,
+			best: None,
+		}
+	}
+
+	fn range(self) -> (f64, f64) {
+		let (worst, best) = self.metric.range();
+		(self.worst.unwrap_or(worst), self.best.unwrap_or(best))
+	}
+
+	/// Value of the metric, scaled so 0 is worst and 1 is best.
+	pub fn normalise(self, perf: PhysicalPerformance) -> f64 {
+		let (worst, best) = self.range();
+		if worst == best {
+			return 0.0;
+		}
+		((self.metric.value(perf) - worst) / (best - worst)).clamp(0.0, 1.0)
+	}
+}
+
+/// Reduces fitness when a metric is worse than a limit.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Penalty {
+	/// What's measured.
+	pub metric: Metric,
+
+	/// Values worse than this are penalised.
+	pub limit: f64,
+
+	/// Fitness is scaled down by `1 - weight × excess`, with the excess
+	/// relative to the metric's default range.
+	pub weight: f64,
+}
+
+impl Penalty {
+	/// How far past the limit the metric is, relative to its range.
+	fn excess(self, perf: PhysicalPerformance) -> f64 {
+		let (worst, best) = self.metric.range();
+		let past = (self.limit - self.metric.value(perf)) / (best - worst);
+		past.max(0.0)
+	}
+}
+
+/// How terms are combined into one fitness value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Combine {
+	/// Weighted average of all terms.
+	#[default]
+	Weighted,
+
+	/// Terms in order of priority: later terms only break ties of earlier ones.
+	///
+	/// Each term gets an equal share of the 64 bits of fitness, so ties are
+	/// to that precision. Weights are ignored.
+	Lexicographic,
+}
+
+/// Composition of fitness from [`Performance`](crate::model::Performance)
+/// metrics.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FitnessSpec {
+	/// How terms are combined.
+	#[serde(default)]
+	pub combine: Combine,
+	/// Metrics that make up fitness, in order of priority.
+	pub terms: Vec<Term>,
+	/// Limits on metrics, applied after the terms are combined.
+	#[serde(default)]
+	pub penalties: Vec<Penalty>,
+}
+
+impl Default for FitnessSpec {
+	/// Mostly optical concentration, then exit angle, then light travel.
+	fn default() -> Self {
+		Self {
+			combine: Combine::Weighted,
+			terms: vec![
+				Term::new(Metric::OpticalConcentration, 10.0),
+				Term::new(Metric::ExitAngle, 5.0),
+				Term::new(Metric::LightTravel, 1.0),
+			],
+			penalties: Vec::new(),
+		}
+	}
+}
+
+impl FitnessSpec {
+	/// Factor by which penalties scale fitness, from 0 to 1.
+	fn penalty_factor(&self, perf: PhysicalPerformance) -> f64 {
+		let penalty: f64 = self
+			.penalties
+			.iter()
+			.map(|p| p.weight * p.excess(perf))
+			.sum();
+		(1.0 - penalty).clamp(0.0, 1.0)
+	}
+
+	/// Weighted average of the terms, from 0 to 1.
+	fn weighted(&self, perf: PhysicalPerformance) -> f64 {
+		let total_weight: f64 = self.terms.iter().map(|t| t.weight).sum();
+		if total_weight <= 0.0 {
+			return 0.0;
+		}
+
+		let sum: f64 = self
+			.terms
+			.iter()
+			.map(|t| t.weight * t.normalise(perf))
+			.sum();
+		self.penalty_factor(perf) * sum / total_weight
+	}
+
+	/// Combined fitness, from 0 to `i64::MAX` so it fits in TOML integers.
+	///
+	/// Designs that no ray goes through get 0, whatever the terms.
+	pub fn fitness(&self, perf: PhysicalPerformance) -> u64 {
+		if perf.exit_ratio <= 0.0 {
+			return 0;
+		}
+		let fitness: u64 = match self.combine {
+			Combine::Weighted => (self.weighted(perf) * u64::MAX as f64) as _,
+			Combine::Lexicographic => self.lexicographic(perf),
+		};
+		fitness >> 1
+	}
+
+	fn lexicographic(&self, perf: PhysicalPerformance) -> u64 {
+		let terms = self.terms.len().clamp(1, 64) as u32;
+		let bits = 64 / terms;
+		let max = (1_u128 << bits) - 1;
+
+		let factor = self.penalty_factor(perf);
+
+		let packed = self.terms.iter().take(64).fold(0_u128, |packed, term| {
+			let value = (term.normalise(perf) * factor * max as f64).round() as u128;
+			(packed << bits) | value.min(max)
+		});
+		// left-align, so fitness spans the full range whatever the bits used
+		(packed << (64 - bits * terms)) as u64
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn perf(exit_ratio: f64, light_travel: f64) -> PhysicalPerformance {
+		PhysicalPerformance {
+			exit_ratio,
+			exit_angle: 30.0,
+			light_travel,
+			concentration: 2.0,
+		}
+	}
+
+	#[test]
+	fn normalises_light_travel_per_exiting_ray() {
+		let term = Term::new(Metric::LightTravel, 1.0);
+		// half the rays travelling as far each as all of them do
+		assert_eq!(Metric::LightTravel.value(perf(0.5, 20_000.0)), 40_000.0);
+		assert_eq!(
+			term.normalise(perf(0.5, 20_000.0)),
+			term.normalise(perf(1.0, 40_000.0))
+		);
+		assert_eq!(term.normalise(perf(1.0, 25_000.0)), 0.75);
+		assert_eq!(term.normalise(perf(0.0, 0.0)), 0.0);
+	}
+
+	#[test]
+	fn nothing_through_is_worst() {
+		let spec = FitnessSpec::default();
+		assert_eq!(spec.fitness(perf(0.0, 0.0)), 0);
+		assert!(spec.fitness(perf(0.01, 1000.0)) > 0);
+	}
+
+	#[test]
+	fn weights_terms() {
+		let spec = FitnessSpec {
+			combine: Combine::Weighted,
+			terms: vec![
+				Term::new(Metric::ExitRatio, 3.0),
+				Term::new(Metric::ExitAngle, 1.0),
+			],
+			penalties: Vec::new(),
+		};
+		// (3 × 0.5 + 1 × 2/3) / 4
+		let expected = (3.0 * 0.5 + 60.0 / 90.0) / 4.0;
+		let fitness = spec.fitness(perf(0.5, 1000.0)) as f64 / i64::MAX as f64;
+		assert!((fitness - expected).abs() < 1e-9, "{fitness} ≠ {expected}");
+	}
+
+	#[test]
+	fn packs_terms_in_order_of_priority() {
+		let spec = FitnessSpec {
+			combine: Combine::Lexicographic,
+			terms: vec![
+				Term::new(Metric::ExitRatio, 1.0),
+				Term::new(Metric::ExitAngle, 1.0),
+			],
+			penalties: Vec::new(),
+		};
+		let (mut better, mut worse) = (perf(0.6, 1000.0), perf(0.5, 1000.0));
+		better.exit_angle = 80.0;
+		worse.exit_angle = 0.0;
+		assert!(spec.fitness(better) > spec.fitness(worse));
+
+		// each term gets 32 bits, the first the high ones, shifted into i64
+		let full = perf(1.0, 1000.0);
+		let mut flat = full;
+		flat.exit_angle = 0.0;
+		assert_eq!(spec.fitness(flat), i64::MAX as u64);
+		flat.exit_angle = 90.0;
+		assert_eq!(spec.fitness(flat), (u64::from(u32::MAX) << 32) >> 1);
+	}
+}
@@ -0,0 +1,287 @@
+use std::{fmt::Display, num::NonZeroU8};
+
+use genevo::genetic::Genotype;
+
+use crate::{
+	constraints::Constraints,
+	model::units::model_ri_to_real_ri,
+	paramset::{normalise_partition_thickness, ParamSet, DEFAULT_LAYERS, MAXIMUM_RI, MINIMUM_RI},
+	sidewall::Sidewall,
+};
+
+/// Number of genes of a [`GradientSet`].
+pub const GENES: usize = 10 + 2 * Sidewall::GENES;
+
+/// Where the genes after those of the sidewalls start.
+const WALLS_END: usize = 4 + 2 * Sidewall::GENES;
+
+/// How RI goes from the top layer to the bottom one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Curve {
+	/// Even steps.
+	#[default]
+	Linear,
+
+	/// `n(z) = n0 + (n1 - n0) (z/H)^p`
+	Power,
+
+	/// Cubic Bézier, with depth as the curve parameter.
+	Bezier,
+}
+
+impl Curve {
+	/// Every curve, in the order of their genes.
+	pub const ALL: [Self; 3] = [Self::Linear, Self::Power, Self::Bezier];
+
+	fn from_gene(gene: u8) -> Self {
+		Self::ALL[usize::from(gene) % Self::ALL.len()]
+	}
+
+	fn gene(self) -> u8 {
+		self as _
+	}
+}
+
+/// Design whose layer RIs follow a curve with few parameters, as an
+/// alternative genome to [`ParamSet`].
+///
+/// Values are stored as they are, like in [`ParamSet`], and brought into range
+/// when converted with [`params`](Self::params).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GradientSet<const N: usize = DEFAULT_LAYERS> {
+	/// Width of the top (entry) face of the lens in mm.
+	pub width_top: u8,
+
+	/// Width of the bottom (exit) face of the lens in mm.
+	pub width_bottom: u8,
+
+	/// Shapes of the left and right sidewalls.
+	pub sidewalls: [Sidewall; 2],
+
+	/// Thickness of each layer in tenths of mm above 3.0mm.
+	pub layers_thickness: u8,
+
+	/// Thickness of each partition in tenths of mm.
+	pub partitions_thickness: u8,
+
+	/// Number of layers, from 1 to N.
+	pub layers: u8,
+
+	/// How RI goes from the top layer to the bottom one.
+	pub curve: Curve,
+
+	/// RI of the top layer, in hundredths above 0.99.
+	pub top: u8,
+
+	/// RI of the bottom layer, in hundredths above 0.99.
+	pub bottom: u8,
+
+	/// For [`Curve::Power`], the first is the exponent as `2^((s - 128) / 32)`.
+	/// For [`Curve::Bezier`], both are control points, in 255ths of the way
+	/// from the top RI to the bottom one.
+	pub shape: [u8; 2],
+}
+
+impl<const N: usize> Genotype for GradientSet<N> {
+	type Dna = u8;
+}
+
+impl<const N: usize> GradientSet<N> {
+	/// Exponent of a [`Curve::Power`].
+	pub fn exponent(self) -> f64 {
+		2_f64.powf((f64::from(self.shape[0]) - 128.0) / 32.0)
+	}
+
+	/// Fraction of the way from the top RI to the bottom one, at a depth
+	/// fraction.
+	fn fraction(self, depth: f64) -> f64 {
+		match self.curve {
+			Curve::Linear => depth,
+			Curve::Power => depth.powf(self.exponent()),
+			Curve::Bezier => {
+				let [c1, c2] = self.shape.map(|c| f64::from(c) / 255.0);
+				let up = 1.0 - depth;
+				3.0 * up * up * depth * c1 + 3.0 * up * depth * depth * c2 + depth.powi(3)
+			}
+		}
+	}
+
+	/// Design this stands for, with RIs rounded to hundredths and then
+	/// [quantised](Constraints::quantise) to the liquids and partitions
+	/// available.
+	pub fn params(self, constraints: &Constraints) -> ParamSet<N> {
+		let count = usize::from(self.layers).clamp(1, N);
+		let top = f64::from(self.top.clamp(MINIMUM_RI, MAXIMUM_RI));
+		let bottom = f64::from(self.bottom.clamp(MINIMUM_RI, MAXIMUM_RI));
+
+		let mut layers = [None; N];
+		for (n, layer) in layers[..count].iter_mut().enumerate() {
+			let depth = if count == 1 {
+				0.0
+			} else {
+				n as f64 / (count - 1) as f64
+			};
+			let ri = top + (bottom - top) * self.fraction(depth);
+			*layer = NonZeroU8::new(
+				ri.round()
+					.clamp(f64::from(MINIMUM_RI), f64::from(MAXIMUM_RI)) as u8,
+			);
+		}
+
+		let mut params = ParamSet {
+			width_top: self.width_top.max(1),
+			width_bottom: self.width_bottom.max(1),
+			sidewalls: self.sidewalls,
+			layers_thickness: self.layers_thickness,
+			partitions_thickness: normalise_partition_thickness(self.partitions_thickness),
+			layers,
+			materials: [0; N],
+			open: 0,
+		};
+		constraints.quantise(&mut params);
+		params
+	}
+
+	/// Genes in order: widths, thicknesses, sidewalls, then the curve.
+	pub fn genes(self) -> [u8; GENES] {
+		let mut genes = [0; GENES];
+		genes[..4].copy_from_slice(&[
+			self.width_top,
+			self.width_bottom,
+			self.layers_thickness,
+			self.partitions_thickness,
+		]);
+		genes[4..4 + Sidewall::GENES].copy_from_slice(&self.sidewalls[0].genes());
+		genes[4 + Sidewall::GENES..WALLS_END].copy_from_slice(&self.sidewalls[1].genes());
+		genes[WALLS_END..].copy_from_slice(&[
+			self.layers,
+			self.curve.gene(),
+			self.top,
+			self.bottom,
+			self.shape[0],
+			self.shape[1],
+		]);
+		genes
+	}
+
+	/// Inverse of [`genes`](Self::genes).
+	pub fn from_genes(genes: [u8; GENES]) -> Self {
+		let (mut left, mut right) = ([0; Sidewall::GENES], [0; Sidewall::GENES]);
+		left.copy_from_slice(&genes[4..4 + Sidewall::GENES]);
+		right.copy_from_slice(&genes[4 + Sidewall::GENES..WALLS_END]);
+		let [layers, curve, top, bottom, shape0, shape1] = genes[WALLS_END..]
+			.try_into()
+			.expect("six genes after the sidewalls");
+		Self {
+			width_top: genes[0],
+			width_bottom: genes[1],
+			sidewalls: [left, right].map(Sidewall::from_genes),
+			layers_thickness: genes[2],
+			partitions_thickness: genes[3],
+			layers,
+			curve: Curve::from_gene(curve),
+			top,
+			bottom,
+			shape: [shape0, shape1],
+		}
+	}
+}
+
+/// Layers are shown before they're quantised to the liquids available.
+impl<const N: usize> Display for GradientSet<N> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let ri = |ri: u8| model_ri_to_real_ri(ri.clamp(MINIMUM_RI, MAXIMUM_RI));
+		write!(f, "{} ", self.params(&Constraints::default()))?;
+		match self.curve {
+			Curve::Linear => write!(f, "linear")?,
+			Curve::Power => write!(f, "power({:.2})", self.exponent())?,
+			Curve::Bezier => write!(
+				f,
+				"bezier({:.3},{:.3})",
+				f64::from(self.shape[0]) / 255.0,
+				f64::from(self.shape[1]) / 255.0
+			)?,
+		}
+		write!(f, " {:.2}→{:.2}", ri(self.top), ri(self.bottom))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn gradient(curve: Curve, layers: u8, top: u8, bottom: u8) -> GradientSet {
+		GradientSet {
+			layers,
+			curve,
+			top,
+			bottom,
+			shape: [40, 200],
+			..GradientSet::from_genes([0; GENES])
+		}
+	}
+
+	#[test]
+	fn ends_at_the_top_and_bottom_ris() {
+		for curve in Curve::ALL {
+			let params = gradient(curve, 6, 35, 50).params(&Constraints::default());
+			let ris: Vec<u8> = params.layers.iter().flatten().map(|ri| ri.get()).collect();
+			assert_eq!(ris.len(), 6);
+			assert_eq!((ris[0], ris[5]), (35, 50), "{curve:?}");
+			assert!(ris.windows(2).all(|w| w[0] <= w[1]), "{curve:?} {ris:?}");
+		}
+
+		// out of range ends are brought in
+		let params = gradient(Curve::Linear, 2, 0, u8::MAX).params(&Constraints::default());
+		assert_eq!(
+			params.layers[..2],
+			[NonZeroU8::new(MINIMUM_RI), NonZeroU8::new(MAXIMUM_RI)]
+		);
+	}
+
+	#[test]
+	fn clamps_layers_to_those_available() {
+		let count = |layers| {
+			gradient(Curve::Linear, layers, 40, 45)
+				.params(&Constraints::default())
+				.len()
+		};
+		assert_eq!(count(0), 1);
+		assert_eq!(count(1), 1);
+		assert_eq!(count(7), 7);
+		assert_eq!(count(DEFAULT_LAYERS as u8), DEFAULT_LAYERS);
+		assert_eq!(count(u8::MAX), DEFAULT_LAYERS);
+	}
+
+	#[test]
+	fn quantises_to_what_is_available() {
+		let constraints = Constraints {
+			partitions: Some(vec![2.5]),
+			ri_range: Some([1.40, 1.45]),
+			..Constraints::default()
+		};
+		let params = gradient(Curve::Linear, 4, 30, 60).params(&constraints);
+		assert_eq!(params.partitions_thickness, 25);
+		let ris: Vec<u8> = params.layers.iter().flatten().map(|ri| ri.get()).collect();
+		assert_eq!((ris[0], ris[3]), (41, 46));
+	}
+
+	#[test]
+	fn round_trips_through_genes() {
+		let genes: [u8; GENES] = std::array::from_fn(|locus| (locus * 37 + 11) as u8);
+		let gradient = GradientSet::<DEFAULT_LAYERS>::from_genes(genes);
+		assert_eq!(GradientSet::from_genes(gradient.genes()), gradient);
+
+		// only the walls and the curve kind are normalised
+		let walls_and_curve = 4..=WALLS_END + 1;
+		for (locus, gene) in gradient.genes().into_iter().enumerate() {
+			if !walls_and_curve.contains(&locus) {
+				assert_eq!(gene, genes[locus], "gene {locus}");
+			}
+		}
+		assert_eq!(
+			gradient.curve,
+			Curve::ALL[usize::from(genes[WALLS_END + 1]) % 3]
+		);
+	}
+}
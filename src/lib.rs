@@ -0,0 +1,77 @@
+//! Model and optimiser for hybrid-liquid AGILEs: lenses made of layers of
+//! liquids of different refractive indices, separated by thin partitions.
+//!
+//! A design is a [`ParamSet`], which is compact enough to be a genome, or a
+//! [`Design`] in physical units. [`raytrace`] measures its [`Performance`],
+//! which [`AgileFitness`] turns into a fitness for the genetic algorithm, or
+//! [`Nsga2`] uses as objectives to find trade-offs between designs. An
+//! [`Experiment`] holds all the settings of a run.
+
+#![warn(missing_docs)]
+
+/// Bounds on the outline of designs.
+pub mod bounds;
+
+/// Random designs within bounds.
+pub mod builder;
+
+/// Liquids available to fill layers with.
+pub mod catalog;
+
+/// Manufacturability constraints.
+pub mod constraints;
+
+/// Liquids diffusing into each other over time.
+pub mod diffusion;
+
+/// Designs in physical units.
+pub mod design;
+
+/// Runs of the genetic algorithm that can be checkpointed.
+pub mod evolution;
+
+/// Settings for a run, loaded from TOML.
+pub mod experiment;
+
+/// Fitness of designs, from their performance.
+pub mod fitness;
+
+/// Designs whose layer RIs follow a curve, as an alternative genome.
+pub mod gradient;
+
+/// Loads on partitions from the liquids resting on them.
+pub mod mechanics;
+
+/// Ray tracing through designs.
+pub mod model;
+
+/// Recipes for mixing the RIs of layers from two liquids.
+pub mod mixing;
+
+/// Genetic operators and repair of designs.
+pub mod mutation;
+
+/// Designs as genomes.
+pub mod paramset;
+
+/// Multi-objective optimisation.
+pub mod pareto;
+
+/// Records of runs.
+pub mod record;
+
+/// Shapes of the sidewalls of designs.
+pub mod sidewall;
+
+/// Designs at operating temperatures other than the one they're given at.
+pub mod thermal;
+
+mod array;
+
+pub use design::Design;
+pub use evolution::Evolution;
+pub use experiment::Experiment;
+pub use fitness::AgileFitness;
+pub use model::{raytrace, Performance, PhysicalPerformance, RaySource};
+pub use paramset::ParamSet;
+pub use pareto::{Front, Nsga2};
@@ -1,106 +1,45 @@
-use genevo::{ga::builder::EmptyGeneticAlgorithmBuilder, operator::prelude::*, prelude::*};
+use std::{env, path::Path};
 
-use fitness::AgileFitness;
-use model::raytrace;
-use mutation::crossover::AgileCrossBreeder;
-use paramset::ParamSet;
+use agile_model::{evolution::checkpoint, Experiment};
+use cli::{Args, CliError, USAGE};
 
-mod array;
-mod builder;
-mod fitness;
-mod model;
-mod mutation;
-mod paramset;
+mod cli;
 
-const POPULATION_SIZE: usize = 200;
-const GENERATION_LIMIT: u64 = 2000;
-const NUM_INDIVIDUALS_PER_PARENTS: usize = 3;
-const SELECTION_RATIO: f64 = 0.7;
-const MUTATION_RATE: f64 = 0.05;
-const REINSERTION_RATIO: f64 = 0.7;
-
-fn main() {
-	{
-		let def = ParamSet::default();
-		let ray = raytrace(def);
-		let sum = ray.summarise();
-		println!("min: {def}\n{sum} = {ray:?}");
-	}
-	{
-		let def = ParamSet::nth(ParamSet::MAX_POSSIBILITIES);
-		let ray = raytrace(def);
-		let sum = ray.summarise();
-		println!("max: {def}\n{sum} = {ray:?}");
+fn try_main() -> Result<(), CliError> {
+	let args = Args::parse(env::args_os().skip(1))?;
+	if args.help || args.command.is_none() {
+		print!("{USAGE}");
+		return Ok(());
 	}
 
-	let initial_population = build_population()
-		.with_genome_builder(builder::RandomBuilder)
-		.of_size(POPULATION_SIZE)
-		.uniform_at_random();
-
-	let alg: EmptyGeneticAlgorithmBuilder<ParamSet, _> = genetic_algorithm();
-	let mut sim = simulate(
-		alg.with_evaluation(AgileFitness)
-			.with_selection(RouletteWheelSelector::new(
-				SELECTION_RATIO,
-				NUM_INDIVIDUALS_PER_PARENTS,
-			))
-			.with_crossover(AgileCrossBreeder)
-			.with_mutation(BreederValueMutator::new(
-				MUTATION_RATE,
-				1,
-				3,
-				u8::MIN,
-				u8::MAX,
-			))
-			.with_reinsertion(ElitistReinserter::new(
-				AgileFitness,
-				false,
-				REINSERTION_RATIO,
-			))
-			.with_initial_population(initial_population)
-			.build(),
-	)
-	.until(or(
-		FitnessLimit::new(AgileFitness.highest_possible_fitness()),
-		GenerationLimit::new(GENERATION_LIMIT),
-	))
-	.build();
-
-	loop {
-		let result = sim.step();
-		match result {
-			Ok(SimResult::Intermediate(step)) => {
-				let evaluated_population = step.result.evaluated_population;
-				let best_solution = step.result.best_solution;
-				println!(
-					"Step: generation: {:04}, average_fitness: {}, best fitness: {}, duration: {}, processing_time: {}",
-					step.iteration,
-					evaluated_population.average_fitness(),
-					best_solution.solution.fitness,
-					step.duration,
-					step.processing_time
-				);
-				println!("{}", best_solution.solution.genome);
-			}
-			Ok(SimResult::Final(step, processing_time, duration, stop_reason)) => {
-				let best_solution = step.result.best_solution;
-				println!("{}", stop_reason);
-				println!(
-					"Final result after {}: generation: {}, best solution with fitness {} found in generation {}, processing_time: {}",
-					duration,
-					step.iteration,
-					best_solution.solution.fitness,
-					best_solution.generation,
-					processing_time
-				);
-				println!("{}", best_solution.solution.genome);
-				break;
-			}
-			Err(error) => {
-				println!("{}", error);
-				break;
+	let config = args
+		.option("config")
+		.map(|path| Experiment::load(Path::new(path)))
+		.transpose()?;
+	let experiment = match args.option("resume") {
+		Some(path) => {
+			let resumed = checkpoint::experiment(Path::new(path))?;
+			if config.is_some_and(|config| config != resumed) {
+				return Err(CliError::ResumeConfig);
 			}
+			resumed
 		}
+		None => config.unwrap_or_default(),
+	};
+
+	match experiment.geometry.layers {
+		5 => cli::run::<5>(&experiment, &args),
+		10 => cli::run::<10>(&experiment, &args),
+		15 => cli::run::<15>(&experiment, &args),
+		20 => cli::run::<20>(&experiment, &args),
+		30 => cli::run::<30>(&experiment, &args),
+		layers => unreachable!("{layers} layers should have failed validation"),
+	}
+}
+
+fn main() {
+	if let Err(err) = try_main() {
+		eprintln!("{err}");
+		std::process::exit(2);
 	}
 }
@@ -0,0 +1,193 @@
+use serde::Serialize;
+
+use crate::{
+	catalog::Catalog,
+	model::{curve::Curve, slabs, Slab},
+	paramset::ParamSet,
+};
+
+/// Acceleration due to gravity, in m/s².
+const GRAVITY: f64 = 9.81;
+
+/// Density of the acrylic partitions are cut from, in g/mL.
+pub const PARTITION_DENSITY: f64 = 1.19;
+
+/// Young's modulus of acrylic, in MPa.
+pub const PARTITION_MODULUS: f64 = 3200.0;
+
+/// Poisson's ratio of acrylic.
+pub const PARTITION_POISSON: f64 = 0.37;
+
+/// Density of liquids that aren't in the catalog, in g/mL: that of water.
+pub const DEFAULT_DENSITY: f64 = 1.0;
+
+/// How a partition bends under the liquid resting on it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct PartitionLoad {
+	/// Layer the partition is above, from 0 at the top.
+	pub layer: usize,
+
+	/// Width between the walls, in mm.
+	pub span: f64,
+
+	/// Pressure from the liquid and the partition's own weight, in Pa.
+	pub pressure: f64,
+
+	/// Sag at the middle of the span, in mm.
+	pub deflection: f64,
+
+	/// Largest bending stress, in MPa.
+	pub stress: f64,
+}
+
+/// Loads on each partition of a design, from the top down.
+///
+/// A partition carries the liquid on it, up to the next partition above, as
+/// if the layer below gave no support, e.g. while it's being filled. Lenses
+/// are long, so each partition is a plate bending across the width of the
+/// lens at its height, simply supported by the walls.
+///
+/// The bottom face isn't a partition, so it isn't loaded here: it sits on the
+/// lens's mount across its whole width rather than spanning the walls.
+pub fn partition_loads<const N: usize>(
+	params: ParamSet<N>,
+	catalog: Option<&Catalog>,
+) -> Vec<PartitionLoad> {
+	let outline = params.outline();
+	let slabs = slabs(params, outline);
+	let materials = catalog
+		.map(|c| c.slab_materials(params, &slabs))
+		.unwrap_or_default();
+
+	let mut loads = Vec::new();
+	// of the liquid since the last partition, in Pa
+	let mut head = 0.0;
+	let mut layer = 0;
+	for (n, slab) in slabs.iter().enumerate() {
+		// µm to m, and g/mL to kg/m³
+		let height = (slab.top - slab.bottom) as f64 / 1e6;
+		if !slab.partition {
+			let density = materials
+				.get(n)
+				.copied()
+				.flatten()
+				.map_or(DEFAULT_DENSITY, |m| m.density);
+			head += density * 1000.0 * GRAVITY * height;
+			layer += 1;
+			continue;
+		}
+
+		let (left, right) = outline.walls_at((slab.top + slab.bottom) as f64 / 2.0);
+		let span = (right - left) / 1000.0;
+		let thickness = height * 1000.0;
+		let pressure = head + PARTITION_DENSITY * 1000.0 * GRAVITY * height;
+		loads.push(PartitionLoad {
+			layer,
+			span,
+			pressure,
+			deflection: deflection(pressure, span, thickness),
+			stress: stress(pressure, span, thickness),
+		});
+		head = 0.0;
+	}
+	loads
+}
+
+/// Slabs of a design, from the top down, with partitions bowed under their
+/// loads, see [`partition_loads`].
+///
+/// Both faces of a partition sag alike, so the layer under it takes its
+/// shape too.
+pub fn sagged_slabs<const N: usize>(params: ParamSet<N>, catalog: Option<&Catalog>) -> Vec<Slab> {
+	let mut loads = partition_loads(params, catalog).into_iter();
+	let mut slabs = slabs(params, params.outline());
+	for n in 0..slabs.len() {
+		if !slabs[n].partition {
+			continue;
+		}
+		let Some(load) = loads.next() else {
+			break;
+		};
+		if !load.deflection.is_finite() {
+			continue;
+		}
+		// mm to µm
+		let sag = Curve::Sag {
+			depth: load.deflection * 1000.0,
+		};
+		slabs[n].curve = sag;
+		if let Some(below) = slabs.get_mut(n + 1) {
+			below.curve = sag;
+		}
+	}
+	slabs
+}
+
+/// Sag of a long, simply supported plate under even pressure, in mm, from
+/// the pressure in Pa, and the span and thickness in mm.
+pub fn deflection(pressure: f64, span: f64, thickness: f64) -> f64 {
+	let rigidity =
+		PARTITION_MODULUS * thickness.powi(3) / (12.0 * (1.0 - PARTITION_POISSON.powi(2)));
+	if rigidity > 0.0 {
+		// Pa to MPa
+		5.0 * pressure / 1e6 * span.powi(4) / (384.0 * rigidity)
+	} else {
+		f64::INFINITY
+	}
+}
+
+/// Largest bending stress of a long, simply supported plate under even
+/// pressure, in MPa, from the pressure in Pa, and the span and thickness in mm.
+pub fn stress(pressure: f64, span: f64, thickness: f64) -> f64 {
+	if thickness > 0.0 {
+		3.0 * pressure / 1e6 * span.powi(2) / (4.0 * thickness.powi(2))
+	} else {
+		f64::INFINITY
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bends_like_a_plate() {
+		// 2 mm of acrylic across 50 mm under 1 kPa: D = E·t³ / 12(1 - ν²)
+		let rigidity = 3200.0 * 8.0 / (12.0 * (1.0 - 0.37 * 0.37));
+		let expected = 5.0 * 1e-3 * 50f64.powi(4) / (384.0 * rigidity);
+		assert!((expected - 0.032_925).abs() < 1e-6);
+		assert!((deflection(1000.0, 50.0, 2.0) - expected).abs() < 1e-12);
+		assert!((stress(1000.0, 50.0, 2.0) - 0.468_75).abs() < 1e-12);
+		assert_eq!(deflection(1000.0, 50.0, 0.0), f64::INFINITY);
+	}
+
+	#[test]
+	fn loads_partitions_with_the_liquid_since_the_last() {
+		let params: ParamSet = "top:50mm bottom:50mm layer:3.00mm part:1.00mm | 1.40 ~ 1.45 1.50 |"
+			.parse()
+			.unwrap();
+		let loads = partition_loads(params, None);
+
+		// its own 1 mm, then two 3 mm layers of water as well
+		let own = PARTITION_DENSITY * 1000.0 * GRAVITY * 1e-3;
+		let liquid = DEFAULT_DENSITY * 1000.0 * GRAVITY * 6e-3;
+		assert_eq!(loads.iter().map(|l| l.layer).collect::<Vec<_>>(), [0, 2]);
+		assert!((loads[0].pressure - own).abs() < 1e-9);
+		assert!((loads[1].pressure - (own + liquid)).abs() < 1e-9);
+		assert!(loads.iter().all(|l| l.span == 50.0));
+		assert_eq!(loads[1].deflection, deflection(own + liquid, 50.0, 1.0));
+	}
+	#[test]
+	fn sags_partitions_and_the_layers_under_them() {
+		let params: ParamSet = "top:50mm bottom:50mm layer:3.00mm part:1.00mm | 1.40 ~ 1.45 1.50 |"
+			.parse()
+			.unwrap();
+		let loads = partition_loads(params, None);
+		let sagged = sagged_slabs(params, None);
+		let sag = |n: usize| Curve::Sag {
+			depth: loads[n].deflection * 1000.0,
+		};
+		let curves: Vec<_> = sagged.iter().map(|slab| slab.curve).collect();
+		assert_eq!(curves, [sag(0), sag(0), Curve::Flat, sag(1), sag(1)]);
+	}
+}
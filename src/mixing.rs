@@ -0,0 +1,282 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	catalog::Material,
+	model::{layer_slabs, units::model_ri_to_real_ri},
+	paramset::ParamSet,
+};
+
+/// How the RI of a mix of two liquids follows from their proportions.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MixingModel {
+	/// Lorentz–Lorenz: the molar refractions `(n² - 1) / (n² + 2)` of the
+	/// liquids add up by volume. Good for liquids that mix without shrinking.
+	#[default]
+	LorentzLorenz,
+
+	/// Measured RIs at volume fractions of the high liquid, as `[fraction, ri]`
+	/// pairs, interpolated linearly.
+	Tabulated {
+		/// Measurements, by fraction.
+		points: Vec<[f64; 2]>,
+	},
+}
+
+/// Two base liquids, and how they mix.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mixing {
+	/// Liquid with the lower RI, e.g. water.
+	pub low: Material,
+
+	/// Liquid with the higher RI, e.g. glycerol.
+	pub high: Material,
+
+	/// How their RIs combine.
+	#[serde(default)]
+	pub model: MixingModel,
+}
+
+/// How to fill one layer.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct LayerRecipe {
+	/// RI the layer is mixed to.
+	pub ri: f64,
+
+	/// Volume of the layer, in mL.
+	pub volume: f64,
+
+	/// Volume fraction of the high liquid, if the RI can be mixed at all.
+	pub fraction: Option<f64>,
+
+	/// Volume of the low liquid, in mL.
+	pub low: f64,
+
+	/// Volume of the high liquid, in mL.
+	pub high: f64,
+
+	/// Mass of the mix, in g.
+	pub mass: f64,
+}
+
+/// How to fill all layers of a design, from the top down.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Recipe {
+	/// Name of the liquid with the lower RI.
+	pub low: String,
+	/// Name of the liquid with the higher RI.
+	pub high: String,
+	/// Each layer, from the top down.
+	pub layers: Vec<LayerRecipe>,
+}
+
+fn lorentz_lorenz(ri: f64) -> f64 {
+	let square = ri * ri;
+	(square - 1.0) / (square + 2.0)
+}
+
+impl MixingModel {
+	/// Volume fraction of the high liquid that gives an RI, if any does.
+	pub fn fraction(&self, low: f64, high: f64, ri: f64) -> Option<f64> {
+		let fraction = match self {
+			Self::LorentzLorenz => {
+				let (low, high) = (lorentz_lorenz(low), lorentz_lorenz(high));
+				(lorentz_lorenz(ri) - low) / (high - low)
+			}
+			Self::Tabulated { points } => {
+				let segment = points.windows(2).find(|pair| {
+					let [[_, a], [_, b]] = [pair[0], pair[1]];
+					(a.min(b)..=a.max(b)).contains(&ri)
+				})?;
+				let [[fa, a], [fb, b]] = [segment[0], segment[1]];
+				if a == b {
+					fa
+				} else {
+					fa + (fb - fa) * (ri - a) / (b - a)
+				}
+			}
+		};
+		(0.0..=1.0).contains(&fraction).then_some(fraction)
+	}
+}
+
+impl Mixing {
+	/// Volume fraction of the high liquid that gives an RI, if any does.
+	pub fn fraction(&self, ri: f64) -> Option<f64> {
+		self.model.fraction(self.low.ri, self.high.ri, ri)
+	}
+
+	/// Quantities to mix for each layer of a design, for a lens this long in mm.
+	///
+	/// Layers whose RI can't be mixed from the two liquids have no fraction,
+	/// and no quantities.
+	pub fn recipe<const N: usize>(&self, params: ParamSet<N>, length: f64) -> Recipe {
+		let layers = params
+			.layers
+			.iter()
+			.map_while(|ri| *ri)
+			.zip(layer_volumes(params, length))
+			.map(|(ri, volume)| {
+				let ri = model_ri_to_real_ri(ri.get());
+				let fraction = self.fraction(ri);
+				let high = fraction.map_or(0.0, |f| f * volume);
+				let low = fraction.map_or(0.0, |f| (1.0 - f) * volume);
+				LayerRecipe {
+					ri,
+					volume,
+					fraction,
+					low,
+					high,
+					mass: low * self.low.density + high * self.high.density,
+				}
+			})
+			.collect();
+		Recipe {
+			low: self.low.name.clone(),
+			high: self.high.name.clone(),
+			layers,
+		}
+	}
+}
+
+/// Volume of each layer of a design, from the top down, in mL.
+///
+/// The lens is an extrusion of its cross-section, this long in mm.
+pub fn layer_volumes<const N: usize>(params: ParamSet<N>, length: f64) -> Vec<f64> {
+	let outline = params.outline();
+	layer_slabs(params, outline)
+		.into_iter()
+		.map(|slab| slab.volume(outline, length))
+		.collect()
+}
+
+impl Recipe {
+	/// Total volumes of the low and high liquids, in mL.
+	pub fn totals(&self) -> (f64, f64) {
+		self.layers.iter().fold((0.0, 0.0), |(low, high), layer| {
+			(low + layer.low, high + layer.high)
+		})
+	}
+}
+
+impl Display for Recipe {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"layer    RI   volume  {:>12}  {:>12}      mass",
+			self.low, self.high
+		)?;
+		for (n, layer) in self.layers.iter().enumerate() {
+			write!(
+				f,
+				"{:>5}  {:.2}  {:>5.1}mL  ",
+				n + 1,
+				layer.ri,
+				layer.volume
+			)?;
+			match layer.fraction {
+				Some(fraction) => writeln!(
+					f,
+					"{:>10.1}mL  {:>10.1}mL  {:>7.1}g  ({:.1}% {})",
+					layer.low,
+					layer.high,
+					layer.mass,
+					fraction * 100.0,
+					self.high
+				)?,
+				None => writeln!(f, "can't be mixed from these liquids")?,
+			}
+		}
+		let (low, high) = self.totals();
+		writeln!(f, "total              {low:>10.1}mL  {high:>10.1}mL")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn liquid(name: &str, ri: f64, density: f64) -> Material {
+		Material {
+			name: name.into(),
+			ri,
+			density,
+			cost: 0.0,
+			absorption: 0.0,
+			miscibility: None,
+			diffusivity: None,
+			thermo_optic: None,
+			expansion: None,
+		}
+	}
+
+	fn mixing(model: MixingModel) -> Mixing {
+		Mixing {
+			low: liquid("water", 1.32, 1.0),
+			high: liquid("glycerol", 1.48, 1.25),
+			model,
+		}
+	}
+
+	#[test]
+	fn mixes_by_molar_refraction() {
+		let water_glycerol = MixingModel::LorentzLorenz;
+		assert_eq!(water_glycerol.fraction(1.333, 1.474, 1.333), Some(0.0));
+		assert_eq!(water_glycerol.fraction(1.333, 1.474, 1.474), Some(1.0));
+		// (n² - 1) / (n² + 2) is 0.2057, 0.2424 and 0.2810 for these
+		let fraction = water_glycerol.fraction(1.333, 1.474, 1.40).unwrap();
+		assert!((fraction - 0.4875).abs() < 1e-3, "{fraction}");
+		assert_eq!(water_glycerol.fraction(1.333, 1.474, 1.33), None);
+		assert_eq!(water_glycerol.fraction(1.333, 1.474, 1.48), None);
+	}
+
+	#[test]
+	fn interpolates_measurements() {
+		let measured = MixingModel::Tabulated {
+			points: vec![[0.0, 1.333], [0.5, 1.39], [1.0, 1.474]],
+		};
+		assert_eq!(measured.fraction(1.333, 1.474, 1.39), Some(0.5));
+		let fraction = measured.fraction(1.333, 1.474, 1.432).unwrap();
+		assert!((fraction - 0.75).abs() < 1e-9, "{fraction}");
+		assert_eq!(measured.fraction(1.333, 1.474, 1.5), None);
+	}
+
+	#[test]
+	fn measures_each_layer() {
+		// 50mm by 5mm, in a metre of lens
+		let params: ParamSet =
+			"top:50mm bottom:50mm layer:5.00mm part:1.00mm  | 1.35 ~ 1.45 1.50 |"
+				.parse()
+				.unwrap();
+		let volumes = layer_volumes(params, 1000.0);
+		assert_eq!(volumes.len(), 3);
+		for volume in volumes {
+			assert!((volume - 250.0).abs() < 1e-9, "{volume}");
+		}
+	}
+
+	#[test]
+	fn splits_each_layer_between_the_liquids() {
+		let params: ParamSet = "top:50mm bottom:50mm layer:5.00mm part:1.00mm  | 1.35 1.45 1.50 |"
+			.parse()
+			.unwrap();
+		let recipe = mixing(MixingModel::default()).recipe(params, 1000.0);
+		let [light, heavy, unmixable] = recipe.layers[..] else {
+			panic!("{recipe:?}");
+		};
+		assert!(light.fraction < heavy.fraction);
+		for layer in [light, heavy] {
+			assert!((layer.low + layer.high - layer.volume).abs() < 1e-9);
+			assert!((layer.mass - (layer.low + 1.25 * layer.high)).abs() < 1e-9);
+		}
+		assert_eq!(
+			(unmixable.fraction, unmixable.low, unmixable.high),
+			(None, 0.0, 0.0)
+		);
+		let (low, high) = recipe.totals();
+		assert!((low + high - 500.0).abs() < 1e-9);
+	}
+}
@@ -1,18 +1,37 @@
-use crate::paramset::ParamSet;
+use std::f64::consts::PI;
+
+use crate::paramset::{ParamSet, MAXIMUM_RI};
 
 use ang::Angle;
-use geo::Point;
+use curve::{Boundary, Curve};
+use geo::{Outline, Point};
+use serde::{Deserialize, Serialize};
 use turtle::Turtle;
-use units::{mm_tenths_to_microns, Microns, RI_AIR};
+use units::{mm_tenths_to_microns, model_ri_to_real_ri, Microns, RI_AIR};
 
-use self::refract::normalise_incidence;
+use self::refract::{denormalise_incidence, normalise_incidence};
 
+/// Shapes of the boundaries between slabs.
+pub mod curve;
+/// Outline of the lens, and where rays meet its walls.
 pub mod geo;
-pub mod refract;
-pub mod turtle;
+/// Shapes of the sidewalls.
+pub mod profile;
+mod refract;
+mod turtle;
+/// Units of the model, and conversions to and from physical ones.
 pub mod units;
 
-#[derive(Clone, Copy, Debug)]
+/// Version of the model, to be bumped with every change to the tracer or to
+/// how fitness is worked out, so cached fitness from before isn't reused.
+pub const MODEL_VERSION: u32 = 2;
+
+/// Performance of a lens design, as measured by [`raytrace`].
+///
+/// This is in packed units for fitness calculations, but (de)serialises in
+/// physical units.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(into = "PhysicalPerformance", from = "PhysicalPerformance")]
 pub struct Performance {
 	/// Proportion of rays that exit at the bottom.
 	///
@@ -21,7 +40,8 @@ pub struct Performance {
 	/// Higher is better.
 	pub exit_ratio: u32,
 
-	/// Average of exit angles (to the normal) for rays that exit at the bottom.
+	/// Average of exit angles (to the normal) for rays that exit at the bottom,
+	/// or a right angle if none do.
 	///
 	/// In 10000th Angle.
 	///
@@ -34,34 +54,140 @@ pub struct Performance {
 	///
 	/// Lower is better.
 	pub light_travel: u32,
+
+	/// Geometric concentration ratio: top width over bottom width.
+	///
+	/// In 1000ths.
+	///
+	/// Higher is better.
+	pub concentration: u32,
 }
 
 impl Performance {
-	pub fn summarise(self) -> u64 {
-		let one = self.exit_ratio as u64;
-		let two = u32::MAX.saturating_sub(self.exit_angle) as u64;
-		let three = u32::MAX.saturating_sub(self.light_travel) as u64;
+	/// Optical concentration: the geometric concentration scaled by the
+	/// proportion of rays that make it through.
+	///
+	/// In the same scale as `exit_ratio`, times the concentration in 1000ths.
+	pub fn optical_concentration(self) -> u64 {
+		(self.exit_ratio as u64) * (self.concentration as u64) / 1000
+	}
+}
+
+/// [`Performance`] in physical units.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PhysicalPerformance {
+	/// Proportion of rays that exit at the bottom, from 0 to 1.
+	pub exit_ratio: f64,
+
+	/// Average of exit angles to the normal, in degrees.
+	pub exit_angle: f64,
+
+	/// Total distance light travels inside the lens, in mm.
+	pub light_travel: f64,
+
+	/// Top width over bottom width.
+	pub concentration: f64,
+}
+
+impl From<Performance> for PhysicalPerformance {
+	fn from(perf: Performance) -> Self {
+		Self {
+			exit_ratio: f64::from(perf.exit_ratio) / f64::from(u32::MAX),
+			exit_angle: Angle::Radians(f64::from(perf.exit_angle) / 10000.0).in_degrees(),
+			light_travel: f64::from(perf.light_travel) / 1000.0,
+			concentration: f64::from(perf.concentration) / 1000.0,
+		}
+	}
+}
+
+impl From<PhysicalPerformance> for Performance {
+	fn from(phys: PhysicalPerformance) -> Self {
+		Self {
+			exit_ratio: (phys.exit_ratio * f64::from(u32::MAX)).round() as _,
+			exit_angle: (Angle::Degrees(phys.exit_angle).in_radians() * 10000.0).round() as _,
+			light_travel: (phys.light_travel * 1000.0).round() as _,
+			concentration: (phys.concentration * 1000.0).round() as _,
+		}
+	}
+}
+
+/// Rays sent into the top of a lens by [`raytrace`].
+///
+/// Rays enter at regular intervals across the top face, at regular angles
+/// between (but excluding) the minimum and maximum.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RaySource {
+	/// Distance between entry points, in mm.
+	pub entry_interval: f64,
+
+	/// Angle between rays at an entry point, in degrees.
+	pub angle_interval: f64,
 
-		one * 10 + two * 5 + three
+	/// Lowest angle to the normal, in degrees; negative is to the left.
+	pub angle_min: f64,
+
+	/// Highest angle to the normal, in degrees.
+	pub angle_max: f64,
+}
+
+impl Default for RaySource {
+	fn default() -> Self {
+		Self {
+			entry_interval: 5.0,
+			angle_interval: 5.0,
+			angle_min: -90.0,
+			angle_max: 90.0,
+		}
 	}
 }
 
-const ENTRY_INTERVAL: Microns = 5_000;
-const ANGLE_INTERVAL: Angle = Angle::Degrees(5.0);
-const ANGLE_MIN: Angle = Angle::Degrees(-90.0);
-const ANGLE_MAX: Angle = Angle::Degrees(90.0);
+/// How many boundaries a ray may meet before it is considered trapped.
+const BOUNDARY_LIMIT: usize = 1_000;
+
+/// Traces rays through a design, from its top face down.
+pub fn raytrace<const N: usize>(params: ParamSet<N>, rays: RaySource) -> Performance {
+	raytrace_profile(params, &slabs(params, params.outline()), rays)
+}
 
-pub fn raytrace(params: ParamSet) -> Performance {
+/// Like [`raytrace`], but through these slabs instead of those of the design,
+/// e.g. once its liquids have diffused. The design still gives the outline,
+/// stretched to the top of the slabs if the stack has grown or shrunk.
+pub fn raytrace_profile<const N: usize>(
+	params: ParamSet<N>,
+	slabs: &[Slab],
+	rays: RaySource,
+) -> Performance {
+	let outline = params.outline();
+	let outline = Outline {
+		height: slabs.first().map_or(outline.height, |slab| slab.top),
+		..outline
+	};
 	let mut traces = Vec::with_capacity(18960);
 
-	let mut entry: Microns = 0;
-	while entry <= ParamSet::WIDTH_TOP {
-		entry += ENTRY_INTERVAL;
-		let mut angle: Angle = ANGLE_MIN;
-		while angle <= ANGLE_MAX {
-			angle += ANGLE_INTERVAL;
-			traces.push(trace_one(params, entry, angle));
+	// the top of each slab, and the bottom of the last
+	let boundaries: Vec<Boundary> = slabs
+		.iter()
+		.map(|slab| Boundary::new(outline, slab.top, slab.curve))
+		.chain(
+			slabs
+				.last()
+				.map(|slab| Boundary::new(outline, slab.bottom, Curve::Flat)),
+		)
+		.collect();
+
+	let entry_interval = ((rays.entry_interval * 1000.0).round() as Microns).max(1);
+	let angle_interval = Angle::Degrees(rays.angle_interval);
+	let angle_max = Angle::Degrees(rays.angle_max);
+
+	let mut entry: Microns = entry_interval / 2;
+	while entry < outline.width_top {
+		let mut angle = Angle::Degrees(rays.angle_min) + angle_interval;
+		while angle < angle_max {
+			traces.push(trace_one(slabs, &boundaries, outline, entry, angle));
+			angle += angle_interval;
 		}
+		entry += entry_interval;
 	}
 
 	let total_rays = traces.len();
@@ -77,18 +203,24 @@ pub fn raytrace(params: ParamSet) -> Performance {
 		.unzip();
 	let total_bottomed = bottom_angles.len();
 	let total_travel: Microns = bottom_travel.into_iter().sum();
-	let average_angle: Angle = Angle::Radians(
-		bottom_angles
-			.iter()
-			.map(|a| a.abs().in_radians())
-			.sum::<f64>()
-			/ (total_bottomed as f64),
-	);
+	// with no rays out of the bottom, the angle is as bad as it gets
+	let average_angle: Angle = if total_bottomed == 0 {
+		Angle::Radians(PI / 2.0)
+	} else {
+		Angle::Radians(
+			bottom_angles
+				.iter()
+				.map(|a| a.abs().in_radians())
+				.sum::<f64>()
+				/ (total_bottomed as f64),
+		)
+	};
 
 	Performance {
-		exit_ratio: ((total_bottomed * (u32::MAX as usize)) / total_rays) as _,
+		exit_ratio: ((total_bottomed * (u32::MAX as usize)) / total_rays.max(1)) as _,
 		exit_angle: (average_angle.in_radians() * 10000.0) as _,
-		light_travel: total_travel as _,
+		light_travel: total_travel.try_into().unwrap_or(u32::MAX),
+		concentration: params.concentration(),
 	}
 }
 
@@ -96,96 +228,162 @@ pub fn raytrace(params: ParamSet) -> Performance {
 enum Traced {
 	TopExit,
 	BottomExit { angle: Angle, travel: Microns },
+	Trapped,
 }
 
-fn trace_one(params: ParamSet, entry_point: Microns, entry_angle: Angle) -> Traced {
+/// A horizontal slice of the lens: either a partition or a layer.
+#[derive(Clone, Copy, Debug)]
+pub struct Slab {
+	/// Nominal height of the top of the slab.
+	pub top: Microns,
+
+	/// Height of its bottom.
+	pub bottom: Microns,
+
+	/// Real RI, not in the units of [`ParamSet`], so it can take any value.
+	pub ri: f64,
+
+	/// Whether it's a partition, rather than a layer.
+	pub partition: bool,
+
+	/// Shape of the top of the slab; the bottom is the top of the one below.
+	pub curve: Curve,
+}
+
+/// Slices of the lens from the top down, each layer under its partition if it
+/// has one.
+pub fn slabs<const N: usize>(params: ParamSet<N>, outline: Outline) -> Vec<Slab> {
 	let part_um = mm_tenths_to_microns(params.partitions_thickness);
 	let layer_um = mm_tenths_to_microns(params.layers_thickness).saturating_add(3_000);
 
-	let layers: Vec<u8> = params
+	// all this kinda considers that the bottom partition doesn't exist :/
+	// ...FIXME maybe?
+
+	let mut slabs = Vec::with_capacity(N * 2);
+	let mut top = outline.height;
+	for (n, ri) in params
 		.layers
 		.iter()
-		.filter_map(|l| l.map(|n| n.get()))
-		.collect();
+		.map_while(|l| l.map(|n| n.get()))
+		.enumerate()
+	{
+		let partition = params.partitioned(n).then_some((part_um, MAXIMUM_RI, true));
+		for (thickness, ri, partition) in partition.into_iter().chain([(layer_um, ri, false)]) {
+			let bottom = top.saturating_sub(thickness);
+			slabs.push(Slab {
+				top,
+				bottom,
+				ri: model_ri_to_real_ri(ri),
+				partition,
+				curve: Curve::Flat,
+			});
+			top = bottom;
+		}
+	}
+	slabs
+}
 
-	let lens_height = (layers.len() as u64) * (part_um + layer_um);
+/// Heights taken through a slab when measuring its cross-section, so curved
+/// walls are followed.
+const AREA_SAMPLES: usize = 16;
 
-	// all this kinda considers that the bottom partition doesn't exist :/
-	// ...FIXME maybe?
+impl Slab {
+	/// Thickness, in mm.
+	pub fn thickness(self) -> f64 {
+		(self.top - self.bottom) as f64 / 1000.0
+	}
+
+	/// Volume, in mL, of a lens this long in mm: the lens is an extrusion of
+	/// its cross-section.
+	pub fn volume(self, outline: Outline, length: f64) -> f64 {
+		let (top, bottom) = (self.top as f64, self.bottom as f64);
+		let width = |n: usize| {
+			let (left, right) =
+				outline.walls_at(top - (top - bottom) * n as f64 / AREA_SAMPLES as f64);
+			right - left
+		};
+		let sum: f64 =
+			(1..AREA_SAMPLES).map(width).sum::<f64>() + (width(0) + width(AREA_SAMPLES)) / 2.0;
+		// µm² to mm², and mm³ to mL
+		let area = sum * (top - bottom) / AREA_SAMPLES as f64 / 1e6;
+		area * length / 1000.0
+	}
+}
+
+/// Layers of the lens from the top down, without their partitions.
+pub fn layer_slabs<const N: usize>(params: ParamSet<N>, outline: Outline) -> Vec<Slab> {
+	slabs(params, outline)
+		.into_iter()
+		.filter(|slab| !slab.partition)
+		.collect()
+}
+
+/// Gives the boundaries between layers that touch without a partition a
+/// curve, such as a meniscus.
+pub fn curve_open_interfaces(slabs: &mut [Slab], curve: Curve) {
+	for n in 1..slabs.len() {
+		if !slabs[n].partition && !slabs[n - 1].partition {
+			slabs[n].curve = curve;
+		}
+	}
+}
+
+fn trace_one(
+	slabs: &[Slab],
+	boundaries: &[Boundary],
+	outline: Outline,
+	entry_point: Microns,
+	entry_angle: Angle,
+) -> Traced {
+	if slabs.is_empty() {
+		return Traced::TopExit;
+	}
 
 	let mut travel: Microns = 0;
+	let x = outline.top_left() + entry_point;
 	let mut ray = Turtle {
 		pos: Point {
-			x: entry_point,
-			y: lens_height,
+			x,
+			y: boundaries[0].height_at(x as f64).round().max(0.0) as _,
 		},
-		ri: RI_AIR,
-		dir: entry_angle,
+		ri: model_ri_to_real_ri(RI_AIR),
+		dir: denormalise_incidence(entry_angle, true),
 	};
 
-	let mut layer: usize = 0;
-	let mut in_layer = false;
-	let mut boundary_above = lens_height;
-	let mut boundary_below = lens_height.saturating_sub(part_um);
-
 	// top partition entry
-	ray.refract_into(ParamSet::MAXIMUM_RI);
+	let mut slab = 0;
+	ray.refract_through(slabs[slab].ri, boundaries[slab].slope_at(x as f64));
 
-	loop {
-		let so_far = ray.travel_to_next_boundary(boundary_above, boundary_below);
+	for _ in 0..BOUNDARY_LIMIT {
+		let (so_far, crossing) = if let Some(traveled) =
+			ray.travel_to_next_boundary(outline, boundaries[slab], boundaries[slab + 1])
+		{
+			traveled
+		} else {
+			return Traced::Trapped;
+		};
 		travel += so_far;
 
-		if ray.pos.y > lens_height || (ray.pos.y == lens_height && !ray.is_going_down()) {
-			break Traced::TopExit;
-		}
-
-		if in_layer {
-			// refract into partition from layer
-			ray.refract_into(ParamSet::MAXIMUM_RI);
-			in_layer = false;
-
-			if ray.is_horizontal() {
-				// no change
-			} else if ray.is_going_down() {
-				boundary_above = ray.pos.y;
-				boundary_below = ray.pos.y - part_um;
-			} else {
-				boundary_above = ray.pos.y + part_um;
-				boundary_below = ray.pos.y;
-			}
+		let next = if !crossing.up {
+			slab + 1
+		} else if let Some(above) = slab.checked_sub(1) {
+			above
 		} else {
-			// refract into layer from partition
-			in_layer = true;
-
-			if ray.is_horizontal() {
-				// no change
-			} else if ray.is_going_down() {
-				layer += 1;
-				boundary_above = ray.pos.y;
-				boundary_below = ray.pos.y - layer_um;
-			} else if layer == 0 {
-				break Traced::TopExit;
-			} else {
-				layer -= 1;
-				boundary_above = ray.pos.y + layer_um;
-				boundary_below = ray.pos.y;
-			};
+			return Traced::TopExit;
+		};
 
-			if let Some(ri) = layers.get(layer) {
-				ray.refract_into(*ri);
-			} else {
-				break Traced::BottomExit {
-					angle: normalise_incidence(ray.dir),
-					travel,
-				};
+		if let Some(Slab { ri, .. }) = slabs.get(next) {
+			if ray.refract_through(*ri, crossing.slope) {
+				slab = next;
 			}
-		}
-
-		if ray.pos.y == 0 {
-			break Traced::BottomExit {
+		} else {
+			return Traced::BottomExit {
 				angle: normalise_incidence(ray.dir),
 				travel,
 			};
 		}
 	}
+
+	// guided along a layer by total internal reflection
+	Traced::Trapped
 }
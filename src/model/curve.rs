@@ -0,0 +1,243 @@
+use super::{geo::Outline, units::Microns};
+
+/// Longest step along a ray when looking for where it crosses a curve, in µm.
+const CURVE_STEP: f64 = 100.0;
+
+/// Halvings of the step where a ray crosses a curve, to place it well within
+/// a µm.
+const BISECTIONS: usize = 24;
+
+/// Shape of the boundary between two slabs, across the lens.
+///
+/// Heights are above the nominal height of the boundary, in µm.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Curve {
+	/// Level across the lens.
+	#[default]
+	Flat,
+
+	/// Parabola between the walls, as a partition sagging under a load.
+	Sag {
+		/// How much lower the middle is than the walls. Negative bulges up.
+		depth: f64,
+	},
+
+	/// Rises at the walls, falling away towards the middle, as a meniscus.
+	Meniscus {
+		/// Rise at each wall. Negative dips at the walls.
+		rise: f64,
+		/// Length over which it falls away, the capillary length.
+		length: f64,
+	},
+}
+
+impl Curve {
+	/// Height and slope (rise per unit of run) at a horizontal position,
+	/// between walls at these positions.
+	fn at(self, x: f64, left: f64, right: f64) -> (f64, f64) {
+		let span = (right - left).max(f64::EPSILON);
+		match self {
+			Self::Flat => (0.0, 0.0),
+			Self::Sag { depth } => {
+				let u = ((x - left) / span).clamp(0.0, 1.0);
+				(
+					-depth * 4.0 * u * (1.0 - u),
+					-depth * 4.0 * (1.0 - 2.0 * u) / span,
+				)
+			}
+			Self::Meniscus { rise, length } => {
+				let left = (-(x - left).max(0.0) / length).exp();
+				let right = (-(right - x).max(0.0) / length).exp();
+				(rise * (left + right), rise * (right - left) / length)
+			}
+		}
+	}
+
+	/// Lowest and highest heights the curve can have.
+	fn extent(self) -> (f64, f64) {
+		match self {
+			Self::Flat => (0.0, 0.0),
+			Self::Sag { depth } => ((-depth).min(0.0), (-depth).max(0.0)),
+			Self::Meniscus { rise, .. } => ((2.0 * rise).min(0.0), (2.0 * rise).max(0.0)),
+		}
+	}
+}
+
+/// A boundary between two slabs, following a curve between the walls at its
+/// nominal height.
+#[derive(Clone, Copy, Debug)]
+pub struct Boundary {
+	/// Nominal height.
+	pub y: Microns,
+	/// Shape around that height.
+	pub curve: Curve,
+	left: f64,
+	right: f64,
+}
+
+impl Boundary {
+	/// Boundary between the walls of the outline at this height.
+	pub fn new(outline: Outline, y: Microns, curve: Curve) -> Self {
+		let (left, right) = outline.walls_at(y as f64);
+		Self {
+			y,
+			curve,
+			left,
+			right,
+		}
+	}
+
+	/// Height at a horizontal position.
+	pub fn height_at(self, x: f64) -> f64 {
+		self.y as f64 + self.curve.at(x, self.left, self.right).0
+	}
+
+	/// Rise per unit of run at a horizontal position.
+	pub fn slope_at(self, x: f64) -> f64 {
+		self.curve.at(x, self.left, self.right).1
+	}
+
+	/// Finds where a ray leaves the slab this boundary is the top (if going
+	/// up) or bottom of.
+	///
+	/// The ray starts at (x, y) and goes along (dx, dy), which is a unit
+	/// vector. Returns the distance along the ray, if it's within the limit.
+	pub fn exit(self, x: f64, y: f64, dx: f64, dy: f64, up: bool, limit: f64) -> Option<f64> {
+		if self.curve == Curve::Flat {
+			return (dy != 0.0 && (dy > 0.0) == up).then(|| (self.y as f64 - y) / dy);
+		}
+
+		// only look where the ray is at heights the curve can be at
+		let (low, high) = self.curve.extent();
+		let (low, high) = (self.y as f64 + low, self.y as f64 + high);
+		let (from, to) = if dy.abs() < f64::EPSILON {
+			if !(low..=high).contains(&y) {
+				return None;
+			}
+			(0.0, limit)
+		} else {
+			let (a, b) = ((low - y) / dy, (high - y) / dy);
+			(a.min(b).max(0.0), a.max(b).min(limit))
+		};
+		if !(from < to && to.is_finite()) {
+			return None;
+		}
+
+		// out of the slab: above a top boundary, or below a bottom one
+		let outside = |t: f64| {
+			let above = y + dy * t - self.height_at(x + dx * t);
+			if up {
+				above >= 0.0
+			} else {
+				above <= 0.0
+			}
+		};
+		// going the way out, the ray is inside until it reaches the heights of
+		// the curve, so if it's out as soon as it gets there, it touched the
+		// curve there
+		if from > 0.0 && (dy > 0.0) == up && outside(from) {
+			return Some(from);
+		}
+		let steps = ((to - from) / CURVE_STEP).ceil().max(1.0);
+		let mut before = from;
+		let mut was_outside = outside(from);
+		for n in 1..=steps as usize {
+			let t = from + (to - from) * n as f64 / steps;
+			let is_outside = outside(t);
+			if is_outside && !was_outside {
+				let (mut inside, mut out) = (before, t);
+				for _ in 0..BISECTIONS {
+					let middle = (inside + out) / 2.0;
+					if outside(middle) {
+						out = middle;
+					} else {
+						inside = middle;
+					}
+				}
+				return Some(out);
+			}
+			before = t;
+			was_outside = is_outside;
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A boundary halfway up a square lens, 10mm across.
+	fn boundary(curve: Curve) -> Boundary {
+		Boundary::new(Outline::new(10_000, 10_000, 10_000), 5_000, curve)
+	}
+
+	const SAG: Curve = Curve::Sag { depth: 200.0 };
+
+	#[test]
+	fn flat_exits_only_the_way_the_ray_goes() {
+		let flat = boundary(Curve::Flat);
+		assert_eq!(
+			flat.exit(5_000.0, 4_000.0, 0.0, 1.0, true, 1e6),
+			Some(1_000.0)
+		);
+		assert_eq!(
+			flat.exit(5_000.0, 6_000.0, 0.0, -1.0, false, 1e6),
+			Some(1_000.0)
+		);
+		assert_eq!(flat.exit(5_000.0, 4_000.0, 0.0, -1.0, true, 1e6), None);
+		assert_eq!(flat.exit(5_000.0, 4_000.0, 1.0, 0.0, true, 1e6), None);
+	}
+
+	#[test]
+	fn sag_is_lowest_in_the_middle() {
+		let sag = boundary(SAG);
+		assert_eq!(sag.height_at(0.0), 5_000.0);
+		assert_eq!(sag.height_at(10_000.0), 5_000.0);
+		assert_eq!(sag.height_at(5_000.0), 4_800.0);
+		assert_eq!(sag.slope_at(5_000.0), 0.0);
+		assert!(sag.slope_at(2_500.0) < 0.0);
+	}
+
+	#[test]
+	fn finds_where_a_ray_crosses_a_curve() {
+		let sag = boundary(SAG);
+		let t = sag.exit(5_000.0, 4_000.0, 0.0, 1.0, true, 1e6).unwrap();
+		assert!((t - 800.0).abs() < 1e-3, "{t}");
+
+		// slanted, up through the sagging top and down through it
+		let (dx, dy) = (0.6, 0.8);
+		for (y, dy, up) in [(4_000.0, dy, true), (6_000.0, -dy, false)] {
+			let t = sag.exit(3_000.0, y, dx, dy, up, 1e6).unwrap();
+			let (x, y) = (3_000.0 + dx * t, y + dy * t);
+			assert!((y - sag.height_at(x)).abs() < 1e-3, "{x}, {y}");
+		}
+
+		// level, through the dip
+		let t = sag.exit(0.0, 4_900.0, 1.0, 0.0, false, 1e6).unwrap();
+		assert!((sag.height_at(t) - 4_900.0).abs() < 1e-3, "{t}");
+	}
+
+	#[test]
+	fn ignores_crossings_into_the_slab_and_past_the_limit() {
+		let sag = boundary(SAG);
+		// starts above the top of the slab, and stays there
+		assert_eq!(sag.exit(5_000.0, 5_100.0, 0.0, 1.0, true, 1e6), None);
+		// would cross, but not within the limit
+		assert_eq!(sag.exit(5_000.0, 4_000.0, 0.0, 1.0, true, 500.0), None);
+		// above the whole curve, going away from it
+		assert_eq!(sag.exit(5_000.0, 5_500.0, 0.0, -1.0, true, 1e6), None);
+	}
+
+	#[test]
+	fn menisci_rise_at_the_walls() {
+		let meniscus = boundary(Curve::Meniscus {
+			rise: 300.0,
+			length: 1_000.0,
+		});
+		assert!(meniscus.height_at(0.0) > 5_299.0);
+		assert!((meniscus.height_at(5_000.0) - 5_000.0).abs() < 5.0);
+		let t = meniscus.exit(200.0, 4_000.0, 0.0, 1.0, true, 1e6).unwrap();
+		assert!((4_000.0 + t - meniscus.height_at(200.0)).abs() < 1e-3);
+	}
+}
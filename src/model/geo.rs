@@ -1,7 +1,6 @@
-use std::fmt::Display;
-
-use super::units::Microns;
+use super::{profile::Profile, units::Microns};
 
+/// A point on the cross-section of the lens.
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Point {
 	/// Horizontal position from leftmost
@@ -11,40 +10,165 @@ pub struct Point {
 	pub y: Microns,
 }
 
+/// Outline of the lens, as a vertical cross-section.
+///
+/// The top and bottom faces are centred on each other; x is measured from the
+/// leftmost point of the outline and y from the bottom face. Each sidewall goes
+/// from a top corner to a bottom corner following its [`Profile`].
 #[derive(Clone, Copy, Debug)]
-pub struct Line {
+pub struct Outline {
+	/// Width of the top (entry) face.
+	pub width_top: Microns,
+	/// Width of the bottom (exit) face.
+	pub width_bottom: Microns,
+	/// Height from the bottom face to the top one.
+	pub height: Microns,
+	/// Shape of the left sidewall.
+	pub left: Profile,
+	/// Shape of the right sidewall.
+	pub right: Profile,
+}
+
+/// Where a ray meets a sidewall.
+#[derive(Clone, Copy, Debug)]
+pub struct WallHit {
+	/// Distance along the ray.
+	pub distance: f64,
+
+	/// Horizontal run of the wall per unit of height, at the hit.
 	pub slope: f64,
-	pub y_intercept: i64,
 }
 
-impl Line {
-	pub fn new(slope: f64, y_intercept: i64) -> Self {
-		Self { slope, y_intercept }
+/// Hits closer than this along the ray are ignored, so a ray that's just been
+/// reflected doesn't hit the same spot again.
+const MINIMUM_HIT_DISTANCE: f64 = 1.0;
+
+impl Outline {
+	/// Outline with straight sidewalls.
+	pub fn new(width_top: Microns, width_bottom: Microns, height: Microns) -> Self {
+		Self {
+			width_top,
+			width_bottom,
+			height,
+			left: Profile::Straight,
+			right: Profile::Straight,
+		}
 	}
 
-	pub fn intersection(self, other: Self) -> Option<Point> {
-		todo!()
+	/// Same outline, with these sidewalls.
+	pub fn with_profiles(self, left: Profile, right: Profile) -> Self {
+		Self {
+			left,
+			right,
+			..self
+		}
 	}
-}
 
-impl Display for Line {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		match (self.slope == 0.0, self.y_intercept == 0) {
-			(true, true) => write!(f, "y = 0"),
-			(true, false) => write!(
-				f,
-				"y = {}{}",
-				if self.y_intercept > 0 { "" } else { "-" },
-				self.y_intercept
-			),
-			(false, true) => write!(f, "y = {}x", self.slope),
-			(false, false) => write!(
-				f,
-				"y = {}x {} {}",
-				self.slope,
-				if self.y_intercept > 0 { "+" } else { "-" },
-				self.y_intercept
+	/// Horizontal distance of the left wall at the top from the origin.
+	fn left_top(self) -> f64 {
+		(self.width_bottom.saturating_sub(self.width_top) as f64) / 2.0
+	}
+
+	/// Horizontal distance of the left wall at the bottom from the origin.
+	fn left_bottom(self) -> f64 {
+		(self.width_top.saturating_sub(self.width_bottom) as f64) / 2.0
+	}
+
+	/// Horizontal positions of the (top, bottom) corners of each wall.
+	fn corners(self) -> [(f64, f64, Profile); 2] {
+		let (left_top, left_bottom) = (self.left_top(), self.left_bottom());
+		[
+			(left_top, left_bottom, self.left),
+			(
+				left_top + self.width_top as f64,
+				left_bottom + self.width_bottom as f64,
+				self.right,
 			),
+		]
+	}
+
+	/// Depth of a height, as a fraction from the top.
+	fn depth(self, y: f64) -> f64 {
+		if self.height == 0 {
+			0.0
+		} else {
+			(self.height as f64 - y) / self.height as f64
+		}
+	}
+
+	/// Position of the left end of the top face.
+	pub fn top_left(self) -> Microns {
+		self.left_top() as _
+	}
+
+	/// Horizontal positions of the left and right walls at a height.
+	pub fn walls_at(self, y: f64) -> (f64, f64) {
+		let z = self.depth(y);
+		let [left, right] = self
+			.corners()
+			.map(|(top, bottom, profile)| top + (bottom - top) * profile.inset_at(z));
+		(left, right)
+	}
+
+	/// Finds the nearest sidewall a ray will hit.
+	///
+	/// The ray starts at (x, y) and goes along (dx, dy), which is a unit vector.
+	pub fn wall_hit(self, x: f64, y: f64, dx: f64, dy: f64) -> Option<WallHit> {
+		if self.height == 0 {
+			return None;
+		}
+
+		let height = self.height as f64;
+		let z0 = self.depth(y);
+		let k = -dy / height;
+
+		let mut nearest: Option<WallHit> = None;
+		for (top, bottom, profile) in self.corners() {
+			let run = bottom - top;
+			for piece in profile.pieces() {
+				// solve x + dx·t = top + run·f(z0 + k·t) for t
+				let [c0, c1, c2] = piece.coefficients.map(|c| c * run);
+				let a = c2 * k * k;
+				let b = 2.0 * c2 * z0 * k + c1 * k - dx;
+				let c = c2 * z0 * z0 + c1 * z0 + c0 + top - x;
+
+				for t in solve_quadratic(a, b, c).into_iter().flatten() {
+					let z = z0 + k * t;
+					if t <= MINIMUM_HIT_DISTANCE
+						|| z < piece.from - f64::EPSILON
+						|| z > piece.to + f64::EPSILON
+						|| nearest.is_some_and(|hit| hit.distance <= t)
+					{
+						continue;
+					}
+
+					nearest = Some(WallHit {
+						distance: t,
+						slope: -run * piece.derivative_at(z) / height,
+					});
+				}
+			}
+		}
+
+		nearest
+	}
+}
+
+/// Real roots of `a·t² + b·t + c = 0`, degrading to linear when `a` is ~zero.
+fn solve_quadratic(a: f64, b: f64, c: f64) -> [Option<f64>; 2] {
+	if a.abs() < 1e-12 {
+		if b == 0.0 {
+			[None, None]
+		} else {
+			[Some(-c / b), None]
+		}
+	} else {
+		let discriminant = b * b - 4.0 * a * c;
+		if discriminant < 0.0 {
+			[None, None]
+		} else {
+			let root = discriminant.sqrt();
+			[Some((-b - root) / (2.0 * a)), Some((-b + root) / (2.0 * a))]
 		}
 	}
 }
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+/// Number of knots between the corners of a piecewise profile.
+pub const KNOTS: usize = 3;
+
+/// Shape of a sidewall, going from the top corner to the bottom corner.
+///
+/// This is expressed as a function from depth (0 at the top, 1 at the bottom)
+/// to the fraction of the horizontal inset covered at that depth, both going
+/// from 0 to 1. A straight wall covers its inset linearly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Profile {
+	/// Straight from corner to corner: `f(z) = z`.
+	#[default]
+	Straight,
+
+	/// Parabola through both corners: `f(z) = z + c·z·(1 - z)`.
+	///
+	/// The curvature `c` is between -1 and 1 so the wall stays monotonic.
+	Parabolic {
+		/// Curvature `c`; positive covers more of the inset near the top.
+		curvature: f64,
+	},
+
+	/// Linear between the corners and evenly spaced knots.
+	Piecewise {
+		/// Fraction of the inset covered at each knot, from the top down.
+		knots: [f64; KNOTS],
+	},
+}
+
+/// A section of a profile as a quadratic: `f(z) = c0 + c1·z + c2·z²`.
+#[derive(Clone, Copy, Debug)]
+pub struct Piece {
+	/// Depth the piece starts at.
+	pub from: f64,
+	/// Depth it ends at.
+	pub to: f64,
+	/// `[c0, c1, c2]`.
+	pub coefficients: [f64; 3],
+}
+
+impl Piece {
+	/// Value at a depth.
+	pub fn value_at(self, z: f64) -> f64 {
+		let [c0, c1, c2] = self.coefficients;
+		c0 + c1 * z + c2 * z * z
+	}
+
+	/// Rate of change with depth, at a depth.
+	pub fn derivative_at(self, z: f64) -> f64 {
+		let [_, c1, c2] = self.coefficients;
+		c1 + 2.0 * c2 * z
+	}
+}
+
+impl Profile {
+	/// Fraction of the inset covered at a depth.
+	pub fn inset_at(self, z: f64) -> f64 {
+		let z = z.clamp(0.0, 1.0);
+		self.pieces()
+			.into_iter()
+			.find(|piece| z <= piece.to)
+			.map_or(1.0, |piece| piece.value_at(z))
+	}
+
+	/// Splits the profile into quadratic pieces, from the top down.
+	pub fn pieces(self) -> SmallVec<[Piece; KNOTS + 1]> {
+		match self {
+			Self::Straight => smallvec::smallvec![Piece {
+				from: 0.0,
+				to: 1.0,
+				coefficients: [0.0, 1.0, 0.0],
+			}],
+			Self::Parabolic { curvature } => {
+				let c = curvature.clamp(-1.0, 1.0);
+				smallvec::smallvec![Piece {
+					from: 0.0,
+					to: 1.0,
+					coefficients: [0.0, 1.0 + c, -c],
+				}]
+			}
+			Self::Piecewise { knots } => {
+				let step = 1.0 / (KNOTS + 1) as f64;
+				let mut points = [(0.0, 0.0); KNOTS + 2];
+				points[KNOTS + 1] = (1.0, 1.0);
+				for (n, knot) in knots.iter().enumerate() {
+					points[n + 1] = (step * (n + 1) as f64, knot.clamp(0.0, 1.0));
+				}
+
+				points
+					.windows(2)
+					.map(|pair| {
+						let [(z0, f0), (z1, f1)] = [pair[0], pair[1]];
+						let m = (f1 - f0) / (z1 - z0);
+						Piece {
+							from: z0,
+							to: z1,
+							coefficients: [f0 - m * z0, m, 0.0],
+						}
+					})
+					.collect()
+			}
+		}
+	}
+}
@@ -2,39 +2,57 @@ use std::f64::consts::PI;
 
 use ang::Angle;
 
-use super::units::model_ri_to_real_ri;
-
 /// Converts an absolute direction to an incidence to the normal.
 ///
 /// The normals in our case are always vertical. A positive incidence is in the
 /// quadrants 3 and 4 of the unit circle ("going left").
-pub const fn normalise_incidence(direction: Angle) -> Angle {
-	todo!()
+pub fn normalise_incidence(direction: Angle) -> Angle {
+	let dir = direction.normalized().in_radians();
+	Angle::Radians(if dir > PI / 2.0 && dir < PI + PI / 2.0 {
+		// going down: against the downward normal
+		dir - PI
+	} else if dir >= PI {
+		// going up and left
+		2.0 * PI - dir
+	} else {
+		// going up and right
+		-dir
+	})
 }
 
 /// Converts an incidence against the normal to an absolute direction.
-pub const fn denormalise_incidence(incidence: Angle) -> Angle {
-	todo!()
+///
+/// As the incidence doesn't say whether the ray goes up or down, that needs to
+/// be provided separately.
+pub fn denormalise_incidence(incidence: Angle, going_down: bool) -> Angle {
+	if going_down {
+		(Angle::Radians(PI) + incidence).normalized()
+	} else {
+		(-incidence).normalized()
+	}
 }
 
 /// Calculates refraction between two mediums for a ray going a certain direction.
 ///
 /// Returns the output direction.
-pub fn snells(old_ri: u8, new_ri: u8, direction: Angle) -> Angle {
-	let old_ri = model_ri_to_real_ri(old_ri);
-	let new_ri = model_ri_to_real_ri(new_ri);
+pub fn snells(old_ri: f64, new_ri: f64, direction: Angle) -> Angle {
 	let incidence = normalise_incidence(direction);
+	let going_down = {
+		let dir = direction.normalized().in_radians();
+		dir > PI / 2.0 && dir < PI + PI / 2.0
+	};
 
 	let new_sin = (old_ri / new_ri) * incidence.sin();
 	let abs_sin = new_sin.abs();
 	if abs_sin < 1.0 {
 		// refraction
-		denormalise_incidence(ang::asin(new_sin).unwrap())
+		denormalise_incidence(ang::asin(new_sin).unwrap(), going_down)
 	} else if abs_sin > 1.0 {
 		// total internal reflection
-		denormalise_incidence(-incidence)
+		denormalise_incidence(incidence, !going_down)
 	} else {
 		// critical angle
-		denormalise_incidence(incidence.signum() * (PI / 2.0))
+		let grazing = Angle::Radians(incidence.in_radians().signum() * (PI / 2.0));
+		denormalise_incidence(grazing, going_down)
 	}
 }
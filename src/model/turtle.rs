@@ -2,12 +2,30 @@ use std::f64::consts::PI;
 
 use ang::Angle;
 
-use super::{geo::Point, refract::snells, units::Microns};
+use super::{
+	curve::Boundary,
+	geo::{Outline, Point},
+	refract::snells,
+	units::Microns,
+};
 
 pub const QUARTER: Angle = Angle::Radians(PI / 2.0);
 pub const HALF: Angle = Angle::Radians(PI);
 pub const THREE_QUARTERS: Angle = Angle::Radians(PI + PI / 2.0);
-pub const FULL: Angle = Angle::Radians(2.0 * PI);
+
+/// How many times a ray may bounce off the sidewalls between two boundaries
+/// before it is considered trapped.
+pub const REFLECTION_LIMIT: usize = 100;
+
+/// Where a turtle got to a boundary.
+#[derive(Clone, Copy, Debug)]
+pub struct Crossing {
+	/// Whether it was the boundary above.
+	pub up: bool,
+
+	/// Rise per unit of run of the boundary where it was crossed.
+	pub slope: f64,
+}
 
 #[derive(Clone, Copy, Default, Debug)]
 pub struct Turtle {
@@ -15,7 +33,7 @@ pub struct Turtle {
 	pub pos: Point,
 
 	/// Current RI
-	pub ri: u8,
+	pub ri: f64,
 
 	/// Current direction
 	///
@@ -48,28 +66,100 @@ impl Turtle {
 		!self.is_going_left() && !self.is_going_right()
 	}
 
-	pub fn turn(&mut self, by: Angle) {
-		self.dir = (self.dir + by).normalized();
-	}
-
 	/// Recomputes directions from the RI change at a boundary.
 	///
-	/// Also does total internal reflection as needed.
-	pub fn refract_into(&mut self, new_ri: u8) {
+	/// Also does total internal reflection as needed, in which case the turtle
+	/// stays in its current medium and this returns false.
+	pub fn refract_into(&mut self, new_ri: f64) -> bool {
 		if self.is_vertical() || self.is_horizontal() || new_ri == self.ri {
 			// no refraction happens
 		} else {
+			let was_going_down = self.is_going_down();
 			self.dir = snells(self.ri, new_ri, self.dir);
+			if self.is_going_down() != was_going_down {
+				return false;
+			}
 		}
 
 		self.ri = new_ri;
+		true
 	}
 
-	/// Takes vertical distances to boundaries above and below,
-	/// outputs travel distance.
+	/// Like [`refract_into`](Self::refract_into), through a boundary with
+	/// this rise per unit of run, so the normal is tilted from the vertical.
+	pub fn refract_through(&mut self, new_ri: f64, slope: f64) -> bool {
+		if slope == 0.0 {
+			return self.refract_into(new_ri);
+		}
+		let tilt = ang::atan(-slope);
+		self.dir = (self.dir - tilt).normalized();
+		let refracted = self.refract_into(new_ri);
+		self.dir = (self.dir + tilt).normalized();
+		refracted
+	}
+
+	/// Takes boundaries above and below, outputs travel distance and which
+	/// was crossed.
 	///
-	/// Also does reflection as needed.
-	pub fn travel_to_next_boundary(&mut self, up: Microns, down: Microns) -> Microns {
-		todo!("travel")
+	/// Also does reflection off the sidewalls as needed, using the local normal
+	/// of the wall where the turtle hits it. Returns None if the
+	/// turtle bounces more than [`REFLECTION_LIMIT`] times without getting to
+	/// a boundary.
+	pub fn travel_to_next_boundary(
+		&mut self,
+		outline: Outline,
+		up: Boundary,
+		down: Boundary,
+	) -> Option<(Microns, Crossing)> {
+		let mut travel = 0.0;
+		let mut x = self.pos.x as f64;
+		let mut y = self.pos.y as f64;
+
+		for _ in 0..=REFLECTION_LIMIT {
+			let (dx, dy) = self.dir.sin_cos();
+
+			let wall = outline.wall_hit(x, y, dx, dy);
+			let limit = wall.map_or(f64::INFINITY, |hit| hit.distance);
+			let boundary = [(up, true), (down, false)]
+				.into_iter()
+				.filter_map(|(boundary, is_up)| {
+					let distance = boundary.exit(x, y, dx, dy, is_up, limit)?;
+					Some((distance, boundary, is_up))
+				})
+				.min_by(|a, b| a.0.total_cmp(&b.0));
+			let wall = wall.filter(|hit| boundary.is_none_or(|(to, ..)| hit.distance < to));
+
+			let Some(wall) = wall else {
+				let (to_boundary, boundary, is_up) = boundary?;
+				travel += to_boundary;
+				let x = x + dx * to_boundary;
+				self.pos = Point {
+					x: x.round().max(0.0) as _,
+					y: (y + dy * to_boundary).round().max(0.0) as _,
+				};
+				let crossing = Crossing {
+					up: is_up,
+					slope: boundary.slope_at(x),
+				};
+				return Some((travel.round() as _, crossing));
+			};
+
+			travel += wall.distance;
+			x += dx * wall.distance;
+			y += dy * wall.distance;
+			self.reflect(wall.slope);
+		}
+
+		None
+	}
+
+	/// Reflects off a surface, given as its horizontal run per unit of height.
+	fn reflect(&mut self, surface_slope: f64) {
+		let (dx, dy) = self.dir.sin_cos();
+		let norm = (1.0 + surface_slope * surface_slope).sqrt();
+		let (nx, ny) = (1.0 / norm, -surface_slope / norm);
+		let dot = dx * nx + dy * ny;
+		let (rx, ry) = (dx - 2.0 * dot * nx, dy - 2.0 * dot * ny);
+		self.dir = ang::atan2(rx, ry).normalized();
 	}
 }
@@ -1,12 +1,32 @@
+/// Lengths and positions, in micrometres
 pub type Microns = u64; // forwards from leftmost
 
+/// Convert from whole mm
+pub fn mm_to_microns(mm: u8) -> Microns {
+	(mm as Microns) * 1000
+}
+
+/// Convert from tenths of mm
 pub fn mm_tenths_to_microns(mm10ths: u8) -> Microns {
 	(mm10ths as Microns) * 100
 }
 
 /// Convert from model RI to real RI
 pub fn model_ri_to_real_ri(model_ri: u8) -> f64 {
-	f64::from(model_ri) * 0.01 + 0.99
+	(f64::from(model_ri) + 99.0) / 100.0
+}
+
+/// Convert from real RI to model RI, rounding to the nearest hundredth
+///
+/// Returns None if the RI can't be represented.
+pub fn real_ri_to_model_ri(real_ri: f64) -> Option<u8> {
+	let model = ((real_ri - 0.99) * 100.0).round();
+	if (1.0..=f64::from(u8::MAX)).contains(&model) {
+		Some(model as _)
+	} else {
+		None
+	}
 }
 
+/// Model RI of air, outside the lens
 pub const RI_AIR: u8 = 1;
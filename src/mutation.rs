@@ -1,54 +1,154 @@
-use std::num::NonZeroU8;
+use std::{cmp::Ordering, num::NonZeroU8};
 
-use crate::paramset::{normalise_partition_thickness, ParamSet, PARTITION_THICKNESSES};
+use genevo::random::Rng;
 
+use crate::{
+	paramset::{ParamSet, PARTITION_THICKNESSES},
+	sidewall::Sidewall,
+};
+
+/// Breeder value mutation of the genomes.
 pub mod breeder;
+/// Crossovers of the genomes.
 pub mod crossover;
+/// Random value mutation of the genomes.
 pub mod random;
+/// Repair of genomes after they're bred or mutated.
+pub mod repair;
+/// Mutations of the structure of the layer stack.
+pub mod structural;
+
+use structural::Structural;
+
+/// Gene 0 is the structure of the layer stack, which isn't a value: it's
+/// changed with [`Structural`] mutations instead.
+const STRUCTURE: usize = 0;
 
-fn prep<R>(mutation_rate: f64, rng: &mut R) -> (usize, usize)
+fn prep<R, const N: usize>(mutation_rate: f64, rng: &mut R) -> (usize, usize)
 where
 	R: genevo::random::Rng + Sized,
 {
-	let genome_length = 3 + ParamSet::LAYERS;
-	let num_mutations = ((genome_length as f64 * mutation_rate) + rng.gen::<f64>()).floor() as _;
-	(genome_length, num_mutations)
+	let genome_length = LAYERS_START + N;
+	(
+		genome_length,
+		mutation_count(genome_length, mutation_rate, rng),
+	)
 }
 
+/// How many genes to mutate, on average the rate times the length.
+fn mutation_count<R: Rng>(genome_length: usize, mutation_rate: f64, rng: &mut R) -> usize {
+	((genome_length as f64 * mutation_rate) + rng.gen::<f64>()).floor() as _
+}
+
+/// Genes before this index are for the general shape, after for layer RIs, or
+/// for catalog entries of the layers that have one.
+const LAYERS_START: usize = 5 + 2 * Sidewall::GENES;
+
 const PART_THICKNESS_RANGE: u8 =
 	PARTITION_THICKNESSES[PARTITION_THICKNESSES.len() - 1] - PARTITION_THICKNESSES[0];
 
-fn old_value(genome: ParamSet, index: usize) -> u8 {
+/// Mutates one gene, with `mutate` giving its new value from the old one.
+fn mutate_gene<R: Rng, const N: usize>(
+	genome: &mut ParamSet<N>,
+	index: usize,
+	rng: &mut R,
+	mutate: impl FnOnce(u8, &mut R) -> u8,
+) {
+	if index == STRUCTURE {
+		Structural::random(rng).apply(genome, rng);
+	} else {
+		let new = mutate(old_value(*genome, index), rng);
+		apply_value(genome, index, new);
+	}
+}
+
+fn old_value<const N: usize>(genome: ParamSet<N>, index: usize) -> u8 {
 	match index {
-		0 => genome.len() as u8,
+		STRUCTURE => unreachable!("the structure gene has no value"),
 		1 => genome.layers_thickness,
-		2 => genome.partitions_thickness * (u8::MAX / PART_THICKNESS_RANGE),
-		n => genome.layers[n - 3].map_or(0, |ri| ri.get()),
+		2 => {
+			let offset = genome
+				.partitions_thickness
+				.saturating_sub(PARTITION_THICKNESSES[0]);
+			(u16::from(offset.min(PART_THICKNESS_RANGE)) * u16::from(u8::MAX)
+				/ u16::from(PART_THICKNESS_RANGE)) as u8
+		}
+		3 => genome.width_top,
+		4 => genome.width_bottom,
+		n @ 5..LAYERS_START => {
+			let n = n - 5;
+			genome.sidewalls[n / Sidewall::GENES].genes()[n % Sidewall::GENES]
+		}
+		n => match genome.materials[n - LAYERS_START] {
+			0 => genome.layers[n - LAYERS_START].map_or(0, |ri| ri.get()),
+			entry => entry,
+		},
 	}
 }
 
-fn apply_value(genome: &mut ParamSet, index: usize, new: u8) {
+fn apply_value<const N: usize>(genome: &mut ParamSet<N>, index: usize, new: u8) {
 	match index {
-		0 => {
-			let current_len = genome.len();
-			let new_len = ParamSet::LAYERS.min((new as usize) % (ParamSet::LAYERS + 1));
-			if current_len < new_len {
-				genome.layers[current_len..new_len].fill(NonZeroU8::new(ParamSet::MINIMUM_RI));
-			} else if current_len > new_len {
-				genome.layers[(new_len - 1)..].fill(None);
-			}
-		}
+		STRUCTURE => unreachable!("the structure gene has no value"),
 		1 => {
 			genome.layers_thickness = new;
 		}
 		2 => {
-			let new_part = (new * PART_THICKNESS_RANGE) / u8::MAX;
-			genome.partitions_thickness = normalise_partition_thickness(new_part);
+			let new_part = (u16::from(new) * u16::from(PART_THICKNESS_RANGE) / u16::from(u8::MAX))
+				as u8 + PARTITION_THICKNESSES[0];
+			genome.partitions_thickness = new_part;
+		}
+		3 => {
+			genome.width_top = new;
+		}
+		4 => {
+			genome.width_bottom = new;
+		}
+		n @ 5..LAYERS_START => {
+			let n = n - 5;
+			let sidewall = &mut genome.sidewalls[n / Sidewall::GENES];
+			let mut genes = sidewall.genes();
+			genes[n % Sidewall::GENES] = new;
+			*sidewall = Sidewall::from_genes(genes);
 		}
 		n => {
-			if let Some(ri) = &mut genome.layers[n - 3] {
-				*ri = unsafe { NonZeroU8::new_unchecked(new.max(ParamSet::MINIMUM_RI)) };
+			// setting a layer to 0 removes it, repair closes the gap
+			let (layer, material) = (
+				&mut genome.layers[n - LAYERS_START],
+				&mut genome.materials[n - LAYERS_START],
+			);
+			if layer.is_none() {
+				return;
+			}
+			match (*material, new) {
+				(_, 0) => (*layer, *material) = (None, 0),
+				(0, ri) => *layer = NonZeroU8::new(ri),
+				// repair wraps entries around the catalog, and sets the RI
+				(_, entry) => *material = entry,
 			}
 		}
 	}
 }
+
+/// Where each gradient in the layers starts, and where the last one ends.
+///
+/// A gradient is a run of layers whose RI goes only one way; equal RIs don't
+/// break it.
+fn gradient_bounds<const N: usize>(params: &ParamSet<N>) -> Vec<usize> {
+	let len = params.len();
+	let mut bounds = vec![0];
+	let mut direction = Ordering::Equal;
+	for n in 1..len {
+		let step = params.layers[n].cmp(&params.layers[n - 1]);
+		if step == Ordering::Equal {
+			continue;
+		}
+		if direction != Ordering::Equal && step != direction {
+			bounds.push(n);
+		}
+		direction = step;
+	}
+	if len > 0 {
+		bounds.push(len);
+	}
+	bounds
+}
@@ -1,14 +1,43 @@
 use genevo::{
 	genetic::Genotype,
 	operator::prelude::{BreederGenomeMutation, BreederValueMutation, RandomValueMutation},
-	random::{random_index, SliceRandom},
+	random::{random_index, Rng, SliceRandom},
 };
 
-use crate::paramset::ParamSet;
+use crate::{
+	gradient::{GradientSet, GENES},
+	paramset::ParamSet,
+};
+
+use super::{mutate_gene, mutation_count, prep};
+
+/// A gene moved up or down by `range`, or a fraction of it.
+fn breeder_value<R: Rng>(
+	old: u8,
+	range: &u8,
+	precision: u8,
+	min_value: &u8,
+	max_value: &u8,
+	rng: &mut R,
+) -> u8 {
+	let sign = *[-1, 1].choose(rng).unwrap();
+	let adjustment = if *[true, false].choose(rng).unwrap() {
+		1. / (1i64 << precision) as f64
+	} else {
+		1.
+	};
 
-use super::{apply_value, old_value, prep};
+	let value_mut = BreederValueMutation::breeder_mutated(old, range, adjustment, sign);
+	if value_mut < *min_value {
+		RandomValueMutation::random_mutated(value_mut, min_value, max_value, rng)
+	} else if value_mut > *max_value {
+		*max_value
+	} else {
+		value_mut
+	}
+}
 
-impl BreederGenomeMutation for ParamSet {
+impl<const N: usize> BreederGenomeMutation for ParamSet<N> {
 	type Dna = u8;
 
 	fn mutate_genome<R>(
@@ -23,33 +52,39 @@ impl BreederGenomeMutation for ParamSet {
 	where
 		R: genevo::random::Rng + Sized,
 	{
-		let (genome_length, num_mutations) = prep(mutation_rate, rng);
+		let (genome_length, num_mutations) = prep::<_, N>(mutation_rate, rng);
 
 		let mut mutated = genome;
 		for _ in 0..num_mutations {
 			let index = random_index(rng, genome_length);
-			let sign = *[-1, 1].choose(rng).unwrap();
-			let adjustment = if *[true, false].choose(rng).unwrap() {
-				1. / (1i64 << precision) as f64
-			} else {
-				1.
-			};
-
-			let old = old_value(mutated, index);
-			let value_mut = BreederValueMutation::breeder_mutated(old, range, adjustment, sign);
-
-			apply_value(
-				&mut mutated,
-				index,
-				if value_mut < *min_value {
-					RandomValueMutation::random_mutated(value_mut, min_value, max_value, rng)
-				} else if value_mut > *max_value {
-					*max_value
-				} else {
-					value_mut
-				},
-			);
+			mutate_gene(&mut mutated, index, rng, |old, rng| {
+				breeder_value(old, range, precision, min_value, max_value, rng)
+			});
 		}
 		mutated
 	}
 }
+
+impl<const N: usize> BreederGenomeMutation for GradientSet<N> {
+	type Dna = u8;
+
+	fn mutate_genome<R>(
+		genome: Self,
+		mutation_rate: f64,
+		range: &<Self as Genotype>::Dna,
+		precision: u8,
+		min_value: &<Self as Genotype>::Dna,
+		max_value: &<Self as Genotype>::Dna,
+		rng: &mut R,
+	) -> Self
+	where
+		R: genevo::random::Rng + Sized,
+	{
+		let mut genes = genome.genes();
+		for _ in 0..mutation_count(GENES, mutation_rate, rng) {
+			let gene = &mut genes[random_index(rng, GENES)];
+			*gene = breeder_value(*gene, range, precision, min_value, max_value, rng);
+		}
+		Self::from_genes(genes)
+	}
+}
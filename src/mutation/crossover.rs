@@ -4,10 +4,15 @@ use genevo::{
 	random::Rng,
 };
 
-use crate::{array::ParamArray, paramset::ParamSet};
+use crate::{
+	array::ParamArray,
+	gradient::{GradientSet, GENES},
+	paramset::ParamSet,
+};
 
-use super::old_value;
+use super::gradient_bounds;
 
+/// Each gene of each child is taken from a random parent.
 #[derive(Default, Clone, Copy, Debug, PartialEq)]
 pub struct AgileCrossBreeder;
 
@@ -17,27 +22,475 @@ impl GeneticOperator for AgileCrossBreeder {
 	}
 }
 
-impl CrossoverOp<ParamSet> for AgileCrossBreeder {
-	fn crossover<R>(&self, parents: Parents<ParamSet>, rng: &mut R) -> Children<ParamSet>
+impl<const N: usize> CrossoverOp<ParamSet<N>> for AgileCrossBreeder {
+	fn crossover<R>(&self, parents: Parents<ParamSet<N>>, rng: &mut R) -> Children<ParamSet<N>>
 	where
 		R: Rng + Sized,
 	{
-		let num_parents = parents.len();
+		let parents: Vec<ParamArray<N>> = parents.into_iter().map(ParamArray::from).collect();
 
 		// breed one child for each partner in parents
-		let mut offspring: Vec<ParamArray> = Vec::with_capacity(num_parents);
-		while num_parents > offspring.len() {
-			let mut genome = ParamArray::default();
-			// for each value in the genotype
-			for locus in 0..ParamSet::LAYERS {
-				// pick the value of a randomly chosen parent
-				let random = rng.gen_range(0..num_parents);
-				let value = old_value(parents[random], locus);
-				genome.set(locus, value);
+		(0..parents.len())
+			.map(|_| {
+				let mut genome = ParamArray::default();
+				for locus in 0..ParamArray::<N>::LEN {
+					let parent = &parents[rng.gen_range(0..parents.len())];
+					genome.set(locus, parent.get(locus));
+				}
+				genome.into()
+			})
+			.collect()
+	}
+}
+
+/// Breeds one child per parent, with the next parent as its partner.
+fn pairwise<G>(parents: Parents<G>, mut breed: impl FnMut(&G, &G) -> G) -> Children<G> {
+	(0..parents.len())
+		.map(|n| breed(&parents[n], &parents[(n + 1) % parents.len()]))
+		.collect()
+}
+
+/// Layers of `a`, with those from `from` up to `to` replaced by those of `b`,
+/// along with their partitions and catalog entries.
+///
+/// Gaps left by a shorter parent are closed by repair.
+fn splice<const N: usize>(a: &ParamSet<N>, b: &ParamSet<N>, from: usize, to: usize) -> ParamSet<N> {
+	let mut child = *a;
+	child.layers[from..to].copy_from_slice(&b.layers[from..to]);
+	child.materials[from..to].copy_from_slice(&b.materials[from..to]);
+	let mask = bits(from, to);
+	child.open = (a.open & !mask) | (b.open & mask);
+	child
+}
+
+/// Bits of the layers from `from` up to `to`.
+fn bits(from: usize, to: usize) -> u32 {
+	let below = |n: usize| u32::MAX.checked_shl(n as _).unwrap_or(0);
+	below(from) & !below(to)
+}
+
+/// Layers up to a random point from one parent, and the rest from the other.
+///
+/// The child has the shape of the first parent.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct OnePointCrossBreeder;
+
+impl GeneticOperator for OnePointCrossBreeder {
+	fn name() -> String {
+		"One-Point-Cross-Breeder".to_string()
+	}
+}
+
+impl<const N: usize> CrossoverOp<ParamSet<N>> for OnePointCrossBreeder {
+	fn crossover<R>(&self, parents: Parents<ParamSet<N>>, rng: &mut R) -> Children<ParamSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		pairwise(parents, |a, b| {
+			let cut = rng.gen_range(0..=a.len().max(b.len()));
+			splice(a, b, cut, N)
+		})
+	}
+}
+
+/// Layers between two random points from one parent, and the rest from the
+/// other.
+///
+/// The child has the shape of the first parent.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct TwoPointCrossBreeder;
+
+impl GeneticOperator for TwoPointCrossBreeder {
+	fn name() -> String {
+		"Two-Point-Cross-Breeder".to_string()
+	}
+}
+
+impl<const N: usize> CrossoverOp<ParamSet<N>> for TwoPointCrossBreeder {
+	fn crossover<R>(&self, parents: Parents<ParamSet<N>>, rng: &mut R) -> Children<ParamSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		pairwise(parents, |a, b| {
+			let len = a.len().max(b.len());
+			let (x, y) = (rng.gen_range(0..=len), rng.gen_range(0..=len));
+			splice(a, b, x.min(y), x.max(y))
+		})
+	}
+}
+
+/// Whole gradients from the top of one parent, then whole gradients from the
+/// bottom of the other, so no gradient is cut in the middle.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct BlockCrossBreeder;
+
+impl GeneticOperator for BlockCrossBreeder {
+	fn name() -> String {
+		"Block-Cross-Breeder".to_string()
+	}
+}
+
+impl<const N: usize> CrossoverOp<ParamSet<N>> for BlockCrossBreeder {
+	fn crossover<R>(&self, parents: Parents<ParamSet<N>>, rng: &mut R) -> Children<ParamSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		pairwise(parents, |a, b| {
+			let a_bounds = gradient_bounds(a);
+			let cut = a_bounds[rng.gen_range(0..a_bounds.len())];
+
+			// b's first gradient that starts at or after the cut
+			let from = gradient_bounds(b)
+				.into_iter()
+				.find(|bound| *bound >= cut)
+				.unwrap_or(cut);
+			let rest = b.len().saturating_sub(from);
+
+			let mut child = *a;
+			child.layers[cut..].fill(None);
+			child.layers[cut..cut + rest].copy_from_slice(&b.layers[from..from + rest]);
+			child.materials[cut..].fill(0);
+			child.materials[cut..cut + rest].copy_from_slice(&b.materials[from..from + rest]);
+			let moved = ((b.open & bits(from, from + rest)) >> from) << cut;
+			child.open = (a.open & bits(0, cut)) | moved;
+			child
+		})
+	}
+}
+
+/// A value from `a + w × (b - a)`, with `w` drawn from `-alpha` to `1 + alpha`.
+fn blend<R: Rng>(a: u8, b: u8, alpha: f64, rng: &mut R) -> u8 {
+	let weight = rng.gen_range(-alpha..=1.0 + alpha);
+	let value = f64::from(a) + weight * (f64::from(b) - f64::from(a));
+	value.round().clamp(0.0, f64::from(u8::MAX)) as u8
+}
+
+/// Layer and partition thicknesses blended between the parents, everything
+/// else from the first.
+///
+/// Each thickness is `a + w × (b - a)`, with `w` drawn from `-alpha` to
+/// `1 + alpha`, so children can also be a little beyond their parents.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct BlendCrossBreeder {
+	/// How far children can go beyond their parents, as a fraction of the
+	/// difference between them.
+	pub alpha: f64,
+}
+
+impl GeneticOperator for BlendCrossBreeder {
+	fn name() -> String {
+		"Blend-Cross-Breeder".to_string()
+	}
+}
+
+impl<const N: usize> CrossoverOp<ParamSet<N>> for BlendCrossBreeder {
+	fn crossover<R>(&self, parents: Parents<ParamSet<N>>, rng: &mut R) -> Children<ParamSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		let alpha = self.alpha;
+		pairwise(parents, |a, b| {
+			let mut blend = |a, b| blend(a, b, alpha, rng);
+			let mut child = *a;
+			child.layers_thickness = blend(a.layers_thickness, b.layers_thickness);
+			child.partitions_thickness = blend(a.partitions_thickness, b.partitions_thickness);
+			child
+		})
+	}
+}
+
+// For gradients, crossovers work on the genes as they are: the curve
+// parameters take the place of the layers.
+
+/// Genes of the curve of a [`GradientSet`], which come last.
+const CURVE_GENES: usize = 6;
+
+impl<const N: usize> CrossoverOp<GradientSet<N>> for AgileCrossBreeder {
+	fn crossover<R>(
+		&self,
+		parents: Parents<GradientSet<N>>,
+		rng: &mut R,
+	) -> Children<GradientSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		let parents: Vec<_> = parents.into_iter().map(GradientSet::genes).collect();
+		(0..parents.len())
+			.map(|_| {
+				GradientSet::from_genes(std::array::from_fn(|locus| {
+					parents[rng.gen_range(0..parents.len())][locus]
+				}))
+			})
+			.collect()
+	}
+}
+
+/// Genes of `a`, with those from `from` up to `to` replaced by those of `b`.
+fn splice_genes<const N: usize>(
+	a: &GradientSet<N>,
+	b: &GradientSet<N>,
+	from: usize,
+	to: usize,
+) -> GradientSet<N> {
+	let mut genes = a.genes();
+	genes[from..to].copy_from_slice(&b.genes()[from..to]);
+	GradientSet::from_genes(genes)
+}
+
+impl<const N: usize> CrossoverOp<GradientSet<N>> for OnePointCrossBreeder {
+	fn crossover<R>(
+		&self,
+		parents: Parents<GradientSet<N>>,
+		rng: &mut R,
+	) -> Children<GradientSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		pairwise(parents, |a, b| {
+			splice_genes(a, b, rng.gen_range(0..=GENES), GENES)
+		})
+	}
+}
+
+impl<const N: usize> CrossoverOp<GradientSet<N>> for TwoPointCrossBreeder {
+	fn crossover<R>(
+		&self,
+		parents: Parents<GradientSet<N>>,
+		rng: &mut R,
+	) -> Children<GradientSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		pairwise(parents, |a, b| {
+			let (x, y) = (rng.gen_range(0..=GENES), rng.gen_range(0..=GENES));
+			splice_genes(a, b, x.min(y), x.max(y))
+		})
+	}
+}
+
+/// The whole curve from one parent, and the shape from the other.
+impl<const N: usize> CrossoverOp<GradientSet<N>> for BlockCrossBreeder {
+	fn crossover<R>(&self, parents: Parents<GradientSet<N>>, _: &mut R) -> Children<GradientSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		pairwise(parents, |a, b| {
+			splice_genes(a, b, GENES - CURVE_GENES, GENES)
+		})
+	}
+}
+
+/// Thicknesses and the RIs at both ends of the curve are blended.
+impl<const N: usize> CrossoverOp<GradientSet<N>> for BlendCrossBreeder {
+	fn crossover<R>(
+		&self,
+		parents: Parents<GradientSet<N>>,
+		rng: &mut R,
+	) -> Children<GradientSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		let alpha = self.alpha;
+		pairwise(parents, |a, b| {
+			let mut blend = |a, b| blend(a, b, alpha, rng);
+			GradientSet {
+				layers_thickness: blend(a.layers_thickness, b.layers_thickness),
+				partitions_thickness: blend(a.partitions_thickness, b.partitions_thickness),
+				top: blend(a.top, b.top),
+				bottom: blend(a.bottom, b.bottom),
+				..*a
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use genevo::random::{Prng, SeedableRng};
+
+	use super::*;
+	use crate::{paramset::DEFAULT_LAYERS, sidewall::Sidewall};
+
+	fn parents() -> Vec<ParamSet> {
+		[
+			"top:104mm bottom:52mm layer:4.00mm part:1.00mm | 1.33 ~ 1.38 1.42#3 1.47 1.40 |",
+			"top:90mm bottom:40mm left:parabolic(80) right:straight layer:6.50mm part:2.00mm | 1.50#1 1.45 ~ 1.41 1.36#2 1.34 ~ 1.35 1.44 |",
+			"top:120mm bottom:60mm layer:3.00mm part:0.50mm | 1.48 1.39 |",
+		]
+		.map(|text| text.parse().unwrap())
+		.to_vec()
+	}
+
+	/// Whether the child took layer `n` from this parent, with its partition
+	/// and catalog entry.
+	fn layer_from(child: &ParamSet, parent: &ParamSet, n: usize) -> bool {
+		child.layers[n] == parent.layers[n]
+			&& child.materials[n] == parent.materials[n]
+			&& child.partitioned(n) == parent.partitioned(n)
+	}
+
+	fn same_shape(child: &ParamSet, parent: &ParamSet) -> bool {
+		child.width_top == parent.width_top
+			&& child.width_bottom == parent.width_bottom
+			&& child.sidewalls == parent.sidewalls
+			&& child.layers_thickness == parent.layers_thickness
+			&& child.partitions_thickness == parent.partitions_thickness
+	}
+
+	#[test]
+	fn uniform_takes_each_gene_from_a_parent() {
+		let parents = parents();
+		let arrays: Vec<ParamArray<DEFAULT_LAYERS>> =
+			parents.iter().copied().map(ParamArray::from).collect();
+		let mut rng = Prng::seed_from_u64(1);
+		for _ in 0..20 {
+			let children = AgileCrossBreeder.crossover(parents.clone(), &mut rng);
+			assert_eq!(children.len(), parents.len());
+			for child in children {
+				let child = ParamArray::from(child);
+				for locus in 0..ParamArray::<DEFAULT_LAYERS>::LEN {
+					assert!(arrays.iter().any(|p| p.get(locus) == child.get(locus)));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn point_crossovers_keep_the_shape() {
+		let parents = parents();
+		let mut rng = Prng::seed_from_u64(2);
+		for _ in 0..50 {
+			let children = [
+				OnePointCrossBreeder.crossover(parents.clone(), &mut rng),
+				TwoPointCrossBreeder.crossover(parents.clone(), &mut rng),
+			];
+			for children in children {
+				for (n, child) in children.iter().enumerate() {
+					let (a, b) = (&parents[n], &parents[(n + 1) % parents.len()]);
+					assert!(same_shape(child, a));
+					for layer in 0..DEFAULT_LAYERS {
+						assert!(layer_from(child, a, layer) || layer_from(child, b, layer));
+					}
+				}
 			}
-			offspring.push(genome);
 		}
+	}
 
-		offspring.into_iter().map(ParamSet::from).collect()
+	#[test]
+	fn one_point_takes_the_rest_from_the_partner() {
+		let parents = parents();
+		let mut rng = Prng::seed_from_u64(3);
+		for _ in 0..50 {
+			let children = OnePointCrossBreeder.crossover(parents.clone(), &mut rng);
+			for (n, child) in children.iter().enumerate() {
+				let (a, b) = (&parents[n], &parents[(n + 1) % parents.len()]);
+				let cut = (0..DEFAULT_LAYERS)
+					.find(|layer| !layer_from(child, a, *layer))
+					.unwrap_or(DEFAULT_LAYERS);
+				for layer in cut..DEFAULT_LAYERS {
+					assert!(layer_from(child, b, layer));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn finds_gradient_bounds() {
+		let [a, b, c] = parents().try_into().unwrap();
+		assert_eq!(gradient_bounds(&a), [0, 4, 5]);
+		assert_eq!(gradient_bounds(&b), [0, 5, 7]);
+		assert_eq!(gradient_bounds(&c), [0, 2]);
+		assert_eq!(gradient_bounds(&ParamSet::<10>::default()), [0, 1]);
+	}
+
+	#[test]
+	fn block_moves_whole_gradients() {
+		let parents = parents();
+		let mut rng = Prng::seed_from_u64(4);
+		for _ in 0..50 {
+			let children = BlockCrossBreeder.crossover(parents.clone(), &mut rng);
+			for (n, child) in children.iter().enumerate() {
+				let (a, b) = (&parents[n], &parents[(n + 1) % parents.len()]);
+				assert!(same_shape(child, a));
+				// some cut in a, then b from some bound on
+				let found = gradient_bounds(a).into_iter().any(|cut| {
+					(0..cut).all(|layer| layer_from(child, a, layer))
+						&& gradient_bounds(b).into_iter().chain([cut]).any(|from| {
+							from >= cut
+								&& (cut..DEFAULT_LAYERS).all(|layer| {
+									let source = from + layer - cut;
+									let ri = b.layers.get(source).copied().flatten();
+									let entry = b.materials.get(source).copied().unwrap_or(0);
+									child.layers[layer] == ri
+										&& child.materials[layer] == entry
+										&& (layer == 0
+											|| child.partitioned(layer) == b.partitioned(source))
+								})
+						})
+				});
+				assert!(found, "{child} from {a} and {b}");
+			}
+		}
+	}
+
+	#[test]
+	fn blend_stays_between_parents_without_alpha() {
+		let parents = parents();
+		let mut rng = Prng::seed_from_u64(5);
+		let blend = BlendCrossBreeder { alpha: 0.0 };
+		for _ in 0..50 {
+			let children = blend.crossover(parents.clone(), &mut rng);
+			for (n, child) in children.iter().enumerate() {
+				let (a, b) = (&parents[n], &parents[(n + 1) % parents.len()]);
+				let between = |value: u8, x: u8, y: u8| x.min(y) <= value && value <= x.max(y);
+				assert!(between(
+					child.layers_thickness,
+					a.layers_thickness,
+					b.layers_thickness
+				));
+				assert!(between(
+					child.partitions_thickness,
+					a.partitions_thickness,
+					b.partitions_thickness
+				));
+				assert_eq!(
+					ParamSet {
+						layers_thickness: a.layers_thickness,
+						partitions_thickness: a.partitions_thickness,
+						..*child
+					},
+					*a
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn gradient_crossovers_take_each_gene_from_a_parent() {
+		// walls and curve kind the same, as their genes don't all round-trip
+		let walls_end = 4 + 2 * Sidewall::GENES;
+		let shared = 4..walls_end + 2;
+		let parents: Vec<GradientSet> = (0..3u8)
+			.map(|n| {
+				GradientSet::from_genes(std::array::from_fn(|locus| {
+					let own = locus == walls_end || !shared.contains(&locus);
+					locus as u8 + if own { n * 50 } else { 0 }
+				}))
+			})
+			.collect();
+		let mut rng = Prng::seed_from_u64(6);
+		for _ in 0..20 {
+			let children = [
+				AgileCrossBreeder.crossover(parents.clone(), &mut rng),
+				OnePointCrossBreeder.crossover(parents.clone(), &mut rng),
+				TwoPointCrossBreeder.crossover(parents.clone(), &mut rng),
+				BlockCrossBreeder.crossover(parents.clone(), &mut rng),
+			];
+			for child in children.into_iter().flatten() {
+				for (locus, gene) in child.genes().into_iter().enumerate() {
+					assert!(parents.iter().any(|p| p.genes()[locus] == gene));
+				}
+			}
+		}
 	}
 }
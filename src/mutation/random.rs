@@ -4,11 +4,14 @@ use genevo::{
 	random::random_index,
 };
 
-use crate::paramset::ParamSet;
+use crate::{
+	gradient::{GradientSet, GENES},
+	paramset::ParamSet,
+};
 
-use super::{apply_value, old_value, prep};
+use super::{mutate_gene, mutation_count, prep};
 
-impl RandomGenomeMutation for ParamSet {
+impl<const N: usize> RandomGenomeMutation for ParamSet<N> {
 	type Dna = u8;
 
 	fn mutate_genome<R>(
@@ -21,17 +24,38 @@ impl RandomGenomeMutation for ParamSet {
 	where
 		R: genevo::random::Rng + Sized,
 	{
-		let (genome_length, num_mutations) = prep(mutation_rate, rng);
+		let (genome_length, num_mutations) = prep::<_, N>(mutation_rate, rng);
 
 		let mut mutated = genome;
 		for _ in 0..num_mutations {
 			let index = random_index(rng, genome_length);
-
-			let old = old_value(mutated, index);
-			let new = RandomValueMutation::random_mutated(old, min_value, max_value, rng);
-			apply_value(&mut mutated, index, new);
+			mutate_gene(&mut mutated, index, rng, |old, rng| {
+				RandomValueMutation::random_mutated(old, min_value, max_value, rng)
+			});
 		}
 
 		mutated
 	}
 }
+
+impl<const N: usize> RandomGenomeMutation for GradientSet<N> {
+	type Dna = u8;
+
+	fn mutate_genome<R>(
+		genome: Self,
+		mutation_rate: f64,
+		min_value: &<Self as Genotype>::Dna,
+		max_value: &<Self as Genotype>::Dna,
+		rng: &mut R,
+	) -> Self
+	where
+		R: genevo::random::Rng + Sized,
+	{
+		let mut genes = genome.genes();
+		for _ in 0..mutation_count(GENES, mutation_rate, rng) {
+			let gene = &mut genes[random_index(rng, GENES)];
+			*gene = RandomValueMutation::random_mutated(*gene, min_value, max_value, rng);
+		}
+		Self::from_genes(genes)
+	}
+}
@@ -0,0 +1,314 @@
+use std::{
+	num::NonZeroU8,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+};
+
+use genevo::{
+	genetic::{Children, Parents},
+	operator::{CrossoverOp, GeneticOperator, MutationOp},
+	random::Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	constraints::Constraints,
+	paramset::{normalise_partition_thickness, ParamSet, MAXIMUM_RI, MINIMUM_RI},
+};
+
+/// Moves all layers to the front of the list, keeping their order, their
+/// partitions and their catalog entries.
+///
+/// Returns whether anything moved.
+fn compact<const N: usize>(params: &mut ParamSet<N>) -> bool {
+	let mut next = 0;
+	let mut moved = false;
+	for n in 0..N {
+		if let Some(ri) = params.layers[n] {
+			if n != next {
+				params.layers[next] = Some(ri);
+				params.layers[n] = None;
+				params.materials[next] = params.materials[n];
+				params.materials[n] = 0;
+				let partitioned = params.partitioned(n);
+				params.set_partitioned(n, true);
+				params.set_partitioned(next, partitioned);
+				moved = true;
+			}
+			next += 1;
+		}
+	}
+	moved
+}
+
+/// Clamps all RIs to the usable range.
+///
+/// Returns whether any were out of it.
+fn clamp_ris<const N: usize>(params: &mut ParamSet<N>) -> bool {
+	let mut clamped = false;
+	for ri in params.layers.iter_mut().flatten() {
+		let value = ri.get().clamp(MINIMUM_RI, MAXIMUM_RI);
+		if value != ri.get() {
+			*ri = NonZeroU8::new(value).expect("RI range doesn't include 0");
+			clamped = true;
+		}
+	}
+	clamped
+}
+
+/// Snaps the partition thickness to the nearest of those available, or of
+/// [`PARTITION_THICKNESSES`](crate::paramset::PARTITION_THICKNESSES) if there's no list of them.
+///
+/// Returns whether it wasn't already.
+fn snap_thickness<const N: usize>(params: &mut ParamSet<N>, available: Option<&[u8]>) -> bool {
+	let thickness = params.partitions_thickness;
+	let snapped = match available {
+		Some(available) => available
+			.iter()
+			.copied()
+			.min_by_key(|p| p.abs_diff(thickness))
+			.unwrap_or(thickness),
+		None => normalise_partition_thickness(thickness),
+	};
+	let changed = snapped != params.partitions_thickness;
+	params.partitions_thickness = snapped;
+	changed
+}
+
+/// How often each kind of repair was needed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairStats {
+	/// Genomes that went through repair.
+	pub checked: u64,
+
+	/// Genomes that needed any repair at all.
+	pub repaired: u64,
+
+	/// Genomes that had gaps in their layers.
+	pub compacted: u64,
+
+	/// Genomes that had RIs out of range.
+	pub ri_clamped: u64,
+
+	/// Genomes that had an unusable partition thickness.
+	pub thickness_snapped: u64,
+
+	/// Genomes that were changed to meet the constraints.
+	pub constrained: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+	checked: AtomicU64,
+	repaired: AtomicU64,
+	compacted: AtomicU64,
+	ri_clamped: AtomicU64,
+	thickness_snapped: AtomicU64,
+	constrained: AtomicU64,
+}
+
+/// Brings genomes back to a valid state after they're changed by operators.
+///
+/// Clones share their counters, so one repairer can be given to several
+/// operators and report on all of them.
+#[derive(Clone, Debug, Default)]
+pub struct Repairer {
+	/// What genomes are repaired to keep to.
+	pub constraints: Constraints,
+	counters: Arc<Counters>,
+}
+
+impl Repairer {
+	/// Repairer with its counters at 0.
+	pub fn new(constraints: Constraints) -> Self {
+		Self {
+			constraints,
+			counters: Arc::default(),
+		}
+	}
+
+	/// Repairs a genome in place.
+	///
+	/// The layer list is compacted, RIs are clamped, the partition thickness is
+	/// snapped to a usable one, and finally constraints are enforced.
+	pub fn repair<const N: usize>(&self, params: &mut ParamSet<N>) {
+		let count = |counter: &AtomicU64, hit: bool| {
+			if hit {
+				counter.fetch_add(1, Ordering::Relaxed);
+			}
+			hit
+		};
+
+		let c = &self.counters;
+		let before = *params;
+		count(&c.checked, true);
+		count(&c.compacted, compact(params));
+		count(&c.ri_clamped, clamp_ris(params));
+		count(
+			&c.thickness_snapped,
+			snap_thickness(params, self.constraints.model_partitions().as_deref()),
+		);
+
+		let unconstrained = *params;
+		self.constraints.repair(params);
+		count(&c.constrained, *params != unconstrained);
+		count(&c.repaired, *params != before);
+	}
+
+	/// Carries on counting from earlier counts, as when resuming a run.
+	pub fn restore(&self, stats: RepairStats) {
+		let c = &self.counters;
+		let set = |counter: &AtomicU64, value| counter.store(value, Ordering::Relaxed);
+		set(&c.checked, stats.checked);
+		set(&c.repaired, stats.repaired);
+		set(&c.compacted, stats.compacted);
+		set(&c.ri_clamped, stats.ri_clamped);
+		set(&c.thickness_snapped, stats.thickness_snapped);
+		set(&c.constrained, stats.constrained);
+	}
+
+	/// Current counts of repairs.
+	pub fn stats(&self) -> RepairStats {
+		let c = &self.counters;
+		let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+		RepairStats {
+			checked: get(&c.checked),
+			repaired: get(&c.repaired),
+			compacted: get(&c.compacted),
+			ri_clamped: get(&c.ri_clamped),
+			thickness_snapped: get(&c.thickness_snapped),
+			constrained: get(&c.constrained),
+		}
+	}
+}
+
+/// Wraps a crossover or mutation operator to repair every genome it produces.
+#[derive(Clone, Debug)]
+pub struct Repaired<O> {
+	/// Operator whose genomes are repaired.
+	pub inner: O,
+	/// Repairer that counts the repairs.
+	pub repairer: Repairer,
+}
+
+impl<O: GeneticOperator> GeneticOperator for Repaired<O> {
+	fn name() -> String {
+		format!("Repaired-{}", O::name())
+	}
+}
+
+impl<O, const N: usize> CrossoverOp<ParamSet<N>> for Repaired<O>
+where
+	O: CrossoverOp<ParamSet<N>>,
+{
+	fn crossover<R>(&self, parents: Parents<ParamSet<N>>, rng: &mut R) -> Children<ParamSet<N>>
+	where
+		R: Rng + Sized,
+	{
+		let mut children = self.inner.crossover(parents, rng);
+		for child in &mut children {
+			self.repairer.repair(child);
+		}
+		children
+	}
+}
+
+impl<O, const N: usize> MutationOp<ParamSet<N>> for Repaired<O>
+where
+	O: MutationOp<ParamSet<N>>,
+{
+	fn mutate<R>(&self, genome: ParamSet<N>, rng: &mut R) -> ParamSet<N>
+	where
+		R: Rng + Sized,
+	{
+		let before = genome;
+		let mut genome = self.inner.mutate(genome, rng);
+		self.repairer.constraints.step_liquids(&before, &mut genome);
+		self.repairer.repair(&mut genome);
+		genome
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compacts_clamps_and_snaps() {
+		let mut params: ParamSet = "layer:3.00mm part:1.00mm | 1.35 1.40 1.45 |"
+			.parse()
+			.unwrap();
+		params.layers[1] = None;
+		params.layers[2] = NonZeroU8::new(MAXIMUM_RI + 3);
+		params.partitions_thickness = 11;
+
+		let repairer = Repairer::default();
+		repairer.repair(&mut params);
+		let expected: ParamSet = "layer:3.00mm part:1.00mm | 1.35 1.50 |".parse().unwrap();
+		assert_eq!(params, expected);
+		assert_eq!(
+			repairer.stats(),
+			RepairStats {
+				checked: 1,
+				repaired: 1,
+				compacted: 1,
+				ri_clamped: 1,
+				thickness_snapped: 1,
+				constrained: 0,
+			}
+		);
+	}
+
+	#[test]
+	fn compacts_open_layers_with_their_partitions() {
+		let mut params: ParamSet = "layer:3.00mm part:1.00mm | 1.35 1.40 ~ 1.45 1.38 |"
+			.parse()
+			.unwrap();
+		// a gap above an open layer
+		params.layers[1] = None;
+		assert!(compact(&mut params));
+		let expected: ParamSet = "layer:3.00mm part:1.00mm | 1.35 ~ 1.45 1.38 |"
+			.parse()
+			.unwrap();
+		assert_eq!(params, expected);
+		assert!(!compact(&mut params));
+	}
+
+	#[test]
+	fn counts_across_clones() {
+		let repairer = Repairer::new(Constraints {
+			partitions: Some(vec![2.5]),
+			..Constraints::default()
+		});
+		let clone = repairer.clone();
+		let mut params = ParamSet::<10>::default();
+		clone.repair(&mut params);
+		assert_eq!(params.partitions_thickness, 25);
+		// a valid genome is checked but left alone
+		repairer.repair(&mut params);
+
+		let stats = repairer.stats();
+		assert_eq!((stats.checked, stats.repaired), (2, 1));
+		assert_eq!((stats.thickness_snapped, stats.constrained), (1, 0));
+	}
+
+	#[test]
+	fn restores_counts() {
+		let stats = RepairStats {
+			checked: 9,
+			repaired: 4,
+			compacted: 3,
+			ri_clamped: 2,
+			thickness_snapped: 1,
+			constrained: 5,
+		};
+		let repairer = Repairer::default();
+		repairer.restore(stats);
+		assert_eq!(repairer.stats(), stats);
+
+		repairer.repair(&mut ParamSet::<10>::default());
+		assert_eq!(repairer.stats().checked, 10);
+	}
+}
@@ -0,0 +1,310 @@
+use std::num::NonZeroU8;
+
+use genevo::random::Rng;
+
+use crate::paramset::{ParamSet, MAXIMUM_RI, MINIMUM_RI};
+
+use super::gradient_bounds;
+
+/// Changes to the layer stack as a whole, rather than to one value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Structural {
+	/// A layer at a random depth, with an RI between those around it and no
+	/// catalog entry, so repair fills it with the nearest liquid.
+	Insert,
+
+	/// A random layer removed, keeping at least one.
+	Delete,
+
+	/// Two adjacent layers swapped.
+	Swap,
+
+	/// All RIs of a gradient moved one step up or down together, with their
+	/// catalog entries cleared so repair fills them by RI.
+	Shift,
+
+	/// The partition above a random layer, other than the top one, taken away
+	/// or put back.
+	Toggle,
+}
+
+/// Bits of the layers above a depth.
+fn bits_above(depth: usize) -> u32 {
+	(1 << depth) - 1
+}
+
+impl Structural {
+	/// Every kind of change.
+	pub const ALL: [Self; 5] = [
+		Self::Insert,
+		Self::Delete,
+		Self::Swap,
+		Self::Shift,
+		Self::Toggle,
+	];
+
+	/// A kind of change, each as likely.
+	pub fn random<R: Rng>(rng: &mut R) -> Self {
+		Self::ALL[rng.gen_range(0..Self::ALL.len())]
+	}
+
+	/// Applies the change, if the stack allows it.
+	///
+	/// Returns whether anything changed. RIs may be taken out of range, and
+	/// partitions taken away between liquids that won't stay apart, to be
+	/// brought back by repair. Layers keep their partitions and catalog
+	/// entries as they move.
+	pub fn apply<const N: usize, R: Rng>(self, genome: &mut ParamSet<N>, rng: &mut R) -> bool {
+		let len = genome.len();
+		let layers = &mut genome.layers;
+		match self {
+			Self::Insert if len < N => {
+				let depth = rng.gen_range(0..=len);
+				let above = depth.checked_sub(1).and_then(|n| layers[n]);
+				let ri = match (above, layers[depth]) {
+					(Some(a), Some(b)) => {
+						NonZeroU8::new(((u16::from(a.get()) + u16::from(b.get())) / 2) as u8)
+					}
+					(Some(ri), None) | (None, Some(ri)) => Some(ri),
+					(None, None) => NonZeroU8::new(rng.gen_range(MINIMUM_RI..=MAXIMUM_RI)),
+				};
+				layers[depth..=len].rotate_right(1);
+				layers[depth] = ri;
+				genome.materials[depth..=len].rotate_right(1);
+				genome.materials[depth] = 0;
+				genome.open =
+					(genome.open & bits_above(depth)) | ((genome.open & !bits_above(depth)) << 1);
+				true
+			}
+			Self::Delete if len > 1 => {
+				let depth = rng.gen_range(0..len);
+				layers[depth..len].rotate_left(1);
+				layers[len - 1] = None;
+				genome.materials[depth..len].rotate_left(1);
+				genome.materials[len - 1] = 0;
+				genome.open = (genome.open & bits_above(depth))
+					| ((genome.open >> 1) & !bits_above(depth)) & !1;
+				true
+			}
+			Self::Swap if len > 1 => {
+				let depth = rng.gen_range(0..len - 1);
+				layers.swap(depth, depth + 1);
+				genome.materials.swap(depth, depth + 1);
+				let moved = layers[depth] != layers[depth + 1]
+					|| genome.materials[depth] != genome.materials[depth + 1];
+				let (above, below) = (genome.partitioned(depth), genome.partitioned(depth + 1));
+				genome.set_partitioned(depth, below);
+				genome.set_partitioned(depth + 1, above);
+				moved || above != below
+			}
+			Self::Shift if len > 0 => {
+				let bounds = gradient_bounds(genome);
+				let n = rng.gen_range(0..bounds.len() - 1);
+				let up = rng.gen_bool(0.5);
+				for ri in genome.layers[bounds[n]..bounds[n + 1]].iter_mut().flatten() {
+					*ri = NonZeroU8::new(if up {
+						ri.get().saturating_add(1)
+					} else {
+						ri.get() - 1
+					})
+					.unwrap_or(*ri);
+				}
+				genome.materials[bounds[n]..bounds[n + 1]].fill(0);
+				true
+			}
+			Self::Toggle if len > 1 => {
+				let layer = rng.gen_range(1..len);
+				genome.set_partitioned(layer, !genome.partitioned(layer));
+				true
+			}
+			_ => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use genevo::random::{Prng, SeedableRng};
+
+	use super::*;
+
+	fn genome() -> ParamSet<6> {
+		"layer:3.00mm part:1.00mm | 1.33 1.38 1.42 1.40 1.36 |"
+			.parse()
+			.unwrap()
+	}
+
+	fn ris<const N: usize>(genome: &ParamSet<N>) -> Vec<u8> {
+		genome.layers.iter().flatten().map(|ri| ri.get()).collect()
+	}
+
+	/// Applies the change many times over to the same genome.
+	fn each_outcome(
+		change: Structural,
+		genome: ParamSet<6>,
+		mut check: impl FnMut(ParamSet<6>, bool),
+	) {
+		let mut rng = Prng::seed_from_u64(1);
+		for _ in 0..100 {
+			let mut changed = genome;
+			let applied = change.apply(&mut changed, &mut rng);
+			check(changed, applied);
+		}
+	}
+
+	#[test]
+	fn insert_adds_a_layer_between_its_neighbours() {
+		let before = ris(&genome());
+		each_outcome(Structural::Insert, genome(), |genome, applied| {
+			assert!(applied);
+			let after = ris(&genome);
+			assert_eq!(after.len(), before.len() + 1);
+			let depth = (0..after.len())
+				.find(|n| before.get(*n) != Some(&after[*n]))
+				.unwrap();
+			assert_eq!([&after[..depth], &after[depth + 1..]].concat(), before);
+
+			let neighbours = [depth.checked_sub(1), Some(depth)].map(|n| before.get(n?).copied());
+			let (low, high) = match neighbours {
+				[Some(a), Some(b)] => (a.min(b), a.max(b)),
+				[Some(ri), None] | [None, Some(ri)] => (ri, ri),
+				[None, None] => unreachable!(),
+			};
+			assert!((low..=high).contains(&after[depth]));
+		});
+
+		let mut full: ParamSet<5> = "layer:3.00mm part:1.00mm | 1.33 1.38 1.42 1.40 1.36 |"
+			.parse()
+			.unwrap();
+		let unchanged = full;
+		assert!(!Structural::Insert.apply(&mut full, &mut Prng::seed_from_u64(1)));
+		assert_eq!(full, unchanged);
+	}
+
+	#[test]
+	fn delete_removes_one_layer_but_never_the_last() {
+		let before = ris(&genome());
+		each_outcome(Structural::Delete, genome(), |genome, applied| {
+			assert!(applied);
+			let after = ris(&genome);
+			assert_eq!(genome.len(), before.len() - 1);
+			assert!((0..before.len()).any(|n| {
+				let mut removed = before.clone();
+				removed.remove(n);
+				removed == after
+			}));
+		});
+
+		let single: ParamSet<6> = "layer:3.00mm part:1.00mm | 1.40 |".parse().unwrap();
+		each_outcome(Structural::Delete, single, |genome, applied| {
+			assert!(!applied);
+			assert_eq!(genome, single);
+		});
+	}
+
+	#[test]
+	fn swap_exchanges_neighbours() {
+		let before = ris(&genome());
+		each_outcome(Structural::Swap, genome(), |genome, applied| {
+			assert!(applied);
+			let after = ris(&genome);
+			assert!((0..before.len() - 1).any(|n| {
+				let mut swapped = before.clone();
+				swapped.swap(n, n + 1);
+				swapped == after
+			}));
+		});
+
+		let same: ParamSet<6> = "layer:3.00mm part:1.00mm | 1.40 1.40 |".parse().unwrap();
+		each_outcome(Structural::Swap, same, |genome, applied| {
+			assert!(!applied);
+			assert_eq!(genome, same);
+		});
+	}
+
+	#[test]
+	fn shift_moves_a_whole_gradient_one_step() {
+		// gradients are 1.33 to 1.42 and 1.40 to 1.36
+		let before = ris(&genome());
+		let mut shifted = [false; 2];
+		each_outcome(Structural::Shift, genome(), |genome, applied| {
+			assert!(applied);
+			let after = ris(&genome);
+			let steps: Vec<i16> = after
+				.iter()
+				.zip(&before)
+				.map(|(a, b)| i16::from(*a) - i16::from(*b))
+				.collect();
+			let gradient = match steps.as_slice() {
+				[s, t, u, 0, 0] if [t, u] == [s, s] => 0,
+				[0, 0, 0, s, t] if s == t => 1,
+				_ => panic!("{steps:?} isn't one gradient shifted"),
+			};
+			assert_eq!(steps.iter().map(|s| s.abs()).max(), Some(1));
+			shifted[gradient] = true;
+		});
+		assert_eq!(shifted, [true; 2]);
+	}
+
+	/// Each layer's RI, and whether it has a partition above it.
+	fn stack<const N: usize>(genome: &ParamSet<N>) -> Vec<(u8, bool)> {
+		(0..genome.len())
+			.map(|n| (genome.layers[n].unwrap().get(), genome.partitioned(n)))
+			.collect()
+	}
+
+	#[test]
+	fn layers_keep_their_partitions() {
+		let open: ParamSet<6> = "layer:3.00mm part:1.00mm | 1.33 ~ 1.38 1.42 ~ 1.40 1.36 |"
+			.parse()
+			.unwrap();
+		let before = stack(&open);
+		// whatever ends up on top is under the top face
+		let topped = |mut stack: Vec<(u8, bool)>| {
+			stack[0].1 = true;
+			stack
+		};
+
+		each_outcome(Structural::Insert, open, |genome, _| {
+			let after = stack(&genome);
+			assert!((0..=before.len()).any(|n| {
+				let mut inserted = before.clone();
+				inserted.insert(n, (after[n].0, true));
+				inserted == after
+			}));
+		});
+		each_outcome(Structural::Delete, open, |genome, _| {
+			let after = stack(&genome);
+			assert!((0..before.len()).any(|n| {
+				let mut removed = before.clone();
+				removed.remove(n);
+				topped(removed) == after
+			}));
+		});
+		each_outcome(Structural::Swap, open, |genome, _| {
+			let after = stack(&genome);
+			assert!((0..before.len() - 1).any(|n| {
+				let mut swapped = before.clone();
+				swapped.swap(n, n + 1);
+				topped(swapped) == after
+			}));
+		});
+	}
+
+	#[test]
+	fn toggle_flips_one_partition_below_the_top() {
+		let before = genome();
+		each_outcome(Structural::Toggle, before, |genome, applied| {
+			assert!(applied);
+			assert_eq!(genome.layers, before.layers);
+			assert!(genome.partitioned(0));
+			assert_eq!((genome.open ^ before.open).count_ones(), 1);
+		});
+
+		let single: ParamSet<6> = "layer:3.00mm part:1.00mm | 1.40 |".parse().unwrap();
+		each_outcome(Structural::Toggle, single, |genome, applied| {
+			assert!(!applied);
+			assert_eq!(genome, single);
+		});
+	}
+}
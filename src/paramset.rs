@@ -2,7 +2,16 @@ use std::{fmt::Display, num::NonZeroU8};
 
 use genevo::genetic::Genotype;
 
-use crate::model::units::{model_ri_to_real_ri, Microns};
+use crate::{
+	model::{
+		geo::Outline,
+		units::{mm_tenths_to_microns, mm_to_microns, model_ri_to_real_ri, Microns},
+	},
+	sidewall::Sidewall,
+};
+
+/// Reading designs back from how they're displayed.
+pub mod parse;
 
 // Set of usable paritition thicknesses.
 ///
@@ -31,15 +40,54 @@ pub fn normalise_partition_thickness(thicc: u8) -> u8 {
 	}
 }
 
+/// Default number of possible layers in a [`ParamSet`].
+pub const DEFAULT_LAYERS: usize = 10;
+
+/// Layer thicknesses [`ParamSet::nth`] goes through.
+pub const POSSIBLE_LAYERS: u64 = u8::MAX as _;
+/// Partition thicknesses [`ParamSet::nth`] goes through.
+pub const POSSIBLE_PARTS: u64 = PARTITION_THICKNESSES.len() as _;
+
+/// Lowest RI of a layer, in the units of [`ParamSet`].
+pub const MINIMUM_RI: u8 = 34; // 0.99 (None) + 0.34 = 1.33 (water)
+/// Highest RI of a layer, in the units of [`ParamSet`].
+pub const MAXIMUM_RI: u8 = 51; // 0.99 (None) + 0.51 = 1.50 (acrylic)
+
+/// RIs of each layer [`ParamSet::nth`] goes through.
+// +1 for the None possibility
+pub const POSSIBLE_RIS: u64 = (1 + MAXIMUM_RI - MINIMUM_RI) as _;
+
+/// Width of the top face of designs that don't give one, in mm.
+pub const DEFAULT_WIDTH_TOP: u8 = 104;
+/// Width of the bottom face of designs that don't give one, in mm.
+pub const DEFAULT_WIDTH_BOTTOM: u8 = 52;
+
 // Parameter set for an AGILE.
 ///
-/// This is optimised for struct size, instead of ease of use: it is 12 bytes.
+/// This is optimised for struct size, instead of ease of use: with the default
+/// of 10 possible layers, it is 36 bytes.
+///
+/// It (de)serialises as a [`Design`](crate::design::Design), in physical units.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub struct ParamSet {
+pub struct ParamSet<const N: usize = DEFAULT_LAYERS> {
+	// Width of the top (entry) face of the lens in mm
+	/// Width of the top (entry) face of the lens in mm.
+	pub width_top: u8,
+
+	// Width of the bottom (exit) face of the lens in mm
+	/// Width of the bottom (exit) face of the lens in mm.
+	pub width_bottom: u8,
+
+	// Shapes of the left and right sidewalls
+	/// Shapes of the left and right sidewalls.
+	pub sidewalls: [Sidewall; 2],
+
 	// Thickness of each layer in tenths of mm above 3.0mm.
+	/// Thickness of each layer in tenths of mm above 3.0mm.
 	pub layers_thickness: u8,
 
 	// Thickness of each partition in tenths of mm
+	/// Thickness of each partition in tenths of mm.
 	pub partitions_thickness: u8,
 
 	// Refractive indices of the layers in hundredths above 0.99
@@ -49,30 +97,60 @@ pub struct ParamSet {
 	/// - 34 = 1.33 (water)
 	/// - 51 = 1.50 (acrylic)
 	///
-	/// There are 10 possible layers; those that are Some are the ones defined
+	/// There are N possible layers; those that are Some are the ones defined
 	/// for this parameter set.
-	pub layers: [Option<NonZeroU8>; Self::LAYERS],
+	pub layers: [Option<NonZeroU8>; N],
+
+	/// Catalog entry each layer is filled with, from 1, or 0 for layers that
+	/// are only given by their RI.
+	///
+	/// When designs are restricted to a catalog, these are the genes that
+	/// choose liquids, and RIs follow them.
+	pub materials: [u8; N],
+
+	/// Layers that touch the one above without a partition, as bits: bit n is
+	/// set if layer n (from 0 at the top) does.
+	///
+	/// The top layer always has a partition, as the top face, so bit 0 is
+	/// never set. This limits N to 32.
+	pub open: u32,
 }
 
-impl Genotype for ParamSet {
+impl<const N: usize> Genotype for ParamSet<N> {
 	type Dna = u8;
 }
 
-impl Default for ParamSet {
+impl<const N: usize> Default for ParamSet<N> {
 	fn default() -> Self {
 		Self::nth(0)
 	}
 }
 
-impl Display for ParamSet {
+impl<const N: usize> Display for ParamSet<N> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		let layers_mm = f32::from(self.layers_thickness) * 0.1 + 3.0;
 		let parts_mm = f32::from(self.partitions_thickness) * 0.1;
+		write!(
+			f,
+			"top:{}mm bottom:{}mm ",
+			self.width_top, self.width_bottom
+		)?;
+		match self.sidewalls {
+			[Sidewall::Straight, Sidewall::Straight] => {}
+			[left, right] if left == right => write!(f, "wall:{left} ")?,
+			[left, right] => write!(f, "left:{left} right:{right} ")?,
+		}
 		write!(f, "layer:{layers_mm:.02}mm part:{parts_mm:.02}mm  | ")?;
-		for ri in &self.layers {
+		for (n, ri) in self.layers.iter().enumerate() {
 			if let Some(ri) = ri {
-				let ri = model_ri_to_real_ri(ri.get());
-				write!(f, "{ri:.02} ")?;
+				if !self.partitioned(n) {
+					write!(f, "~ ")?;
+				}
+				write!(f, "{:.02}", model_ri_to_real_ri(ri.get()))?;
+				match self.materials[n] {
+					0 => write!(f, " ")?,
+					entry => write!(f, "#{entry} ")?,
+				}
 			} else {
 				break;
 			}
@@ -81,24 +159,18 @@ impl Display for ParamSet {
 	}
 }
 
-impl ParamSet {
-	pub const POSSIBLE_LAYERS: u64 = u8::MAX as _;
-	pub const POSSIBLE_PARTS: u64 = PARTITION_THICKNESSES.len() as _;
-
-	pub const LAYERS: usize = 10;
-	pub const MINIMUM_RI: u8 = 34; // 0.99 (None) + 0.34 = 1.33 (water)
-	pub const MAXIMUM_RI: u8 = 51; // 0.99 (None) + 0.51 = 1.50 (acrylic)
-
-	// +1 for the None possibility
-	pub const POSSIBLE_RIS: u64 = (1 + Self::MAXIMUM_RI - Self::MINIMUM_RI) as _;
-
+impl<const N: usize> ParamSet<N> {
+	/// Index of the last parameter set [`nth`](Self::nth) generates.
+	///
+	/// `None` past 12 layers, where the sequence no longer fits in a `u64`.
 	// -1 because because it overflows the possibility space!
-	pub const MAX_POSSIBILITIES: u64 =
-		Self::POSSIBLE_LAYERS * Self::POSSIBLE_PARTS * Self::POSSIBLE_RIS.pow(Self::LAYERS as _)
-			- 1;
-
-	pub const WIDTH_TOP: Microns = 104_000;
-	pub const WIDTH_BOTTOM: Microns = 52_000;
+	pub const MAX_POSSIBILITIES: Option<u64> = match POSSIBLE_RIS.checked_pow(N as _) {
+		Some(ris) => match (POSSIBLE_LAYERS * POSSIBLE_PARTS).checked_mul(ris) {
+			Some(possibilities) => Some(possibilities - 1),
+			None => None,
+		},
+		None => None,
+	};
 
 	/// Generate the Nth parameter set.
 	///
@@ -106,30 +178,36 @@ impl ParamSet {
 	/// - thickness of layers (in 0.1mm increments, 256 steps)
 	/// - thickness of partitions (out of [`PARTITION_THICKNESSES`], 9 steps)
 	/// - RI of each layer (in 0.01 increments, starting at None, 18 steps each)
+	///
+	/// The outline widths and sidewall shape are left at their defaults.
 	pub fn nth(mut n: u64) -> Self {
-		let layer_n = u8::try_from(n % Self::POSSIBLE_LAYERS).unwrap();
-		n /= Self::POSSIBLE_LAYERS;
-		let part_n = usize::try_from(n % Self::POSSIBLE_PARTS).unwrap();
-		n /= Self::POSSIBLE_PARTS;
+		let layer_n = u8::try_from(n % POSSIBLE_LAYERS).unwrap();
+		n /= POSSIBLE_LAYERS;
+		let part_n = usize::try_from(n % POSSIBLE_PARTS).unwrap();
+		n /= POSSIBLE_PARTS;
 
-		let mut ris = [None::<NonZeroU8>; Self::LAYERS];
+		let mut ris = [None::<NonZeroU8>; N];
 		for ri in &mut ris {
 			*ri = NonZeroU8::new(
-				ri.map_or(Self::MINIMUM_RI, |u| u.get())
-					+ u8::try_from(n % Self::POSSIBLE_RIS).unwrap(),
+				ri.map_or(MINIMUM_RI, |u| u.get()) + u8::try_from(n % POSSIBLE_RIS).unwrap(),
 			);
 
 			if n == 0 {
 				break;
 			}
 
-			n /= Self::POSSIBLE_RIS;
+			n /= POSSIBLE_RIS;
 		}
 
 		Self {
+			width_top: DEFAULT_WIDTH_TOP,
+			width_bottom: DEFAULT_WIDTH_BOTTOM,
+			sidewalls: [Sidewall::Straight; 2],
 			layers_thickness: layer_n,
 			partitions_thickness: PARTITION_THICKNESSES[part_n],
 			layers: ris,
+			materials: [0; N],
+			open: 0,
 		}
 	}
 
@@ -138,8 +216,89 @@ impl ParamSet {
 		self.layers
 			.iter()
 			.enumerate()
-			.filter(|(_, layer)| layer.is_none())
-			.next()
-			.map_or(0, |(n, _)| n)
+			.find(|(_, layer)| layer.is_none())
+			.map_or(N, |(n, _)| n)
+	}
+
+	/// Whether there are no layers at all.
+	pub fn is_empty(self) -> bool {
+		self.layers.first().is_none_or(Option::is_none)
+	}
+
+	/// Stops designs of more layers than [`open`](Self::open) has bits for
+	/// from compiling.
+	const OPEN_FITS: () = assert!(N <= 32, "`open` only has bits for 32 layers");
+
+	/// Whether a layer has a partition above it.
+	pub fn partitioned(self, layer: usize) -> bool {
+		let () = Self::OPEN_FITS;
+		layer == 0 || self.open & (1 << layer) == 0
+	}
+
+	/// Puts a partition above a layer, or takes it away.
+	///
+	/// The top layer always keeps its partition.
+	pub fn set_partitioned(&mut self, layer: usize, partitioned: bool) {
+		let () = Self::OPEN_FITS;
+		if partitioned {
+			self.open &= !(1 << layer);
+		} else if layer > 0 {
+			self.open |= 1 << layer;
+		}
+	}
+
+	/// Number of partitions in the stack.
+	pub fn partitions(self) -> usize {
+		(0..self.len()).filter(|n| self.partitioned(*n)).count()
+	}
+
+	/// Total height of the lens stack, in micrometres.
+	pub fn height(self) -> Microns {
+		let part_um = mm_tenths_to_microns(self.partitions_thickness);
+		let layer_um = mm_tenths_to_microns(self.layers_thickness).saturating_add(3_000);
+		(self.partitions() as Microns) * part_um + (self.len() as Microns) * layer_um
+	}
+
+	/// Geometric concentration ratio (top width over bottom width), in 1000ths.
+	pub fn concentration(self) -> u32 {
+		(u32::from(self.width_top) * 1000) / u32::from(self.width_bottom.max(1))
+	}
+
+	/// Outline of the lens, in micrometres.
+	pub fn outline(self) -> Outline {
+		Outline::new(
+			mm_to_microns(self.width_top),
+			mm_to_microns(self.width_bottom),
+			self.height(),
+		)
+		.with_profiles(self.sidewalls[0].into(), self.sidewalls[1].into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::array::ParamArray;
+
+	#[test]
+	fn enumerates_up_to_twelve_layers() {
+		assert!(ParamSet::<12>::MAX_POSSIBILITIES.is_some());
+		assert_eq!(ParamSet::<13>::MAX_POSSIBILITIES, None);
+		assert_eq!(ParamSet::<30>::MAX_POSSIBILITIES, None);
+
+		// the last set has every layer at the highest RI
+		let last = ParamSet::<12>::nth(ParamSet::<12>::MAX_POSSIBILITIES.unwrap());
+		assert_eq!(last.len(), 12);
+		assert!(last.layers.iter().all(|ri| ri.unwrap().get() == MAXIMUM_RI));
+	}
+
+	#[test]
+	fn thirty_layers_round_trip_through_genes() {
+		let mut params = ParamSet::<30>::default();
+		for (n, layer) in params.layers.iter_mut().enumerate() {
+			*layer = NonZeroU8::new(MINIMUM_RI + (n as u8 % 18));
+		}
+		assert_eq!(params.len(), 30);
+		assert_eq!(ParamSet::from(ParamArray::from(params)), params);
 	}
 }
@@ -0,0 +1,329 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{
+	design::{Design, DesignError},
+	model::{
+		profile::{Profile, KNOTS},
+		units::{model_ri_to_real_ri, real_ri_to_model_ri},
+	},
+	paramset::{ParamSet, DEFAULT_WIDTH_BOTTOM, DEFAULT_WIDTH_TOP, MAXIMUM_RI, MINIMUM_RI},
+};
+
+/// Errors from parsing a [`ParamSet`] from its [`Display`](std::fmt::Display) form.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum ParseError {
+	/// There's no list of layers.
+	#[error("expected layer RIs between `|` bars, like `| 1.33 1.40 |`")]
+	MissingBars,
+
+	/// Something before the layers isn't a field.
+	#[error("expected `key:value`, got `{0}`")]
+	NotAField(String),
+
+	/// A field isn't one of those of a design.
+	#[error("unknown field `{0}`, expected one of top, bottom, wall, left, right, layer, part")]
+	UnknownField(String),
+
+	/// A field, or a wall, is given twice.
+	#[error("field `{0}` is given more than once")]
+	DuplicateField(&'static str),
+
+	/// A field that has no default is missing.
+	#[error("missing field `{0}`")]
+	MissingField(&'static str),
+
+	/// A length isn't a number of mm.
+	#[error("field `{field}` should be a length in mm, like `3.50mm`, got `{value}`")]
+	InvalidLength {
+		/// Field of the length.
+		field: &'static str,
+		/// Value given.
+		value: String,
+	},
+
+	/// A wall isn't one of the shapes.
+	#[error("invalid wall `{0}`, expected straight, parabolic(c) or piecewise(a,b,c)")]
+	InvalidWall(String),
+
+	/// A layer isn't a number.
+	#[error("invalid refractive index `{0}`")]
+	InvalidRi(String),
+
+	/// A layer's catalog entry isn't a number from 1.
+	#[error("invalid catalog entry `{0}`, expected a number from 1 like `#3`")]
+	InvalidMaterial(String),
+
+	/// A layer's RI is outside of those the model handles, once rounded.
+	#[error("refractive index {ri} is outside of {min:.02} to {max:.02}")]
+	RiOutOfRange {
+		/// RI given.
+		ri: f64,
+		/// Lowest RI possible.
+		min: f64,
+		/// Highest RI possible.
+		max: f64,
+	},
+
+	/// The design can't be represented.
+	#[error(transparent)]
+	Design(#[from] DesignError),
+}
+
+fn parse_length(field: &'static str, value: &str) -> Result<f64, ParseError> {
+	value
+		.strip_suffix("mm")
+		.and_then(|mm| mm.parse().ok())
+		.ok_or_else(|| ParseError::InvalidLength {
+			field,
+			value: value.into(),
+		})
+}
+
+fn parse_wall(value: &str) -> Result<Profile, ParseError> {
+	let invalid = || ParseError::InvalidWall(value.into());
+	if value == "straight" {
+		return Ok(Profile::Straight);
+	}
+
+	let (kind, args) = value
+		.strip_suffix(')')
+		.and_then(|v| v.split_once('('))
+		.ok_or_else(invalid)?;
+	let args = args
+		.split(',')
+		.map(|arg| arg.trim().parse::<f64>())
+		.collect::<Result<Vec<_>, _>>()
+		.map_err(|_| invalid())?;
+
+	match (kind, args.as_slice()) {
+		("parabolic", [curvature]) => Ok(Profile::Parabolic {
+			curvature: *curvature,
+		}),
+		("piecewise", knots) if knots.len() == KNOTS => {
+			let mut array = [0.0; KNOTS];
+			array.copy_from_slice(knots);
+			Ok(Profile::Piecewise { knots: array })
+		}
+		_ => Err(invalid()),
+	}
+}
+
+fn set<T>(slot: &mut Option<T>, field: &'static str, value: T) -> Result<(), ParseError> {
+	if slot.replace(value).is_some() {
+		Err(ParseError::DuplicateField(field))
+	} else {
+		Ok(())
+	}
+}
+
+/// Parses the format produced by `Display`, e.g.
+/// `top:104mm bottom:52mm layer:3.50mm part:1.00mm  | 1.33 1.40 ~ 1.45 |`,
+/// where `~` marks a layer with no partition above it, and `#n` after an RI
+/// the catalog entry the layer is filled with, as in `1.47#2`.
+///
+/// `wall` sets both sidewalls, `left` and `right` set one each. The top and
+/// bottom widths and the walls are optional and default to a 104mm/52mm
+/// straight-walled lens. Thicknesses are rounded to tenths of mm but otherwise
+/// kept as given, to be checked against the materials of an experiment.
+impl<const N: usize> FromStr for ParamSet<N> {
+	type Err = ParseError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (fields, rest) = s.split_once('|').ok_or(ParseError::MissingBars)?;
+		let ris = rest
+			.trim_end()
+			.strip_suffix('|')
+			.ok_or(ParseError::MissingBars)?;
+
+		let (mut top, mut bottom, mut layer, mut part) = (None, None, None, None);
+		let (mut left, mut right) = (None, None);
+		for field in fields.split_whitespace() {
+			let (key, value) = field
+				.split_once(':')
+				.ok_or_else(|| ParseError::NotAField(field.into()))?;
+			match key {
+				"top" => set(&mut top, "top", parse_length("top", value)?)?,
+				"bottom" => set(&mut bottom, "bottom", parse_length("bottom", value)?)?,
+				"wall" => {
+					let wall = parse_wall(value)?;
+					set(&mut left, "left", wall)?;
+					set(&mut right, "right", wall)?;
+				}
+				"left" => set(&mut left, "left", parse_wall(value)?)?,
+				"right" => set(&mut right, "right", parse_wall(value)?)?,
+				"layer" => set(&mut layer, "layer", parse_length("layer", value)?)?,
+				"part" => set(&mut part, "part", parse_length("part", value)?)?,
+				_ => return Err(ParseError::UnknownField(key.into())),
+			}
+		}
+
+		let (min, max) = (
+			model_ri_to_real_ri(MINIMUM_RI),
+			model_ri_to_real_ri(MAXIMUM_RI),
+		);
+		let (mut layers, mut materials, mut open) = (Vec::new(), Vec::new(), Vec::new());
+		let mut partitioned = true;
+		for ri in ris.split_whitespace() {
+			// `~` before a layer means it has no partition above
+			if ri == "~" {
+				partitioned = false;
+				continue;
+			}
+			// `#n` after the RI is the catalog entry the layer is filled with
+			let (ri, entry) = match ri.split_once('#') {
+				Some((ri, entry)) => (
+					ri,
+					entry
+						.parse()
+						.ok()
+						.filter(|entry| *entry > 0)
+						.ok_or_else(|| ParseError::InvalidMaterial(entry.into()))?,
+				),
+				None => (ri, 0),
+			};
+			let value: f64 = ri.parse().map_err(|_| ParseError::InvalidRi(ri.into()))?;
+			// check the RI as it will be stored, after rounding
+			let stored = real_ri_to_model_ri(value);
+			if !stored.is_some_and(|ri| (MINIMUM_RI..=MAXIMUM_RI).contains(&ri)) {
+				return Err(ParseError::RiOutOfRange {
+					ri: value,
+					min,
+					max,
+				});
+			}
+			if !partitioned {
+				open.push(layers.len());
+				partitioned = true;
+			}
+			layers.push(value);
+			materials.push(entry);
+		}
+		if materials.iter().all(|entry| *entry == 0) {
+			materials.clear();
+		}
+
+		Ok(Design {
+			width_top: top.unwrap_or(f64::from(DEFAULT_WIDTH_TOP)),
+			width_bottom: bottom.unwrap_or(f64::from(DEFAULT_WIDTH_BOTTOM)),
+			sidewall: left.unwrap_or_default(),
+			right_sidewall: right,
+			layer_thickness: layer.ok_or(ParseError::MissingField("layer"))?,
+			partition_thickness: part.ok_or(ParseError::MissingField("part"))?,
+			layers,
+			materials,
+			open,
+		}
+		.try_into()?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::num::NonZeroU8;
+
+	use super::*;
+	use crate::sidewall::Sidewall;
+
+	fn round_trip(params: ParamSet) {
+		let text = params.to_string();
+		assert_eq!(text.parse::<ParamSet>(), Ok(params), "{text}");
+	}
+
+	#[test]
+	fn display_round_trips() {
+		for n in [0, 1, 17, 12345, 987654] {
+			round_trip(ParamSet::nth(n));
+		}
+	}
+
+	#[test]
+	fn display_round_trips_walls_and_open_layers() {
+		let mut params = ParamSet::nth(4321);
+		params.width_top = 120;
+		params.width_bottom = 40;
+		params.layers[..3].copy_from_slice(&[34, 40, 51].map(NonZeroU8::new));
+		params.materials[..3].copy_from_slice(&[2, 0, 11]);
+		params.set_partitioned(2, false);
+		for sidewalls in [
+			[Sidewall::Parabolic(200); 2],
+			[Sidewall::Parabolic(30), Sidewall::Straight],
+			[Sidewall::Straight, Sidewall::Piecewise([10, 128, 250])],
+		] {
+			params.sidewalls = sidewalls;
+			round_trip(params);
+		}
+	}
+
+	#[test]
+	fn keeps_partitions_outside_the_usual_thicknesses() {
+		let params: ParamSet = "layer:3.50mm part:2.50mm | 1.33 1.40 |".parse().unwrap();
+		assert_eq!(params.partitions_thickness, 25);
+		round_trip(params);
+	}
+
+	#[test]
+	fn rejects_ris_that_round_out_of_range() {
+		for ri in ["1.3249", "1.5051"] {
+			let text = format!("layer:3.50mm part:1.00mm | {ri} |");
+			assert!(matches!(
+				text.parse::<ParamSet>(),
+				Err(ParseError::RiOutOfRange { .. })
+			));
+		}
+	}
+
+	#[test]
+	fn reads_catalog_entries() {
+		let params: ParamSet = "layer:3.50mm part:1.00mm | 1.38#3 1.40 |".parse().unwrap();
+		assert_eq!(params.materials[..3], [3, 0, 0]);
+		assert!(matches!(
+			"layer:3.50mm part:1.00mm | 1.38#0 |".parse::<ParamSet>(),
+			Err(ParseError::InvalidMaterial(_))
+		));
+	}
+
+	#[test]
+	fn rejects_a_wall_given_twice() {
+		let text = "wall:straight left:straight layer:3.50mm part:1.00mm | 1.33 |";
+		assert_eq!(
+			text.parse::<ParamSet>(),
+			Err(ParseError::DuplicateField("left"))
+		);
+	}
+
+	#[test]
+	fn rejects_malformed_designs() {
+		let cases = [
+			("layer:3.50mm part:1.00mm 1.33", ParseError::MissingBars),
+			(
+				"layer part:1.00mm | 1.33 |",
+				ParseError::NotAField("layer".into()),
+			),
+			(
+				"side:10 layer:3.50mm part:1.00mm | 1.33 |",
+				ParseError::UnknownField("side".into()),
+			),
+			("part:1.00mm | 1.33 |", ParseError::MissingField("layer")),
+			(
+				"layer:3.5 part:1.00mm | 1.33 |",
+				ParseError::InvalidLength {
+					field: "layer",
+					value: "3.5".into(),
+				},
+			),
+			(
+				"wall:round layer:3.50mm part:1.00mm | 1.33 |",
+				ParseError::InvalidWall("round".into()),
+			),
+			(
+				"layer:3.50mm part:1.00mm | 1.33 high |",
+				ParseError::InvalidRi("high".into()),
+			),
+		];
+		for (text, error) in cases {
+			assert_eq!(text.parse::<ParamSet>(), Err(error), "{text}");
+		}
+	}
+}
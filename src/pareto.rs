@@ -0,0 +1,474 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use genevo::{
+	operator::{CrossoverOp, MutationOp},
+	prelude::GenomeBuilder,
+	random::Rng,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	bounds::OutlineBounds,
+	catalog::Catalog,
+	constraints::Constraints,
+	model::{raytrace, Performance, PhysicalPerformance, RaySource},
+	paramset::ParamSet,
+};
+
+/// Number of objectives optimised for.
+const OBJECTIVES: usize = 4;
+
+/// Objective values of a design, all to be minimised.
+type Objectives = [u64; OBJECTIVES];
+
+/// A design on the front, with its performance.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Solution<const N: usize> {
+	/// Design, as it was traced.
+	pub design: ParamSet<N>,
+	/// How it performs.
+	pub performance: Performance,
+}
+
+impl<const N: usize> Solution<N> {
+	/// Optimises for transmission, exit angle, the light travel of each ray
+	/// that exits and geometric concentration, separately.
+	///
+	/// Infeasible designs, and those that no ray goes through, are worse than
+	/// any feasible one in everything.
+	fn objectives(self, feasible: bool) -> Objectives {
+		let perf = self.performance;
+		if !feasible || perf.exit_ratio == 0 {
+			return [u64::MAX; OBJECTIVES];
+		}
+
+		// travel per ray out of the bottom, up to a factor: every design is
+		// traced with the same rays
+		let mean_travel =
+			u64::from(perf.light_travel) * u64::from(u32::MAX) / u64::from(perf.exit_ratio);
+		[
+			(u32::MAX - perf.exit_ratio).into(),
+			perf.exit_angle.into(),
+			mean_travel,
+			(u32::MAX - perf.concentration).into(),
+		]
+	}
+}
+
+/// Non-dominated designs, best optical concentration first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Front<const N: usize> {
+	/// Designs on the front.
+	pub designs: Vec<Solution<N>>,
+}
+
+impl<const N: usize> Front<N> {
+	/// Shows the front as a table, naming the materials of designs if there's
+	/// a catalog.
+	pub fn table<'a>(&'a self, catalog: Option<&'a Catalog>) -> Table<'a, N> {
+		Table {
+			front: self,
+			catalog,
+		}
+	}
+}
+
+impl<const N: usize> Display for Front<N> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		self.table(None).fmt(f)
+	}
+}
+
+/// See [`Front::table`].
+pub struct Table<'a, const N: usize> {
+	front: &'a Front<N>,
+	catalog: Option<&'a Catalog>,
+}
+
+impl<const N: usize> Display for Table<'_, N> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"{:>4}  {:>6}  {:>6}  {:>7}  {:>9}  {:>6}  design",
+			"#", "exit", "optic", "angle", "travel", "conc"
+		)?;
+		for (n, solution) in self.front.designs.iter().enumerate() {
+			let perf = PhysicalPerformance::from(solution.performance);
+			let design = match self.catalog {
+				Some(catalog) => catalog.label(solution.design).to_string(),
+				None => solution.design.to_string(),
+			};
+			writeln!(
+				f,
+				"{:>4}  {:>5.1}%  {:>5.2}x  {:>6.2}°  {:>7.1}mm  {:>5.2}x  {}",
+				n + 1,
+				perf.exit_ratio * 100.0,
+				perf.exit_ratio * perf.concentration,
+				perf.exit_angle,
+				perf.light_travel,
+				perf.concentration,
+				design
+			)?;
+		}
+		Ok(())
+	}
+}
+
+/// Whether `a` is at least as good as `b` in everything, and better in something.
+fn dominates(a: &Objectives, b: &Objectives) -> bool {
+	a.iter().zip(b).all(|(a, b)| a <= b) && a != b
+}
+
+/// Sorts into fronts of non-dominated members, best front first.
+fn non_dominated_sort(objectives: &[Objectives]) -> Vec<Vec<usize>> {
+	let len = objectives.len();
+	let mut dominated_by = vec![0_usize; len];
+	let mut dominating = vec![Vec::new(); len];
+	for a in 0..len {
+		for b in (a + 1)..len {
+			if dominates(&objectives[a], &objectives[b]) {
+				dominating[a].push(b);
+				dominated_by[b] += 1;
+			} else if dominates(&objectives[b], &objectives[a]) {
+				dominating[b].push(a);
+				dominated_by[a] += 1;
+			}
+		}
+	}
+
+	let mut fronts = Vec::new();
+	let mut front: Vec<usize> = (0..len).filter(|n| dominated_by[*n] == 0).collect();
+	while !front.is_empty() {
+		let mut next = Vec::new();
+		for a in &front {
+			for b in &dominating[*a] {
+				dominated_by[*b] -= 1;
+				if dominated_by[*b] == 0 {
+					next.push(*b);
+				}
+			}
+		}
+		fronts.push(front);
+		front = next;
+	}
+	fronts
+}
+
+/// Crowding distance of each member of a front, in the same order.
+///
+/// Members at the extremes of any objective are infinitely far from others.
+fn crowding_distances(objectives: &[Objectives], front: &[usize]) -> Vec<f64> {
+	let mut distances = vec![0.0; front.len()];
+	let mut order: Vec<usize> = (0..front.len()).collect();
+	let columns = (0..OBJECTIVES).map(|objective| {
+		front
+			.iter()
+			.map(|member| objectives[*member][objective] as f64)
+			.collect::<Vec<_>>()
+	});
+	for column in columns {
+		let value = |n: usize| column[n];
+		order.sort_by(|a, b| value(*a).total_cmp(&value(*b)));
+
+		let (first, last) = (order[0], order[order.len() - 1]);
+		distances[first] = f64::INFINITY;
+		distances[last] = f64::INFINITY;
+
+		let range = value(last) - value(first);
+		if range <= 0.0 {
+			continue;
+		}
+		for n in 1..order.len().saturating_sub(1) {
+			distances[order[n]] += (value(order[n + 1]) - value(order[n - 1])) / range;
+		}
+	}
+	distances
+}
+
+/// Rank (front number) and crowding distance for each member.
+fn rank<const N: usize>(members: &[Member<N>]) -> (Vec<Vec<usize>>, Vec<(usize, f64)>) {
+	let objectives: Vec<Objectives> = members.iter().map(|m| m.objectives).collect();
+	let fronts = non_dominated_sort(&objectives);
+	let mut ranks = vec![(0, 0.0); members.len()];
+	for (rank, front) in fronts.iter().enumerate() {
+		for (member, distance) in front.iter().zip(crowding_distances(&objectives, front)) {
+			ranks[*member] = (rank, distance);
+		}
+	}
+	(fronts, ranks)
+}
+
+/// Lower rank wins, then larger crowding distance.
+fn compare_rank(a: (usize, f64), b: (usize, f64)) -> Ordering {
+	a.0.cmp(&b.0).then(b.1.total_cmp(&a.1))
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Member<const N: usize> {
+	solution: Solution<N>,
+	objectives: Objectives,
+}
+
+/// Multi-objective optimiser, using NSGA-II.
+///
+/// Rather than one fitness value, this keeps transmission, exit angle, light
+/// travel and concentration separate and finds designs that can't be improved
+/// in one without getting worse in another.
+///
+/// Constraints are always treated as hard, whatever their enforcement.
+pub struct Nsga2<B, C, M> {
+	/// Designs in each generation.
+	pub population_size: usize,
+	/// Generations to run for.
+	pub generations: u64,
+	/// Builds the first generation.
+	pub builder: B,
+	/// How parents are bred.
+	pub crossover: C,
+	/// How children are mutated.
+	pub mutation: M,
+	/// Designs outside of these are infeasible.
+	pub outline: OutlineBounds,
+	/// Designs that violate these are infeasible.
+	pub constraints: Constraints,
+	/// Rays traced to measure performance.
+	pub rays: RaySource,
+}
+
+impl<B, C, M> Nsga2<B, C, M> {
+	fn evaluate<const N: usize>(&self, designs: Vec<ParamSet<N>>) -> Vec<Member<N>> {
+		let (outline, constraints, rays) = (&self.outline, &self.constraints, self.rays);
+		designs
+			.into_par_iter()
+			.map(|design| {
+				let solution = Solution {
+					design,
+					performance: raytrace(design, rays),
+				};
+				let feasible = outline.contains(design) && constraints.check(design).is_empty();
+				Member {
+					solution,
+					objectives: solution.objectives(feasible),
+				}
+			})
+			.collect()
+	}
+
+	/// Runs the optimiser, reporting the current front after each generation.
+	pub fn run<const N: usize, R>(
+		&self,
+		rng: &mut R,
+		mut report: impl FnMut(u64, &Front<N>),
+	) -> Front<N>
+	where
+		B: GenomeBuilder<ParamSet<N>>,
+		C: CrossoverOp<ParamSet<N>>,
+		M: MutationOp<ParamSet<N>>,
+		R: Rng + Sized,
+	{
+		let initial = (0..self.population_size)
+			.map(|n| self.builder.build_genome(n, rng))
+			.collect();
+		let mut population = self.evaluate(initial);
+
+		for generation in 0..self.generations {
+			let (_, ranks) = rank(&population);
+			let tournament = |rng: &mut R| {
+				let a = rng.gen_range(0..population.len());
+				let b = rng.gen_range(0..population.len());
+				let winner = match compare_rank(ranks[a], ranks[b]) {
+					Ordering::Greater => b,
+					_ => a,
+				};
+				population[winner].solution.design
+			};
+
+			let mut offspring = Vec::with_capacity(self.population_size);
+			while offspring.len() < self.population_size {
+				let parents = vec![tournament(rng), tournament(rng)];
+				for child in self.crossover.crossover(parents, rng) {
+					offspring.push(self.mutation.mutate(child, rng));
+				}
+			}
+			offspring.truncate(self.population_size);
+
+			population.extend(self.evaluate(offspring));
+			population = self.survivors(population);
+			report(generation, &self.front(&population));
+		}
+
+		self.front(&population)
+	}
+
+	/// Picks the next population from the best fronts, preferring less crowded
+	/// members from the front that doesn't fit completely.
+	fn survivors<const N: usize>(&self, combined: Vec<Member<N>>) -> Vec<Member<N>> {
+		let (fronts, ranks) = rank(&combined);
+		let mut next = Vec::with_capacity(self.population_size);
+		for mut front in fronts {
+			if next.len() + front.len() > self.population_size {
+				front.sort_by(|a, b| compare_rank(ranks[*a], ranks[*b]));
+				front.truncate(self.population_size - next.len());
+			}
+			next.extend(front.into_iter().map(|n| combined[n]));
+			if next.len() >= self.population_size {
+				break;
+			}
+		}
+		next
+	}
+
+	/// Distinct, feasible designs in the first front.
+	fn front<const N: usize>(&self, population: &[Member<N>]) -> Front<N> {
+		let (fronts, _) = rank(population);
+		let mut solutions: Vec<Solution<N>> = Vec::new();
+		for n in fronts.into_iter().next().unwrap_or_default() {
+			let member = population[n];
+			if member.objectives != [u64::MAX; OBJECTIVES]
+				&& !solutions.iter().any(|s| s.design == member.solution.design)
+			{
+				solutions.push(member.solution);
+			}
+		}
+		solutions.sort_by_key(|s| std::cmp::Reverse(s.performance.optical_concentration()));
+		Front { designs: solutions }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn member(objectives: Objectives) -> Member<10> {
+		let design = ParamSet::default();
+		Member {
+			solution: Solution {
+				design,
+				performance: raytrace(design, RaySource::default()),
+			},
+			objectives,
+		}
+	}
+
+	#[test]
+	fn dominates_only_when_no_worse_anywhere() {
+		assert!(dominates(&[1, 2, 3, 4], &[1, 2, 3, 5]));
+		assert!(!dominates(&[1, 2, 3, 4], &[1, 2, 3, 4]));
+		assert!(!dominates(&[1, 2, 3, 4], &[0, 3, 3, 4]));
+		assert!(!dominates(&[1, 2, 3, 5], &[1, 2, 3, 4]));
+	}
+
+	#[test]
+	fn sorts_into_fronts() {
+		let objectives = [
+			[2, 2, 0, 0],
+			[1, 3, 0, 0],
+			[3, 3, 0, 0],
+			[3, 1, 0, 0],
+			[4, 4, 0, 0],
+		];
+		assert_eq!(
+			non_dominated_sort(&objectives),
+			[vec![0, 1, 3], vec![2], vec![4]]
+		);
+	}
+
+	#[test]
+	fn crowding_favours_the_extremes() {
+		// a three point front along the first two objectives
+		let objectives = [[0, 4, 0, 0], [1, 1, 0, 0], [4, 0, 0, 0]];
+		let distances = crowding_distances(&objectives, &[0, 1, 2]);
+		assert_eq!(distances[0], f64::INFINITY);
+		assert_eq!(distances[2], f64::INFINITY);
+		// (4 - 0) / 4 in the first, (4 - 0) / 4 in the second
+		assert_eq!(distances[1], 2.0);
+	}
+
+	#[test]
+	fn survivors_fill_from_the_best_fronts() {
+		let nsga2 = Nsga2 {
+			population_size: 3,
+			generations: 0,
+			builder: (),
+			crossover: (),
+			mutation: (),
+			outline: OutlineBounds::default(),
+			constraints: Constraints::default(),
+			rays: RaySource::default(),
+		};
+		let combined = [
+			[5, 5, 0, 0],
+			[0, 4, 0, 0],
+			[1, 1, 0, 0],
+			[2, 2, 0, 0],
+			[4, 0, 0, 0],
+			[3, 3, 0, 0],
+		]
+		.map(member)
+		.to_vec();
+		let survivors: Vec<Objectives> = nsga2
+			.survivors(combined)
+			.into_iter()
+			.map(|m| m.objectives)
+			.collect();
+		// the whole first front fits
+		assert_eq!(survivors, [[0, 4, 0, 0], [1, 1, 0, 0], [4, 0, 0, 0]]);
+
+		// when a front doesn't fit, the least crowded of it go through
+		let combined = [[0, 4, 0, 0], [1, 3, 0, 0], [2, 2, 0, 0], [4, 0, 0, 0]]
+			.map(member)
+			.to_vec();
+		let nsga2 = Nsga2 {
+			population_size: 2,
+			..nsga2
+		};
+		let survivors: Vec<Objectives> = nsga2
+			.survivors(combined)
+			.into_iter()
+			.map(|m| m.objectives)
+			.collect();
+		assert_eq!(survivors, [[0, 4, 0, 0], [4, 0, 0, 0]]);
+	}
+
+	#[test]
+	fn transmission_is_its_own_objective() {
+		let solution = member([0; OBJECTIVES]).solution;
+		let mut dimmer = solution;
+		dimmer.performance.exit_ratio /= 2;
+		let [bright, ..] = solution.objectives(true);
+		let [dim, ..] = dimmer.objectives(true);
+		assert!(bright < dim);
+		assert_eq!(solution.objectives(false), [u64::MAX; OBJECTIVES]);
+
+		let mut dark = solution;
+		dark.performance.exit_ratio = 0;
+		assert_eq!(dark.objectives(true), [u64::MAX; OBJECTIVES]);
+	}
+
+	#[test]
+	fn names_materials_in_the_table() {
+		let mut front = Front {
+			designs: vec![member([0; OBJECTIVES]).solution],
+		};
+		front.designs[0].design.materials[0] = 1;
+		let catalog = Catalog {
+			materials: vec![crate::catalog::Material {
+				name: "water".into(),
+				ri: 1.33,
+				density: 1.0,
+				cost: 0.0,
+				absorption: 0.0,
+				miscibility: None,
+				diffusivity: None,
+				thermo_optic: None,
+				expansion: None,
+			}],
+		};
+		let plain = front.to_string();
+		let named = front.table(Some(&catalog)).to_string();
+		assert!(plain.trim_end().ends_with("#1 |"), "{plain}");
+		assert!(named.trim_end().ends_with("| water |"), "{named}");
+		assert_eq!(plain.lines().next(), named.lines().next());
+	}
+}
@@ -0,0 +1,170 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+	experiment::Experiment, fitness::cache::CacheStats, model::Performance,
+	mutation::repair::RepairStats, paramset::ParamSet, pareto::Front,
+};
+
+/// Statistics for one generation of a run.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GenerationStats {
+	/// Number of the generation.
+	pub generation: u64,
+	/// Mean fitness of its population.
+	pub average_fitness: u64,
+	/// Fitness of the best design found so far.
+	pub best_fitness: u64,
+}
+
+/// A design along with how it fared.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Evaluated<const N: usize> {
+	/// Design, within the liquids and partitions available.
+	pub design: ParamSet<N>,
+	/// Its fitness.
+	pub fitness: u64,
+	/// How it performs.
+	pub performance: Performance,
+
+	/// Generation in which this design was found.
+	pub generation: u64,
+}
+
+/// Everything about a run, for consumption by other tools.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord<const N: usize> {
+	/// Settings the run was made with.
+	pub config: Experiment,
+
+	/// Seed of all randomness in the run.
+	pub seed: u64,
+	/// Fittest design found, if any generation has run.
+	pub best: Option<Evaluated<N>>,
+	/// Each generation that's run.
+	pub generations: Vec<GenerationStats>,
+	/// How often genomes needed repairs.
+	pub repairs: RepairStats,
+	/// How well the fitness cache did.
+	pub cache: CacheStats,
+}
+
+/// Trade-offs found by a multi-objective run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrontRecord<const N: usize> {
+	/// Settings the run was made with.
+	pub config: Experiment,
+
+	/// Seed of all randomness in the run.
+	pub seed: u64,
+	/// Designs on the last front.
+	pub front: Front<N>,
+}
+
+/// File formats records can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	/// Pretty-printed JSON.
+	Json,
+	/// TOML, whose integers only go up to `i64::MAX`.
+	Toml,
+}
+
+impl Format {
+	/// Picks a format from a file extension, defaulting to JSON.
+	pub fn from_path(path: &Path) -> Self {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("toml") => Self::Toml,
+			_ => Self::Json,
+		}
+	}
+
+	/// Writes a value in this format.
+	pub fn serialise<T: Serialize>(self, value: &T) -> Result<String, SaveError> {
+		Ok(match self {
+			Self::Json => serde_json::to_string_pretty(value)?,
+			Self::Toml => toml::to_string(value)?,
+		})
+	}
+}
+
+/// Why a record couldn't be written.
+#[derive(Debug, Error)]
+pub enum SaveError {
+	/// The file couldn't be written.
+	#[error("writing file: {0}")]
+	Io(#[from] io::Error),
+
+	/// The record couldn't be serialised to JSON.
+	#[error("serialising to JSON: {0}")]
+	Json(#[from] serde_json::Error),
+
+	/// The record couldn't be serialised to TOML.
+	#[error("serialising to TOML: {0}")]
+	Toml(#[from] toml::ser::Error),
+}
+
+impl<const N: usize> RunRecord<N> {
+	/// Writes the record to a file, in the format given by its extension.
+	pub fn save(&self, path: &Path) -> Result<(), SaveError> {
+		let contents = Format::from_path(path).serialise(self)?;
+		fs::write(path, contents)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::RaySource;
+
+	fn record() -> RunRecord<10> {
+		let design = ParamSet::nth(987_654);
+		RunRecord {
+			config: Experiment::default(),
+			seed: 7,
+			best: Some(Evaluated {
+				design,
+				fitness: 42,
+				performance: crate::model::raytrace(design, RaySource::default()),
+				generation: 3,
+			}),
+			generations: vec![GenerationStats {
+				generation: 1,
+				average_fitness: 12,
+				best_fitness: 42,
+			}],
+			repairs: RepairStats::default(),
+			cache: CacheStats::default(),
+		}
+	}
+
+	#[test]
+	fn picks_format_from_extension() {
+		assert_eq!(Format::from_path(Path::new("run.toml")), Format::Toml);
+		assert_eq!(Format::from_path(Path::new("run.json")), Format::Json);
+		assert_eq!(Format::from_path(Path::new("run")), Format::Json);
+	}
+
+	#[test]
+	fn round_trips_in_both_formats() {
+		let record = record();
+		let expected = record.best.unwrap();
+		for format in [Format::Json, Format::Toml] {
+			let text = format.serialise(&record).unwrap();
+			let back: RunRecord<10> = match format {
+				Format::Json => serde_json::from_str(&text).unwrap(),
+				Format::Toml => toml::from_str(&text).unwrap(),
+			};
+			let best = back.best.unwrap();
+			assert_eq!(best.design, expected.design);
+			assert_eq!(best.fitness, expected.fitness);
+			assert_eq!(best.performance.exit_ratio, expected.performance.exit_ratio);
+			assert_eq!(best.performance.exit_angle, expected.performance.exit_angle);
+			assert_eq!(back.config, record.config);
+			assert_eq!(back.generations.len(), 1);
+		}
+	}
+}
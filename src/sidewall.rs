@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+use crate::model::profile::{Profile, KNOTS};
+
+/// Sidewall shape, encoded for the genome.
+///
+/// Each side of a lens has its own, going from its top corner to its bottom
+/// corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Sidewall {
+	/// Straight from corner to corner.
+	#[default]
+	Straight,
+
+	/// Curvature in 127ths, offset by 128: 128 is straight.
+	Parabolic(u8),
+
+	/// Inset fraction at each knot, in 255ths.
+	Piecewise([u8; KNOTS]),
+}
+
+impl Sidewall {
+	/// Number of genes used to encode a sidewall.
+	pub const GENES: usize = 1 + KNOTS;
+
+	const STRAIGHT_KNOTS: [u8; KNOTS] = [64, 128, 191];
+
+	/// Decodes from genes: a kind, then parameters.
+	///
+	/// The kind gene is spread over the whole range so mutations can move it.
+	pub fn from_genes(genes: [u8; Self::GENES]) -> Self {
+		let [kind, params @ ..] = genes;
+		match kind / 86 {
+			0 => Self::Straight,
+			1 => Self::Parabolic(params[0]),
+			_ => Self::Piecewise(params),
+		}
+	}
+
+	/// Encodes as genes, the inverse of [`from_genes`](Self::from_genes).
+	pub fn genes(self) -> [u8; Self::GENES] {
+		let (kind, params) = match self {
+			Self::Straight => (0, Self::STRAIGHT_KNOTS),
+			Self::Parabolic(curvature) => {
+				let mut params = Self::STRAIGHT_KNOTS;
+				params[0] = curvature;
+				(86, params)
+			}
+			Self::Piecewise(knots) => (172, knots),
+		};
+
+		let mut genes = [kind; Self::GENES];
+		genes[1..].copy_from_slice(&params);
+		genes
+	}
+}
+
+impl From<Sidewall> for Profile {
+	fn from(wall: Sidewall) -> Self {
+		match wall {
+			Sidewall::Straight => Self::Straight,
+			Sidewall::Parabolic(curvature) => Self::Parabolic {
+				curvature: ((f64::from(curvature) - 128.0) / 127.0).clamp(-1.0, 1.0),
+			},
+			Sidewall::Piecewise(knots) => Self::Piecewise {
+				knots: knots.map(|k| f64::from(k) / 255.0),
+			},
+		}
+	}
+}
+
+impl From<Profile> for Sidewall {
+	fn from(profile: Profile) -> Self {
+		match profile {
+			Profile::Straight => Self::Straight,
+			Profile::Parabolic { curvature } => {
+				Self::Parabolic((curvature.clamp(-1.0, 1.0) * 127.0 + 128.0).round() as _)
+			}
+			Profile::Piecewise { knots } => {
+				Self::Piecewise(knots.map(|k| (k.clamp(0.0, 1.0) * 255.0).round() as _))
+			}
+		}
+	}
+}
+
+impl Display for Sidewall {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match Profile::from(*self) {
+			Profile::Straight => write!(f, "straight"),
+			Profile::Parabolic { curvature } => write!(f, "parabolic({curvature:.03})"),
+			Profile::Piecewise { knots } => {
+				write!(f, "piecewise(")?;
+				for (n, knot) in knots.iter().enumerate() {
+					if n > 0 {
+						write!(f, ",")?;
+					}
+					write!(f, "{knot:.03}")?;
+				}
+				write!(f, ")")
+			}
+		}
+	}
+}
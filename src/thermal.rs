@@ -0,0 +1,160 @@
+use serde::Serialize;
+
+use crate::{
+	catalog::Catalog,
+	model::{raytrace_profile, slabs, units::Microns, Performance, RaySource, Slab},
+	paramset::ParamSet,
+};
+
+/// Temperature that designs and catalogs give RIs and thicknesses at, in °C.
+pub const REFERENCE_TEMPERATURE: f64 = 20.0;
+
+/// Change of RI with temperature for liquids that don't give one, per K:
+/// about that of water.
+pub const DEFAULT_THERMO_OPTIC: f64 = -1e-4;
+
+/// Volumetric expansion for liquids that don't give one, per K: about that
+/// of water.
+pub const DEFAULT_EXPANSION: f64 = 2e-4;
+
+/// Slabs of a design at an operating temperature, from the top down.
+///
+/// Each layer's RI changes by the thermo-optic coefficient of its liquid, and
+/// its thickness by its expansion, taking the walls to hold the width. The
+/// stack rests on the bottom face, so its top rises as layers expand, and the
+/// walls are taken to stretch with it, keeping the widths of both faces.
+/// Partitions are taken not to change.
+pub fn slabs_at<const N: usize>(
+	params: ParamSet<N>,
+	catalog: Option<&Catalog>,
+	temperature: f64,
+) -> Vec<Slab> {
+	let change = temperature - REFERENCE_TEMPERATURE;
+
+	let mut slabs = slabs(params, params.outline());
+	let materials = catalog
+		.map(|c| c.slab_materials(params, &slabs))
+		.unwrap_or_default();
+	let mut bottom = slabs.last().map_or(0, |slab| slab.bottom);
+	for (n, slab) in slabs.iter_mut().enumerate().rev() {
+		let mut thickness = (slab.top - slab.bottom) as f64;
+		if !slab.partition {
+			let material = materials.get(n).copied().flatten();
+			let thermo_optic = material
+				.and_then(|m| m.thermo_optic)
+				.unwrap_or(DEFAULT_THERMO_OPTIC);
+			let expansion = material
+				.and_then(|m| m.expansion)
+				.unwrap_or(DEFAULT_EXPANSION);
+			slab.ri += thermo_optic * change;
+			thickness *= (1.0 + expansion * change).max(0.0);
+		}
+		slab.bottom = bottom;
+		slab.top = bottom + thickness.round() as Microns;
+		bottom = slab.top;
+	}
+	slabs
+}
+
+/// Like [`raytrace`](crate::model::raytrace), but at an operating temperature
+/// in °C. See [`slabs_at`].
+pub fn raytrace_at<const N: usize>(
+	params: ParamSet<N>,
+	catalog: Option<&Catalog>,
+	temperature: f64,
+	rays: RaySource,
+) -> Performance {
+	raytrace_profile(params, &slabs_at(params, catalog, temperature), rays)
+}
+
+/// Performance of a design at one temperature.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct OperatingPoint {
+	/// In °C.
+	pub temperature: f64,
+
+	/// How it performs, from the stack at that temperature.
+	pub performance: Performance,
+
+	/// Height of the stack, in mm.
+	pub height: f64,
+}
+
+/// Performance of a design across temperatures, in °C.
+pub fn operating_range<const N: usize>(
+	params: ParamSet<N>,
+	catalog: Option<&Catalog>,
+	temperatures: impl IntoIterator<Item = f64>,
+	rays: RaySource,
+) -> Vec<OperatingPoint> {
+	temperatures
+		.into_iter()
+		.map(|temperature| {
+			let slabs = slabs_at(params, catalog, temperature);
+			OperatingPoint {
+				temperature,
+				performance: raytrace_profile(params, &slabs, rays),
+				height: slabs.first().map_or(0.0, |slab| slab.top as f64 / 1000.0),
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::catalog::Material;
+
+	const DESIGN: &str = "top:50mm bottom:50mm layer:3.00mm part:1.00mm | 1.40#1 1.45 |";
+
+	fn catalog() -> Catalog {
+		Catalog {
+			materials: vec![Material {
+				name: "glycerol-60".into(),
+				ri: 1.40,
+				density: 1.15,
+				cost: 0.0,
+				absorption: 0.0,
+				miscibility: None,
+				diffusivity: None,
+				thermo_optic: Some(-2e-4),
+				expansion: Some(1e-3),
+			}],
+		}
+	}
+
+	#[test]
+	fn leaves_the_stack_at_the_reference_temperature() {
+		let params: ParamSet = DESIGN.parse().unwrap();
+		let nominal = slabs(params, params.outline());
+		let at = slabs_at(params, Some(&catalog()), REFERENCE_TEMPERATURE);
+		assert_eq!(at.len(), nominal.len());
+		for (at, nominal) in at.iter().zip(&nominal) {
+			assert_eq!((at.top, at.bottom), (nominal.top, nominal.bottom));
+			assert_eq!(at.ri, nominal.ri);
+		}
+	}
+
+	#[test]
+	fn expands_and_shifts_each_layer_by_its_liquid() {
+		let params: ParamSet = DESIGN.parse().unwrap();
+		let nominal = slabs(params, params.outline());
+		let hot = slabs_at(params, Some(&catalog()), REFERENCE_TEMPERATURE + 10.0);
+		let thickness = |slab: &Slab| slab.top - slab.bottom;
+
+		// partition, the catalog's liquid, partition, a liquid of defaults
+		let [part, listed, _, unlisted] = [0, 1, 2, 3].map(|n| (hot[n], nominal[n]));
+		assert_eq!(thickness(&part.0), thickness(&part.1));
+		assert_eq!(part.0.ri, part.1.ri);
+		assert!((listed.0.ri - (listed.1.ri - 2e-3)).abs() < 1e-12);
+		assert_eq!(thickness(&listed.0), thickness(&listed.1) * 101 / 100);
+		let expected = unlisted.1.ri + DEFAULT_THERMO_OPTIC * 10.0;
+		assert!((unlisted.0.ri - expected).abs() < 1e-12);
+		assert_eq!(thickness(&unlisted.0), thickness(&unlisted.1) * 1002 / 1000);
+
+		// resting on the bottom face, with nothing between the slabs
+		assert_eq!(hot.last().unwrap().bottom, nominal.last().unwrap().bottom);
+		assert!(hot.windows(2).all(|pair| pair[0].bottom == pair[1].top));
+		assert!(hot[0].top > nominal[0].top);
+	}
+}